@@ -0,0 +1,22 @@
+-- Price threshold watches fire once base + premium drops below a target
+-- while the name is in the premium auction.
+
+CREATE TYPE watch_kind AS ENUM ('available', 'price_below');
+CREATE TYPE price_currency AS ENUM ('usd', 'eth');
+
+ALTER TABLE watch_list
+ADD COLUMN kind watch_kind NOT NULL DEFAULT 'available',
+ADD COLUMN threshold DOUBLE PRECISION,
+ADD COLUMN currency price_currency,
+ADD COLUMN trigger_at timestamptz;
+
+-- a user can have one watch of each kind per name
+DROP INDEX unique_active_watch;
+CREATE UNIQUE INDEX unique_active_watch
+ON watch_list (user_id, name, kind)
+WHERE status = 'active';
+
+-- the sweeper only looks at active watches that are due
+CREATE INDEX watch_list_trigger_at
+ON watch_list (trigger_at)
+WHERE status = 'active';
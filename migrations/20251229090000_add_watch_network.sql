@@ -0,0 +1,9 @@
+-- Watches belong to a network now that Coco serves more than mainnet.
+
+ALTER TABLE watch_list
+ADD COLUMN network VARCHAR NOT NULL DEFAULT 'mainnet';
+
+DROP INDEX unique_active_watch;
+CREATE UNIQUE INDEX unique_active_watch
+ON watch_list (user_id, network, name, kind)
+WHERE status = 'active';
@@ -0,0 +1,13 @@
+-- Shared second level for the in-process response cache, one row per
+-- (network, field, name) holding the newest block it was read at.
+
+CREATE TABLE response_cache (
+    network VARCHAR NOT NULL,
+    field VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    value JSONB NOT NULL,
+    cached_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (network, field, name)
+);
@@ -0,0 +1,88 @@
+-- Local copy of ENS name events, written by the coco-indexer binary.
+-- Hashes and addresses are raw bytes, wei amounts are NUMERIC since they
+-- can exceed BIGINT. Every event table is keyed by its log so re-indexing a
+-- range is a no-op.
+
+CREATE TABLE indexer_checkpoints (
+    network VARCHAR PRIMARY KEY,
+    block_number BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE name_registrations (
+    network VARCHAR NOT NULL,
+    label_hash BYTEA NOT NULL,
+    -- only known when the controller event is seen in the same transaction
+    label VARCHAR,
+    owner BYTEA NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    base_cost NUMERIC(78, 0),
+    premium NUMERIC(78, 0),
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (network, tx_hash, log_index)
+);
+
+CREATE INDEX name_registrations_label_hash_idx ON name_registrations (network, label_hash);
+CREATE INDEX name_registrations_block_idx ON name_registrations (network, block_number);
+
+CREATE TABLE name_renewals (
+    network VARCHAR NOT NULL,
+    label_hash BYTEA NOT NULL,
+    label VARCHAR,
+    expires_at TIMESTAMPTZ NOT NULL,
+    cost NUMERIC(78, 0),
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (network, tx_hash, log_index)
+);
+
+CREATE INDEX name_renewals_label_hash_idx ON name_renewals (network, label_hash);
+
+CREATE TABLE name_transfers (
+    network VARCHAR NOT NULL,
+    label_hash BYTEA NOT NULL,
+    from_address BYTEA NOT NULL,
+    to_address BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (network, tx_hash, log_index)
+);
+
+CREATE INDEX name_transfers_label_hash_idx ON name_transfers (network, label_hash);
+CREATE INDEX name_transfers_to_idx ON name_transfers (network, to_address);
+
+CREATE TABLE wrapper_events (
+    network VARCHAR NOT NULL,
+    node BYTEA NOT NULL,
+    kind VARCHAR NOT NULL,
+    name VARCHAR,
+    owner BYTEA,
+    fuses BIGINT,
+    expires_at TIMESTAMPTZ,
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (network, tx_hash, log_index)
+);
+
+CREATE INDEX wrapper_events_node_idx ON wrapper_events (network, node);
+
+CREATE TABLE registry_events (
+    network VARCHAR NOT NULL,
+    node BYTEA NOT NULL,
+    kind VARCHAR NOT NULL,
+    label_hash BYTEA,
+    owner BYTEA,
+    resolver BYTEA,
+    ttl BIGINT,
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (network, tx_hash, log_index)
+);
+
+CREATE INDEX registry_events_node_idx ON registry_events (network, node);
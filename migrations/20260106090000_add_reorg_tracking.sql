@@ -0,0 +1,58 @@
+-- Reorg safety for the indexer. Block hashes are kept for every indexed
+-- block that had events plus each checkpoint, so a parent hash mismatch can
+-- be walked back to the common ancestor. Watch updates derived from events
+-- keep the values they replaced so a rollback can restore them.
+
+ALTER TABLE indexer_checkpoints
+ADD COLUMN block_hash BYTEA;
+
+CREATE TABLE indexed_blocks (
+    network VARCHAR NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    PRIMARY KEY (network, block_number)
+);
+
+-- lets registrar events, which only carry the label hash, find watches
+ALTER TABLE watch_list
+ADD COLUMN label_hash BYTEA;
+
+CREATE INDEX watch_list_label_hash
+ON watch_list (network, label_hash)
+WHERE status = 'active';
+
+CREATE TABLE watch_changes (
+    watch_id UUID NOT NULL REFERENCES watch_list (id) ON DELETE CASCADE,
+    network VARCHAR NOT NULL,
+    previous_expires_at TIMESTAMPTZ,
+    previous_trigger_at TIMESTAMPTZ,
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    PRIMARY KEY (watch_id, tx_hash, log_index)
+);
+
+CREATE INDEX watch_changes_block_idx ON watch_changes (network, block_number);
+
+CREATE TYPE watch_notification_kind AS ENUM ('registered', 'renewed', 'transferred');
+
+-- released once the event is `confirmations` blocks deep, the bot only
+-- delivers released rows. A reorg deletes unreleased rows and marks
+-- released ones retracted.
+CREATE TABLE watch_notifications (
+    id BIGSERIAL PRIMARY KEY,
+    watch_id UUID NOT NULL REFERENCES watch_list (id) ON DELETE CASCADE,
+    network VARCHAR NOT NULL,
+    kind watch_notification_kind NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    released_at TIMESTAMPTZ,
+    retracted_at TIMESTAMPTZ,
+    UNIQUE (watch_id, tx_hash, log_index)
+);
+
+CREATE INDEX watch_notifications_pending_idx
+ON watch_notifications (network, block_number)
+WHERE released_at IS NULL;
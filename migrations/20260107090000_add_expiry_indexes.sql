@@ -0,0 +1,4 @@
+-- The expiring and released feeds look names up by expiry.
+
+CREATE INDEX name_registrations_expires_idx ON name_registrations (network, expires_at);
+CREATE INDEX name_renewals_expires_idx ON name_renewals (network, expires_at);
@@ -0,0 +1,5 @@
+-- Block time of each registration, for the registration duration. Rows
+-- indexed before this have no value.
+
+ALTER TABLE name_registrations
+ADD COLUMN registered_at TIMESTAMPTZ;
@@ -0,0 +1,28 @@
+-- Known preimages of label hashes, so events and token ids can be shown as
+-- names. A label hash is the same on every network, so there is no network
+-- column. The first source to record a label keeps it.
+
+CREATE TYPE label_source AS ENUM ('event', 'wordlist', 'query');
+
+CREATE TABLE labels (
+    label_hash BYTEA PRIMARY KEY,
+    label VARCHAR NOT NULL,
+    source label_source NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO labels (label_hash, label, source)
+SELECT DISTINCT ON (label_hash) label_hash, label, 'event'::label_source
+FROM (
+    SELECT label_hash, label FROM name_registrations WHERE label IS NOT NULL
+    UNION ALL
+    SELECT label_hash, label FROM name_renewals WHERE label IS NOT NULL
+) seen
+ON CONFLICT DO NOTHING;
+
+-- watched names were queried through coco too
+INSERT INTO labels (label_hash, label, source)
+SELECT DISTINCT ON (label_hash) label_hash, split_part(name, '.', 1), 'query'::label_source
+FROM watch_list
+WHERE label_hash IS NOT NULL
+ON CONFLICT DO NOTHING;
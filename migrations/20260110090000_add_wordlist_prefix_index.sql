@@ -0,0 +1,2 @@
+-- Name suggestions look up wordlist labels starting with the queried label.
+CREATE INDEX labels_wordlist_prefix_idx ON labels (label text_pattern_ops) WHERE source = 'wordlist';
@@ -0,0 +1,35 @@
+-- Pattern scans, expanded into labels and checked in the background by the
+-- scanner. `checked` counts labels in expansion order, so a scan picked up
+-- again after a restart carries on where it stopped.
+
+CREATE TYPE scan_status AS ENUM ('pending', 'running', 'completed', 'failed');
+
+CREATE TABLE scans (
+    id UUID PRIMARY KEY,
+    network VARCHAR NOT NULL,
+    pattern VARCHAR NOT NULL,
+    palindrome BOOLEAN NOT NULL,
+    status scan_status NOT NULL,
+    total BIGINT NOT NULL,
+    checked BIGINT NOT NULL DEFAULT 0,
+    available BIGINT NOT NULL DEFAULT 0,
+    error VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX scans_queued_idx ON scans (created_at) WHERE status IN ('pending', 'running');
+
+CREATE TABLE scan_results (
+    scan_id UUID NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+    -- place in the pattern's expansion, results download in this order
+    position BIGINT NOT NULL,
+    name VARCHAR NOT NULL,
+    available BOOLEAN NOT NULL,
+    owner BYTEA,
+    expires_at TIMESTAMPTZ,
+    base_cost NUMERIC(78, 0),
+    premium NUMERIC(78, 0),
+    PRIMARY KEY (scan_id, position)
+);
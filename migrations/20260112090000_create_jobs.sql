@@ -0,0 +1,39 @@
+-- Batch availability checks over an uploaded name list, worked through in
+-- chunks by the job worker. Like scans, `checked` counts names in upload
+-- order so an interrupted job carries on where it stopped.
+
+CREATE TYPE job_status AS ENUM ('pending', 'running', 'completed', 'failed');
+
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY,
+    network VARCHAR NOT NULL,
+    status job_status NOT NULL,
+    total BIGINT NOT NULL,
+    checked BIGINT NOT NULL DEFAULT 0,
+    available BIGINT NOT NULL DEFAULT 0,
+    error VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX jobs_queued_idx ON jobs (created_at) WHERE status IN ('pending', 'running');
+
+CREATE TABLE job_names (
+    job_id UUID NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+    position BIGINT NOT NULL,
+    name VARCHAR NOT NULL,
+    PRIMARY KEY (job_id, position)
+);
+
+CREATE TABLE job_results (
+    job_id UUID NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+    position BIGINT NOT NULL,
+    name VARCHAR NOT NULL,
+    available BOOLEAN NOT NULL,
+    owner BYTEA,
+    expires_at TIMESTAMPTZ,
+    base_cost NUMERIC(78, 0),
+    premium NUMERIC(78, 0),
+    PRIMARY KEY (job_id, position)
+);
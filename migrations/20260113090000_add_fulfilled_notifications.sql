@@ -0,0 +1,9 @@
+-- The sweeper queues a notification when a watch is fulfilled. Those come
+-- from reading the chain rather than from a log, so they have no tx hash or
+-- log index.
+
+ALTER TYPE watch_notification_kind ADD VALUE 'fulfilled';
+
+ALTER TABLE watch_notifications
+ALTER COLUMN tx_hash DROP NOT NULL,
+ALTER COLUMN log_index DROP NOT NULL;
@@ -0,0 +1,13 @@
+-- Every watch has a label hash once coco-watch-hashes has filled in the ones
+-- created before 20260106090000, which registrar events need to find them.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM watch_list WHERE label_hash IS NULL) THEN
+        RAISE EXCEPTION 'watch_list has rows without a label_hash, run coco-watch-hashes first';
+    END IF;
+END
+$$;
+
+ALTER TABLE watch_list
+ALTER COLUMN label_hash SET NOT NULL;
@@ -0,0 +1,43 @@
+use coco::{
+    config,
+    services::{
+        avatar::metadata_client, indexer::run_indexer, labels::LabelQueue,
+        provider_pool::run_health_checks, registrations::registration_notices,
+    },
+    startup::{connect_networks, create_pool},
+    types::api::AppState,
+};
+use std::sync::Arc;
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    dotenvy::dotenv().ok();
+
+    let config = config::Config::load_env()?;
+
+    // SQLx - Postgres Pool
+    let connection = create_pool(&config.database).await?;
+
+    // Alloy - one provider and address set per configured network
+    let networks = connect_networks(&config, &connection).await?;
+
+    let http = metadata_client(&config.avatar);
+    let app_state = Arc::new(AppState {
+        app_config: config,
+        connection,
+        networks,
+        http,
+        registrations: registration_notices(),
+        // the indexer saves event labels itself
+        labels: LabelQueue::new().0,
+    });
+
+    info!("Indexing {} networks", app_state.networks.len());
+
+    tokio::spawn(run_health_checks(app_state.clone()));
+    run_indexer(app_state).await;
+
+    Ok(())
+}
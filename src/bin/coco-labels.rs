@@ -0,0 +1,39 @@
+use coco::{
+    config,
+    services::labels::{save_labels, wordlist_labels},
+    startup::create_pool,
+    types::api::LabelSource,
+};
+use tracing::info;
+
+// labels per insert, keeps the bind arrays a sensible size
+const CHUNK_SIZE: usize = 10_000;
+
+// usage: coco-labels <wordlist>...
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    dotenvy::dotenv().ok();
+
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        return Err("usage: coco-labels <wordlist>...".into());
+    }
+
+    let config = config::Config::load_env()?;
+
+    // SQLx - Postgres Pool
+    let connection = create_pool(&config.database).await?;
+
+    for path in paths {
+        let labels = wordlist_labels(&std::fs::read_to_string(&path)?);
+
+        let mut added = 0;
+        for chunk in labels.chunks(CHUNK_SIZE) {
+            added += save_labels(&connection, chunk, LabelSource::Wordlist).await?;
+        }
+        info!("{}: {} labels, {} new", path, labels.len(), added);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use coco::{config, services::ens::normalise_and_hash_name_for, startup::create_pool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+// fills in label hashes for watches created before they were stored, run it
+// before the migration that makes them required
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    dotenvy::dotenv().ok();
+
+    let config = config::Config::load_env()?;
+
+    // SQLx - Postgres Pool
+    let connection = create_pool(&config.database).await?;
+
+    let mut missing = 0;
+    for network in &config.networks {
+        let watches: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT id, name FROM watch_list WHERE network = $1 AND label_hash IS NULL",
+        )
+        .bind(&network.name)
+        .fetch_all(&connection)
+        .await?;
+
+        let mut hashed = 0;
+        for (id, name) in watches {
+            match normalise_and_hash_name_for(&name, &network.tld) {
+                Ok(normalised) => {
+                    sqlx::query("UPDATE watch_list SET label_hash = $2 WHERE id = $1")
+                        .bind(id)
+                        .bind(normalised.label_hash.as_slice())
+                        .execute(&connection)
+                        .await?;
+                    hashed += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "Can't hash watched name {} on {}: {}",
+                        name, network.name, e
+                    );
+                    missing += 1;
+                }
+            }
+        }
+        info!("{}: {} watch label hashes filled in", network.name, hashed);
+    }
+
+    // includes watches on networks that are no longer configured
+    let remaining: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM watch_list WHERE label_hash IS NULL")
+            .fetch_one(&connection)
+            .await?;
+    if remaining > 0 {
+        return Err(format!(
+            "{} watches still have no label hash ({} failed to normalise)",
+            remaining, missing
+        )
+        .into());
+    }
+
+    Ok(())
+}
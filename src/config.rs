@@ -1,5 +1,11 @@
 use super::errors::ConfigError;
+use crate::types::ens::{EnsContractAddresses, RegistrarStorageLayout};
+use alloy::primitives::B256;
 use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+const DEFAULT_NETWORKS_CONFIG: &str = "config/networks.json";
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
@@ -7,9 +13,145 @@ pub struct Config {
     pub application_port: String,
     pub base_rpc: String,
     pub subgraph_url: String,
+    pub watch_sweep_interval_secs: u64,
+    // bearer token for admin routes, which are disabled when it's unset
+    pub admin_token: Option<String>,
+    pub multicall: MulticallConfig,
+    pub rpc_pool: RpcPoolConfig,
+    pub response_cache: ResponseCacheConfig,
+    pub indexer: IndexerConfig,
+    pub avatar: AvatarConfig,
+    pub scan: ScanConfig,
+    pub jobs: JobConfig,
+    pub default_network: String,
+    pub networks: Vec<NetworkConfig>,
     pub database: DBConfig,
 }
 
+// Limits for splitting large name lists across multicalls
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct MulticallConfig {
+    pub chunk_size: usize,
+    // chunks in flight per network, shared by every request
+    pub concurrency: usize,
+    // chunks in flight per network for batch scans and jobs, on top of `concurrency`
+    pub background_concurrency: usize,
+    // hard cap on names per request, anything above is a 413
+    pub max_names: usize,
+}
+
+// how the indexer walks each network's registrar, controller and wrapper logs
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct IndexerConfig {
+    // eth_getLogs range per request, halved when a provider rejects it
+    pub batch_blocks: u64,
+    // how often to look for new blocks once caught up
+    pub poll_interval_secs: u64,
+    // blocks a derived notification waits for before it's released
+    pub confirmations: u64,
+}
+
+// where ENSIP-12 avatars and their NFT metadata are fetched from
+#[derive(Deserialize, Debug, Clone)]
+pub struct AvatarConfig {
+    // ipfs:// and ipns:// URIs are rewritten to <gateway>/ipfs/<cid>
+    pub ipfs_gateway: String,
+    pub arweave_gateway: String,
+    pub metadata_timeout_secs: u64,
+}
+
+// background pattern scans
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct ScanConfig {
+    // most labels one pattern may expand to
+    pub max_labels: usize,
+    // names checked between progress updates
+    pub chunk_size: usize,
+    // how often the scanner looks for queued scans
+    pub poll_interval_secs: u64,
+}
+
+// background batch checks over uploaded name lists
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct JobConfig {
+    pub max_names: usize,
+    pub chunk_size: usize,
+    pub poll_interval_secs: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct ResponseCacheConfig {
+    // upper bound on entry age, a new block invalidates them sooner
+    pub ttl_secs: u64,
+    // share entries between instances through the response_cache table
+    pub postgres: bool,
+}
+
+// Failover and circuit breaker settings shared by every network's provider pool
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct RpcPoolConfig {
+    // consecutive transport failures before an endpoint's circuit opens
+    pub failure_threshold: u32,
+    pub circuit_cooldown_secs: u64,
+    pub health_check_interval_secs: u64,
+    pub health_check_timeout_secs: u64,
+    // providers cross-checked by consistency=quorum reads
+    pub quorum_size: usize,
+    // per attempt, a slow endpoint counts as a transport failure. Time spent
+    // queued for multicall permits isn't counted
+    pub request_timeout_secs: u64,
+    // extra passes over the endpoints after every one of them failed
+    pub max_retries: u32,
+    // backoff before retry n is a random delay up to base * 2^(n-1)
+    pub retry_base_delay_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NetworkConfig {
+    pub name: String,
+    // tried in order of health and latency, see services::provider_pool
+    pub rpc_urls: Vec<String>,
+    // suffix names are registered under, eg "eth" or "base.eth"
+    pub tld: String,
+    pub contracts: EnsContractAddresses,
+    // expected time between blocks, used for Cache-Control max-age
+    pub block_time_secs: u64,
+    // where the indexer starts when it has no checkpoint yet
+    pub index_from_block: u64,
+    // unset on networks whose registrar layout we haven't checked
+    pub registrar_layout: Option<RegistrarStorageLayout>,
+    // block that proof verification trusts by default
+    pub trusted_block_hash: Option<B256>,
+}
+
+// entry as written in the networks file, the rpc can come from an env var
+// so keys stay out of the file
+#[derive(Deserialize)]
+struct NetworkFileEntry {
+    name: String,
+    rpc_url: Option<String>,
+    rpc_env: Option<String>,
+    #[serde(default = "default_tld")]
+    tld: String,
+    contracts: EnsContractAddresses,
+    #[serde(default = "default_block_time_secs")]
+    block_time_secs: u64,
+    #[serde(default)]
+    index_from_block: u64,
+    #[serde(default)]
+    registrar_layout: Option<RegistrarStorageLayout>,
+    #[serde(default)]
+    trusted_block_hash: Option<B256>,
+}
+
+fn default_tld() -> String {
+    "eth".to_string()
+}
+
+fn default_block_time_secs() -> u64 {
+    12
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct DBConfig {
     pub username: String,
@@ -21,15 +163,65 @@ pub struct DBConfig {
 
 impl Config {
     pub fn load_env() -> Result<Self, ConfigError> {
+        let eth_rpc =
+            std::env::var("ETH_RPC").map_err(|_| ConfigError::MissingEnv("ETH_RPC".to_string()))?;
+
         Ok(Self {
-            eth_rpc: std::env::var("ETH_RPC")
-                .map_err(|_| ConfigError::MissingEnv("ETH_RPC".to_string()))?,
+            networks: load_networks(&eth_rpc)?,
+            default_network: std::env::var("DEFAULT_NETWORK")
+                .unwrap_or_else(|_| "mainnet".to_string()),
+            eth_rpc,
             base_rpc: std::env::var("BASE_RPC")
                 .map_err(|_| ConfigError::MissingEnv("BASE_RPC".to_string()))?,
             subgraph_url: std::env::var("SUBGRAPH_URL")
                 .map_err(|_| ConfigError::MissingEnv("SUBGRAPH_URL".to_string()))?,
             application_port: std::env::var("APP_PORT")
                 .map_err(|_| ConfigError::MissingEnv("APP_PORT".to_string()))?,
+            watch_sweep_interval_secs: std::env::var("WATCH_SWEEP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            admin_token: std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            rpc_pool: RpcPoolConfig {
+                failure_threshold: env_or("RPC_FAILURE_THRESHOLD", 3),
+                circuit_cooldown_secs: env_or("RPC_CIRCUIT_COOLDOWN_SECS", 30),
+                health_check_interval_secs: env_or("RPC_HEALTH_CHECK_INTERVAL_SECS", 15),
+                health_check_timeout_secs: env_or("RPC_HEALTH_CHECK_TIMEOUT_SECS", 5),
+                quorum_size: env_or("RPC_QUORUM_SIZE", 2),
+                request_timeout_secs: env_or("RPC_REQUEST_TIMEOUT_SECS", 10),
+                max_retries: env_or("RPC_MAX_RETRIES", 2),
+                retry_base_delay_ms: env_or("RPC_RETRY_BASE_DELAY_MS", 200),
+            },
+            response_cache: ResponseCacheConfig {
+                ttl_secs: env_or("RESPONSE_CACHE_TTL_SECS", 60),
+                postgres: env_or("RESPONSE_CACHE_POSTGRES", false),
+            },
+            indexer: IndexerConfig {
+                batch_blocks: env_or("INDEXER_BATCH_BLOCKS", 2_000),
+                poll_interval_secs: env_or("INDEXER_POLL_INTERVAL_SECS", 12),
+                confirmations: env_or("INDEXER_CONFIRMATIONS", 12),
+            },
+            avatar: AvatarConfig {
+                ipfs_gateway: env_or("IPFS_GATEWAY", "https://ipfs.io".to_string()),
+                arweave_gateway: env_or("ARWEAVE_GATEWAY", "https://arweave.net".to_string()),
+                metadata_timeout_secs: env_or("AVATAR_METADATA_TIMEOUT_SECS", 10),
+            },
+            scan: ScanConfig {
+                max_labels: env_or("SCAN_MAX_LABELS", 100_000),
+                chunk_size: env_or("SCAN_CHUNK_SIZE", 500),
+                poll_interval_secs: env_or("SCAN_POLL_INTERVAL_SECS", 5),
+            },
+            jobs: JobConfig {
+                max_names: env_or("JOB_MAX_NAMES", 100_000),
+                chunk_size: env_or("JOB_CHUNK_SIZE", 500),
+                poll_interval_secs: env_or("JOB_POLL_INTERVAL_SECS", 5),
+            },
+            multicall: MulticallConfig {
+                chunk_size: env_or("MULTICALL_CHUNK_SIZE", 100),
+                concurrency: env_or("MULTICALL_CONCURRENCY", 4),
+                background_concurrency: env_or("MULTICALL_BACKGROUND_CONCURRENCY", 2),
+                max_names: env_or("MAX_NAMES_PER_REQUEST", 1_000),
+            },
             database: DBConfig {
                 username: std::env::var("DB_USERNAME").unwrap_or_else(|_| {
                     tracing::warn!("Using default username for db");
@@ -66,3 +258,84 @@ impl DBConfig {
         )
     }
 }
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+// Reads the network registry from NETWORKS_CONFIG (or config/networks.json).
+// Without a file we fall back to mainnet on ETH_RPC.
+fn load_networks(eth_rpc: &str) -> Result<Vec<NetworkConfig>, ConfigError> {
+    let path = match std::env::var("NETWORKS_CONFIG") {
+        Ok(path) => path,
+        Err(_) if Path::new(DEFAULT_NETWORKS_CONFIG).exists() => {
+            DEFAULT_NETWORKS_CONFIG.to_string()
+        }
+        Err(_) => {
+            tracing::warn!("No networks config found, using mainnet only");
+            return Ok(vec![NetworkConfig {
+                name: "mainnet".to_string(),
+                rpc_urls: split_urls(eth_rpc),
+                tld: default_tld(),
+                block_time_secs: default_block_time_secs(),
+                // ENS registry deployment
+                index_from_block: 9_380_380,
+                contracts: EnsContractAddresses::mainnet(),
+                registrar_layout: Some(RegistrarStorageLayout::mainnet()),
+                trusted_block_hash: std::env::var("TRUSTED_BLOCK_HASH")
+                    .ok()
+                    .and_then(|hash| hash.parse().ok()),
+            }]);
+        }
+    };
+
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|e| ConfigError::InvalidNetworks(format!("{}: {}", path, e)))?;
+    let entries: Vec<NetworkFileEntry> = serde_json::from_str(&raw)
+        .map_err(|e| ConfigError::InvalidNetworks(format!("{}: {}", path, e)))?;
+
+    let mut networks = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let rpc_url = match (entry.rpc_url, entry.rpc_env) {
+            (Some(url), _) => url,
+            (None, Some(env)) => match std::env::var(&env) {
+                Ok(url) => url,
+                Err(_) => {
+                    // networks without an rpc configured are skipped, not fatal
+                    tracing::warn!("Skipping network {}: {} is not set", entry.name, env);
+                    continue;
+                }
+            },
+            (None, None) => {
+                return Err(ConfigError::InvalidNetworks(format!(
+                    "network {} needs rpc_url or rpc_env",
+                    entry.name
+                )));
+            }
+        };
+
+        networks.push(NetworkConfig {
+            name: entry.name,
+            rpc_urls: split_urls(&rpc_url),
+            tld: entry.tld,
+            block_time_secs: entry.block_time_secs,
+            index_from_block: entry.index_from_block,
+            contracts: entry.contracts,
+            registrar_layout: entry.registrar_layout,
+            trusted_block_hash: entry.trusted_block_hash,
+        });
+    }
+
+    Ok(networks)
+}
+
+// ETH_RPC and friends take a comma separated list of endpoints
+fn split_urls(urls: &str) -> Vec<String> {
+    urls.split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
@@ -1,14 +1,21 @@
 use actix_web::http::StatusCode;
+use alloy::hex;
 use alloy::providers::MulticallError;
-use serde::{Deserialize, Serialize};
+use alloy::sol_types::{SolInterface, decode_revert_reason};
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::types::api::ApiError;
+use crate::types::api::{ApiError, QuorumResult};
+use crate::types::ens::{ControllerReverts, RegistrarReverts};
 
 #[derive(Debug, Error, Clone)]
 pub enum ConfigError {
     #[error("Missing environment variables: {0}")]
     MissingEnv(String),
+
+    #[error("Invalid networks config: {0}")]
+    InvalidNetworks(String),
 }
 
 #[derive(Debug, Error)]
@@ -16,51 +23,469 @@ pub enum CocoError {
     #[error("Invalid query input")]
     InvalidQueryInput,
 
+    #[error("Admin token missing or wrong")]
+    Unauthorized,
+
     #[error("Couldn't normalise the name: {0}")]
     InvalidName(String),
 
     #[error("Invalid Ethereum address")]
     InvalidAddress,
 
+    #[error("Unknown network: {0}")]
+    UnknownNetwork(String),
+
+    #[error("Too many names: {requested} requested, limit is {limit}")]
+    TooManyNames { requested: usize, limit: usize },
+
+    #[error("Invalid watch request: {0}")]
+    InvalidWatch(String),
+
     #[error("Something went wrong during the ENS multicall process")]
     Ens(#[source] MulticallError),
+
+    #[error("RPC request failed")]
+    Rpc(#[source] TransportError),
+
+    #[error("RPC request timed out")]
+    RpcTimeout,
+
+    #[error("RPC provider rate limited the request")]
+    RateLimited,
+
+    #[error("Contract call reverted: {0}")]
+    Reverted(String),
+
+    #[error("No healthy RPC provider for {0}")]
+    NoHealthyProvider(String),
+
+    #[error("Block not found")]
+    BlockNotFound,
+
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
+
+    #[error("RPC provider has no state for the requested block")]
+    ArchiveRequired,
+
+    #[error("Quorum needs {required} healthy providers, only {available} available")]
+    QuorumUnavailable { required: usize, available: usize },
+
+    #[error("Providers disagree at block {block}")]
+    QuorumMismatch {
+        block: u64,
+        results: Vec<QuorumResult>,
+    },
+
+    #[error("Proof verification unavailable: {0}")]
+    ProofUnavailable(String),
+
+    #[error("Proof verification failed: {0}")]
+    InvalidProof(String),
+
+    #[error("Invalid contenthash: {0}")]
+    InvalidContenthash(String),
+
+    #[error("Couldn't resolve the avatar: {0}")]
+    AvatarUnavailable(String),
+
+    #[error("Unknown token: {0}")]
+    UnknownToken(String),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Unknown scan: {0}")]
+    ScanNotFound(Uuid),
+
+    #[error("Scan {0} hasn't finished")]
+    ScanNotFinished(Uuid),
+
+    #[error("Scan {0} failed: {1}")]
+    ScanFailed(Uuid, String),
+
+    #[error("Unknown job: {0}")]
+    JobNotFound(Uuid),
+
+    #[error("Job {0} hasn't finished")]
+    JobNotFinished(Uuid),
+
+    #[error("Job {0} failed: {1}")]
+    JobFailed(Uuid, String),
+
+    #[error("Invalid upload: {0}")]
+    InvalidUpload(String),
+
+    #[error("Contract call failed")]
+    Contract(#[source] alloy::contract::Error),
+
+    #[error("Something went wrong talking to the database")]
+    Database(#[source] sqlx::Error),
 }
 
 impl From<MulticallError> for CocoError {
     fn from(e: MulticallError) -> Self {
-        CocoError::Ens(e)
+        match e {
+            MulticallError::TransportError(e) => e.into(),
+            MulticallError::CallFailed(data) => CocoError::Reverted(describe_revert(&data)),
+            e => CocoError::Ens(e),
+        }
+    }
+}
+
+impl From<TransportError> for CocoError {
+    fn from(e: TransportError) -> Self {
+        match &e {
+            RpcError::ErrorResp(payload) if payload.is_retry_err() => CocoError::RateLimited,
+            RpcError::ErrorResp(payload)
+                if payload.as_revert_data().is_none() && is_missing_state(&payload.message) =>
+            {
+                CocoError::ArchiveRequired
+            }
+            RpcError::ErrorResp(payload) => match payload.as_revert_data() {
+                Some(data) => CocoError::Reverted(describe_revert(&data)),
+                None => CocoError::Rpc(e),
+            },
+            RpcError::Transport(TransportErrorKind::HttpError(http)) if http.status == 429 => {
+                CocoError::RateLimited
+            }
+            _ => CocoError::Rpc(e),
+        }
+    }
+}
+
+impl From<alloy::contract::Error> for CocoError {
+    fn from(e: alloy::contract::Error) -> Self {
+        match e {
+            alloy::contract::Error::TransportError(e) => e.into(),
+            e => CocoError::Contract(e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for CocoError {
+    fn from(e: sqlx::Error) -> Self {
+        CocoError::Database(e)
     }
 }
 
 impl CocoError {
+    /// Whether the request never got a proper answer from the node, so
+    /// retrying, possibly against another endpoint, could help.
+    pub fn is_transport_error(&self) -> bool {
+        match self {
+            CocoError::Rpc(e) => is_transport(e),
+            CocoError::RpcTimeout | CocoError::RateLimited | CocoError::BlockNotFound => true,
+            _ => false,
+        }
+    }
+
     pub fn status_code(&self) -> StatusCode {
         match self {
             CocoError::InvalidQueryInput => StatusCode::BAD_REQUEST,
+            CocoError::Unauthorized => StatusCode::UNAUTHORIZED,
             CocoError::InvalidName(_) => StatusCode::BAD_REQUEST,
             CocoError::InvalidAddress => StatusCode::BAD_REQUEST,
+            CocoError::UnknownNetwork(_) => StatusCode::BAD_REQUEST,
+            CocoError::TooManyNames { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            CocoError::InvalidWatch(_) => StatusCode::BAD_REQUEST,
             CocoError::Ens(_) => StatusCode::BAD_GATEWAY, // RPC and chain errors
+            CocoError::Rpc(_) => StatusCode::BAD_GATEWAY,
+            CocoError::RpcTimeout => StatusCode::GATEWAY_TIMEOUT,
+            CocoError::RateLimited => StatusCode::SERVICE_UNAVAILABLE,
+            CocoError::Reverted(_) => StatusCode::BAD_GATEWAY,
+            CocoError::NoHealthyProvider(_) => StatusCode::SERVICE_UNAVAILABLE,
+            CocoError::BlockNotFound => StatusCode::BAD_GATEWAY,
+            CocoError::InvalidBlock(_) => StatusCode::BAD_REQUEST,
+            CocoError::ArchiveRequired => StatusCode::NOT_IMPLEMENTED,
+            CocoError::QuorumUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            CocoError::QuorumMismatch { .. } => StatusCode::BAD_GATEWAY,
+            CocoError::ProofUnavailable(_) => StatusCode::BAD_REQUEST,
+            CocoError::InvalidProof(_) => StatusCode::BAD_GATEWAY,
+            CocoError::InvalidContenthash(_) => StatusCode::BAD_REQUEST,
+            CocoError::AvatarUnavailable(_) => StatusCode::BAD_GATEWAY,
+            CocoError::UnknownToken(_) => StatusCode::NOT_FOUND,
+            CocoError::InvalidPattern(_) => StatusCode::BAD_REQUEST,
+            CocoError::ScanNotFound(_) => StatusCode::NOT_FOUND,
+            CocoError::ScanNotFinished(_) => StatusCode::CONFLICT,
+            CocoError::ScanFailed(..) => StatusCode::CONFLICT,
+            CocoError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            CocoError::JobNotFinished(_) => StatusCode::CONFLICT,
+            CocoError::JobFailed(..) => StatusCode::CONFLICT,
+            CocoError::InvalidUpload(_) => StatusCode::BAD_REQUEST,
+            CocoError::Contract(_) => StatusCode::BAD_GATEWAY,
+            CocoError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     pub fn to_api_error(&self) -> ApiError {
         match self {
-            CocoError::InvalidQueryInput => Api {
+            CocoError::InvalidQueryInput => ApiError {
                 code: "invalid_query_input",
                 message: "Input is malformed".to_string(),
-            }
+                details: None,
+            },
+            CocoError::Unauthorized => ApiError {
+                code: "unauthorized",
+                message: "This endpoint needs the admin token as a bearer token".to_string(),
+                details: None,
+            },
             CocoError::InvalidName(msg) => ApiError {
                 code: "invalid_name",
                 message: msg.clone(),
+                details: None,
             },
             CocoError::InvalidAddress => ApiError {
                 code: "invalid_address",
                 message: "Wallet address provided is not valid".to_string(),
+                details: None,
+            },
+            CocoError::UnknownNetwork(name) => ApiError {
+                code: "unknown_network",
+                message: format!("Network {} is not configured", name),
+                details: None,
+            },
+            CocoError::TooManyNames { requested, limit } => ApiError {
+                code: "too_many_names",
+                message: format!(
+                    "{} names requested, at most {} are allowed per request",
+                    requested, limit
+                ),
+                details: None,
+            },
+            CocoError::InvalidWatch(msg) => ApiError {
+                code: "invalid_watch",
+                message: msg.clone(),
+                details: None,
             },
 
             CocoError::Ens(_) => ApiError {
-                code: "ens_eeor",
+                code: "ens_error",
                 message: "ENS lookup failed".to_string(),
+                details: None,
+            },
+            CocoError::Rpc(_) => ApiError {
+                code: "rpc_error",
+                message: "RPC request failed".to_string(),
+                details: None,
+            },
+            CocoError::RpcTimeout => ApiError {
+                code: "rpc_timeout",
+                message: "RPC request timed out".to_string(),
+                details: None,
+            },
+            CocoError::RateLimited => ApiError {
+                code: "rpc_rate_limited",
+                message: "RPC provider is rate limiting requests, try again shortly".to_string(),
+                details: None,
+            },
+            CocoError::Reverted(reason) => ApiError {
+                code: "contract_reverted",
+                message: format!("Contract call reverted: {}", reason),
+                details: None,
+            },
+            CocoError::NoHealthyProvider(network) => ApiError {
+                code: "no_healthy_provider",
+                message: format!("No healthy RPC provider available for {}", network),
+                details: None,
+            },
+            CocoError::BlockNotFound => ApiError {
+                code: "block_not_found",
+                message: "The RPC did not return the requested block".to_string(),
+                details: None,
+            },
+            CocoError::InvalidBlock(msg) => ApiError {
+                code: "invalid_block",
+                message: msg.clone(),
+                details: None,
+            },
+            CocoError::ArchiveRequired => ApiError {
+                code: "archive_node_required",
+                message: "None of the configured RPC providers keep state for that block, \
+                          historical lookups need an archive node"
+                    .to_string(),
+                details: None,
+            },
+            CocoError::QuorumUnavailable {
+                required,
+                available,
+            } => ApiError {
+                code: "quorum_unavailable",
+                message: format!(
+                    "Quorum read needs {} healthy providers, only {} available",
+                    required, available
+                ),
+                details: None,
+            },
+            CocoError::QuorumMismatch { block, results } => ApiError {
+                code: "quorum_mismatch",
+                message: format!("Providers returned different results at block {}", block),
+                details: serde_json::to_value(results).ok(),
+            },
+            CocoError::ProofUnavailable(msg) => ApiError {
+                code: "proof_unavailable",
+                message: msg.clone(),
+                details: None,
+            },
+            CocoError::InvalidProof(msg) => ApiError {
+                code: "invalid_proof",
+                message: format!("The RPC returned a proof that doesn't verify: {}", msg),
+                details: None,
+            },
+            CocoError::InvalidContenthash(msg) => ApiError {
+                code: "invalid_contenthash",
+                message: format!("Invalid contenthash: {}", msg),
+                details: None,
+            },
+            CocoError::AvatarUnavailable(msg) => ApiError {
+                code: "avatar_unavailable",
+                message: format!("Couldn't resolve the avatar {}", msg),
+                details: None,
+            },
+            CocoError::UnknownToken(id) => ApiError {
+                code: "unknown_token",
+                message: format!("Token {} isn't a registered name", id),
+                details: None,
+            },
+            CocoError::InvalidPattern(msg) => ApiError {
+                code: "invalid_pattern",
+                message: format!("Invalid pattern: {}", msg),
+                details: None,
+            },
+            CocoError::ScanNotFound(id) => ApiError {
+                code: "scan_not_found",
+                message: format!("No scan with id {}", id),
+                details: None,
+            },
+            CocoError::ScanNotFinished(id) => ApiError {
+                code: "scan_not_finished",
+                message: format!("Scan {} is still running, poll it until it completes", id),
+                details: None,
+            },
+            CocoError::ScanFailed(id, error) => ApiError {
+                code: "scan_failed",
+                message: format!("Scan {} failed: {}", id, error),
+                details: None,
+            },
+            CocoError::JobNotFound(id) => ApiError {
+                code: "job_not_found",
+                message: format!("No job with id {}", id),
+                details: None,
+            },
+            CocoError::JobNotFinished(id) => ApiError {
+                code: "job_not_finished",
+                message: format!("Job {} is still running, poll it until it completes", id),
+                details: None,
+            },
+            CocoError::JobFailed(id, error) => ApiError {
+                code: "job_failed",
+                message: format!("Job {} failed: {}", id, error),
+                details: None,
+            },
+            CocoError::InvalidUpload(msg) => ApiError {
+                code: "invalid_upload",
+                message: format!("Invalid upload: {}", msg),
+                details: None,
+            },
+            CocoError::Contract(_) => ApiError {
+                code: "contract_call_failed",
+                message: "Contract call failed".to_string(),
+                details: None,
+            },
+            CocoError::Database(_) => ApiError {
+                code: "database_error",
+                message: "Database request failed".to_string(),
+                details: None,
             },
         }
     }
 }
+
+// Pruned nodes word this differently: geth and nethermind report a missing
+// trie node, erigon and reth unavailable history, hosted providers mention
+// archive access.
+fn is_missing_state(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "missing trie node",
+        "historical state",
+        "state not available",
+        "state is not available",
+        "state unavailable",
+        "state already discarded",
+        "pruned",
+        "archive",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+// an error response means the node answered (eg a revert), anything else is
+// the transport or the node misbehaving
+fn is_transport(e: &TransportError) -> bool {
+    !matches!(e, RpcError::ErrorResp(_))
+}
+
+/// Human readable description of revert data from the controller or registrar.
+pub fn describe_revert(data: &[u8]) -> String {
+    use ControllerReverts::ControllerRevertsErrors as ControllerError;
+    use RegistrarReverts::RegistrarRevertsErrors as RegistrarError;
+
+    if data.is_empty() {
+        return "reverted without a reason".to_string();
+    }
+
+    if let Ok(e) = ControllerError::abi_decode(data) {
+        return match e {
+            ControllerError::CommitmentTooNew(e) => {
+                format!("CommitmentTooNew: commitment {} is too new", e.commitment)
+            }
+            ControllerError::CommitmentTooOld(e) => {
+                format!("CommitmentTooOld: commitment {} has expired", e.commitment)
+            }
+            ControllerError::NameNotAvailable(e) => {
+                format!("NameNotAvailable: {} is not available", e.name)
+            }
+            ControllerError::DurationTooShort(e) => {
+                format!("DurationTooShort: {} seconds is too short", e.duration)
+            }
+            ControllerError::ResolverRequiredWhenDataSupplied(_) => {
+                "ResolverRequiredWhenDataSupplied: records need a resolver".to_string()
+            }
+            ControllerError::UnexpiredCommitmentExists(e) => format!(
+                "UnexpiredCommitmentExists: commitment {} is still pending",
+                e.commitment
+            ),
+            ControllerError::InsufficientValue(_) => {
+                "InsufficientValue: not enough ETH sent".to_string()
+            }
+            ControllerError::Unauthorised(e) => {
+                format!("Unauthorised: not allowed to manage {}", e.node)
+            }
+            ControllerError::MaxCommitmentAgeTooLow(_) => "MaxCommitmentAgeTooLow".to_string(),
+            ControllerError::MaxCommitmentAgeTooHigh(_) => "MaxCommitmentAgeTooHigh".to_string(),
+        };
+    }
+
+    if let Ok(e) = RegistrarError::abi_decode(data) {
+        return match e {
+            RegistrarError::Expired(e) => format!("Expired: token {} has expired", e.tokenId),
+            RegistrarError::NonexistentToken(e) => {
+                format!("NonexistentToken: token {} was never registered", e.tokenId)
+            }
+            RegistrarError::NotAvailable(e) => {
+                format!("NotAvailable: token {} is not available", e.tokenId)
+            }
+            RegistrarError::OnlyController(_) => {
+                "OnlyController: caller is not a controller".to_string()
+            }
+            RegistrarError::RegistrarNotLive(_) => {
+                "RegistrarNotLive: registrar doesn't own its node".to_string()
+            }
+        };
+    }
+
+    // plain require messages and panics
+    decode_revert_reason(data)
+        .unwrap_or_else(|| format!("unknown revert data {}", hex::encode_prefixed(data)))
+}
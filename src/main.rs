@@ -1,8 +1,17 @@
-use alloy::providers::{Provider, ProviderBuilder};
 use coco::{
     config,
-    startup::{create_pool, run},
-    types::{api::AppState, ens::EnsContractAddresses},
+    services::{
+        avatar::metadata_client,
+        batch::run_batch_worker,
+        jobs::Jobs,
+        labels::{LabelQueue, run_label_writer},
+        provider_pool::run_health_checks,
+        registrations::{registration_notices, run_registration_listener},
+        scan::Scans,
+        watch::run_sweeper,
+    },
+    startup::{connect_networks, create_pool, run},
+    types::api::AppState,
 };
 use std::net::TcpListener;
 use std::sync::Arc;
@@ -23,24 +32,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Listening here: {:?}", listener);
 
-    // Alloy
-    let rpc = &config.eth_rpc;
-    let provider = ProviderBuilder::new().connect(rpc).await?;
-    // FillProvider<JoinFill<Identity, JoinFill<GasFiller, JoinFill<BlobGasFiller, JoinFill<NonceFiller, ChainIdFiller>>>>, RootProvider>
-
     // SQLx - Postgres Pool
     let connection = create_pool(&config.database).await?;
 
-    // contract addresses
-    let contract_addresses = EnsContractAddresses::mainnet();
+    // Alloy - one provider and address set per configured network
+    let networks = connect_networks(&config, &connection).await?;
 
+    let http = metadata_client(&config.avatar);
+    let (labels, queued_labels) = LabelQueue::new();
     let app_state = Arc::new(AppState {
         app_config: config,
-        provider,
         connection,
-        ens_contract_addresses: contract_addresses,
+        networks,
+        http,
+        registrations: registration_notices(),
+        labels,
     });
 
+    // fail early rather than on the first request
+    app_state.network(None)?;
+
+    tokio::spawn(run_sweeper(app_state.clone()));
+    tokio::spawn(run_health_checks(app_state.clone()));
+    tokio::spawn(run_batch_worker::<Scans>(app_state.clone()));
+    tokio::spawn(run_batch_worker::<Jobs>(app_state.clone()));
+    tokio::spawn(run_registration_listener(app_state.clone()));
+    tokio::spawn(run_label_writer(
+        app_state.connection.clone(),
+        queued_labels,
+    ));
+
     run(listener, app_state)
         .map_err(|e| -> AppError { Box::new(e) })?
         .await
@@ -0,0 +1,25 @@
+//! avatar.rs
+use crate::services::avatar::resolve_avatar;
+use crate::services::labels::remember_names;
+use crate::types::api::{AppState, NetworkQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+
+// ENSIP-12 avatar as a displayable URL, with NFT ownership checked
+pub async fn avatar(
+    name: web::Path<String>,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    match resolve_avatar(&state, network, &name).await {
+        Ok(avatar) => {
+            remember_names(&state.labels, &network.tld, &[name.to_string()]);
+            HttpResponse::Ok().json(avatar)
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
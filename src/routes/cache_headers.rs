@@ -0,0 +1,49 @@
+//! cache_headers.rs
+use crate::types::api::BlockContext;
+use actix_web::http::header::{self, CacheControl, CacheDirective, ETag, EntityTag};
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::Serialize;
+
+/// Responds with `body` tagged by the block it was read at. The ETag is the
+/// block hash and max-age runs until the next block is expected, so clients
+/// and proxies can reuse the answer until the chain moves.
+pub fn block_cached_json<T: Serialize>(
+    req: &HttpRequest,
+    block: &BlockContext,
+    block_time_secs: u64,
+    body: &T,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(block.hash.to_string());
+    let block_age = Utc::now()
+        .timestamp()
+        .saturating_sub(block.timestamp as i64);
+    let max_age = (block_time_secs as i64 - block_age).max(0) as u32;
+
+    let not_modified = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .any(|tag| tag.trim() == "*" || tag.trim() == etag.to_string())
+        });
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(max_age),
+        ]));
+
+    if not_modified {
+        response.finish()
+    } else {
+        response.json(body)
+    }
+}
@@ -1,16 +1,23 @@
 //! check.rs
-use crate::services::ens::check_name_availability;
-use crate::types::api::{AppState, CheckNameResponse, CheckQuery};
-use actix_web::{HttpRequest, HttpResponse, http::StatusCode, web};
+use crate::routes::cache_headers::block_cached_json;
+use crate::routes::stream::stream_rows;
+use crate::services::blocks::block_selector;
+use crate::services::ens::{
+    check_name_availability, check_name_availability_quorum, check_name_limit,
+};
+use crate::services::labels::remember_names;
+use crate::types::api::{AppState, AtBlock, CheckNameResponse, CheckQuery, Consistency};
+use actix_web::{HttpRequest, HttpResponse, web};
 use alloy::primitives::{Address, U256};
 use serde::Serialize;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 // ---- Response to match bot -----
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NameCheckData {
+    pub block_number: u64,
+    pub block_timestamp: u64,
     pub values: Vec<NameCheckResponse>,
 }
 
@@ -51,10 +58,59 @@ pub async fn check_names(
         .collect();
 
     dbg!("{}", &names);
-    match check_name_availability(state.get_ref(), &names).await {
-        Ok(rows) => {
-            let response = prepare_response_data(rows);
-            HttpResponse::Ok().json(response)
+    let network = match state.network(query_names.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    let at = match block_selector(query_names.at_block, query_names.at_time.as_deref()) {
+        Ok(at) => at,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    if let Err(e) = check_name_limit(network, &names) {
+        return HttpResponse::build(e.status_code()).json(e.to_api_error());
+    }
+    remember_names(&state.labels, &network.tld, &names);
+
+    if query_names.stream {
+        let state = state.get_ref().clone();
+        let network_name = network.name.clone();
+        let consistency = query_names.consistency;
+        return stream_rows(
+            &req,
+            names,
+            network.multicall.config.chunk_size,
+            network.multicall.config.concurrency,
+            at,
+            move |names, at| {
+                let state = state.clone();
+                let network_name = network_name.clone();
+                async move {
+                    let network = state.network(Some(&network_name))?;
+                    let results = match consistency {
+                        Consistency::Single => check_name_availability(network, &names, at).await,
+                        Consistency::Quorum => {
+                            check_name_availability_quorum(network, &names, at).await
+                        }
+                    }?;
+                    Ok(AtBlock {
+                        block: results.block,
+                        rows: prepare_response_data(results).values,
+                    })
+                }
+            },
+        );
+    }
+
+    let results = match query_names.consistency {
+        Consistency::Single => check_name_availability(network, &names, at).await,
+        Consistency::Quorum => check_name_availability_quorum(network, &names, at).await,
+    };
+
+    match results {
+        Ok(results) => {
+            let block = results.block;
+            let response = prepare_response_data(results);
+            block_cached_json(&req, &block, network.block_time_secs, &response)
         }
         Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
     }
@@ -62,11 +118,14 @@ pub async fn check_names(
 }
 
 // utils
-fn prepare_response_data(rows: Vec<CheckNameResponse>) -> NameCheckData {
-    let values = rows
+fn prepare_response_data(results: AtBlock<CheckNameResponse>) -> NameCheckData {
+    let now_secs = results.block.timestamp;
+
+    let values = results
+        .rows
         .into_iter()
         .map(|r| {
-            let days_left = r.expires.and_then(u256_to_days_left);
+            let days_left = r.expires.and_then(|e| u256_to_days_left(e, now_secs));
 
             let registeration_price = r.price.map(|p| Cost {
                 base: p.base.to_string(),
@@ -83,15 +142,18 @@ fn prepare_response_data(rows: Vec<CheckNameResponse>) -> NameCheckData {
         })
         .collect();
 
-    NameCheckData { values }
+    NameCheckData {
+        block_number: results.block.number,
+        block_timestamp: results.block.timestamp,
+        values,
+    }
 }
 
-pub fn u256_to_days_left(expires: U256) -> Option<u64> {
+/// Whole days between `now_secs` (the pinned block's timestamp) and `expires`.
+pub fn u256_to_days_left(expires: U256, now_secs: u64) -> Option<u64> {
     // fit unit seconds from ens to u64 for chrono
     let expiry_secs: u64 = expires.try_into().ok()?;
 
-    let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
-
     if expiry_secs < now_secs {
         return Some(0);
     }
@@ -0,0 +1,59 @@
+use crate::errors::CocoError;
+use crate::services::ens::refresh_contract_constants;
+use crate::types::api::{AppState, NetworkQuery};
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, web};
+use std::sync::Arc;
+
+pub async fn get_constants(
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    match state.network(query.network.as_deref()) {
+        Ok(network) => HttpResponse::Ok().json(network.constants()),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+// re-read after a contract upgrade without restarting the server
+pub async fn refresh_constants(
+    req: HttpRequest,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    // every refresh is a round of RPC calls, so only operators get to trigger it
+    if let Err(e) = require_admin(&req, state.app_config.admin_token.as_deref()) {
+        return HttpResponse::build(e.status_code()).json(e.to_api_error());
+    }
+
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match refresh_contract_constants(network).await {
+        Ok(constants) => HttpResponse::Ok().json(constants),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+/// Checks the request carries `Authorization: Bearer <token>`. With no token
+/// configured nobody passes.
+pub fn require_admin(req: &HttpRequest, token: Option<&str>) -> Result<(), CocoError> {
+    let given = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match (token, given) {
+        (Some(token), Some(given)) if constant_time_eq(token.as_bytes(), given.as_bytes()) => {
+            Ok(())
+        }
+        _ => Err(CocoError::Unauthorized),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
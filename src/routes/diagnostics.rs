@@ -0,0 +1,16 @@
+use crate::services::provider_pool::EndpointDiagnostics;
+use crate::types::api::AppState;
+use actix_web::{HttpResponse, web};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// endpoint health per network, urls are reduced to scheme and host
+pub async fn rpc_diagnostics(state: web::Data<Arc<AppState>>) -> HttpResponse {
+    let networks: HashMap<&str, Vec<EndpointDiagnostics>> = state
+        .networks
+        .iter()
+        .map(|(name, network)| (name.as_str(), network.pool.diagnostics()))
+        .collect();
+
+    HttpResponse::Ok().json(networks)
+}
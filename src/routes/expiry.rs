@@ -1,17 +1,23 @@
-use super::check::u256_to_days_left;
-use crate::services::ens::check_name_expiry;
-use crate::types::api::{AppState, CheckExpiryResponse, CheckQuery};
-use actix_web::{HttpResponse, web};
+use crate::routes::cache_headers::block_cached_json;
+use crate::routes::stream::stream_rows;
+use crate::services::blocks::block_selector;
+use crate::services::ens::{check_name_expiry, check_name_limit};
+use crate::services::labels::remember_names;
+use crate::services::premium::PREMIUM_DECAY_DAYS;
+use crate::types::api::{AppState, AtBlock, CheckExpiryResponse, CheckQuery};
+use actix_web::{HttpRequest, HttpResponse, web};
 use alloy::primitives::U256;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-const GRACE_PERIOD_SECS: i64 = 90 * 24 * 60 * 60;
+const PREMIUM_PERIOD_SECS: i64 = PREMIUM_DECAY_DAYS as i64 * 24 * 60 * 60;
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExpiryData {
+    pub block_number: u64,
+    pub block_timestamp: u64,
     pub values: Vec<ExpiryResponse>,
 }
 
@@ -22,12 +28,15 @@ pub struct ExpiryResponse {
 
     pub expiry_date: Option<String>,
     pub grace_period_end: Option<String>,
+    pub premium_period_end: Option<String>,
     pub is_expired: bool,
     pub is_in_grace_period: bool,
+    pub is_in_premium_period: bool,
     pub days_until_expiry: Option<i64>,
 }
 
 pub async fn check_expiry(
+    req: HttpRequest,
     query_names: web::Query<CheckQuery>,
     state: web::Data<Arc<AppState>>,
 ) -> HttpResponse {
@@ -38,66 +47,130 @@ pub async fn check_expiry(
         .filter(|n| !n.is_empty())
         .collect();
 
-    match check_name_expiry(state.get_ref(), &names).await {
-        Ok(rows) => {
-            dbg!("{}", &rows);
-            let response = prepare_response_data(rows);
-            HttpResponse::Ok().json(response)
+    let network = match state.network(query_names.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    let at = match block_selector(query_names.at_block, query_names.at_time.as_deref()) {
+        Ok(at) => at,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    if let Err(e) = check_name_limit(network, &names) {
+        return HttpResponse::build(e.status_code()).json(e.to_api_error());
+    }
+    remember_names(&state.labels, &network.tld, &names);
+
+    if query_names.stream {
+        let state = state.get_ref().clone();
+        let network_name = network.name.clone();
+        return stream_rows(
+            &req,
+            names,
+            network.multicall.config.chunk_size,
+            network.multicall.config.concurrency,
+            at,
+            move |names, at| {
+                let state = state.clone();
+                let network_name = network_name.clone();
+                async move {
+                    let network = state.network(Some(&network_name))?;
+                    let grace_period_secs = network.constants().grace_period_secs as i64;
+                    let results = check_name_expiry(network, &names, at).await?;
+                    Ok(AtBlock {
+                        block: results.block,
+                        rows: prepare_response_data(results, grace_period_secs).values,
+                    })
+                }
+            },
+        );
+    }
+
+    match check_name_expiry(network, &names, at).await {
+        Ok(results) => {
+            let grace_period_secs = network.constants().grace_period_secs as i64;
+            let block = results.block;
+            let response = prepare_response_data(results, grace_period_secs);
+            block_cached_json(&req, &block, network.block_time_secs, &response)
         }
         Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
     }
 }
 
-fn prepare_response_data(rows: Vec<CheckExpiryResponse>) -> ExpiryData {
-    let now_secs: i64 = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+fn prepare_response_data(
+    results: AtBlock<CheckExpiryResponse>,
+    grace_period_secs: i64,
+) -> ExpiryData {
+    // everything is relative to the pinned block, not the server clock
+    let now_secs = results.block.timestamp as i64;
 
-    let values = rows
+    let values = results
+        .rows
         .into_iter()
-        .map(|r| {
-            // If the name is available, it’s not registered: no expiry info.
-            if r.available {
-                return ExpiryResponse {
-                    name: r.name,
-                    expiry_date: None,
-                    grace_period_end: None,
-                    is_expired: false,
-                    is_in_grace_period: false,
-                    days_until_expiry: None,
-                };
-            }
-
-            // Not available => should have an expiry.
-            let expiry_secs_opt: Option<i64> = r.expiry_date.and_then(u256_to_i64);
-
-            let grace_end_secs_opt = expiry_secs_opt.map(|s| s + GRACE_PERIOD_SECS);
-
-            let is_expired = expiry_secs_opt
-                .map(|expiry| now_secs >= expiry)
-                .unwrap_or(false);
-
-            let is_in_grace_period = match (expiry_secs_opt, grace_end_secs_opt) {
-                (Some(expiry), Some(grace_end)) => now_secs >= expiry && now_secs < grace_end,
-                _ => false,
-            };
-
-            let days_until_expiry =
-                expiry_secs_opt.map(|expiry| ((expiry - now_secs) / 86_400).max(0));
-
-            ExpiryResponse {
-                name: r.name,
-                expiry_date: expiry_secs_opt.and_then(unix_to_iso),
-                grace_period_end: grace_end_secs_opt.and_then(unix_to_iso),
-                is_expired,
-                is_in_grace_period,
-                days_until_expiry,
-            }
-        })
+        .map(|r| expiry_response(r, now_secs, grace_period_secs))
         .collect();
 
-    ExpiryData { values }
+    ExpiryData {
+        block_number: results.block.number,
+        block_timestamp: results.block.timestamp,
+        values,
+    }
+}
+
+/// Builds the expiry view of a name relative to `now_secs`.
+pub fn expiry_response(
+    r: CheckExpiryResponse,
+    now_secs: i64,
+    grace_period_secs: i64,
+) -> ExpiryResponse {
+    // `available` stays true through the premium auction, so the expiry
+    // decides everything. Only names that were never registered have none.
+    let expiry_secs_opt: Option<i64> = r
+        .expiry_date
+        .filter(|e| !e.is_zero())
+        .map(|e| u256_to_i64(e).unwrap_or(i64::MAX));
+    if expiry_secs_opt.is_none() && r.available {
+        return ExpiryResponse {
+            name: r.name,
+            expiry_date: None,
+            grace_period_end: None,
+            premium_period_end: None,
+            is_expired: false,
+            is_in_grace_period: false,
+            is_in_premium_period: false,
+            days_until_expiry: None,
+        };
+    }
+
+    let grace_end_secs_opt = expiry_secs_opt.map(|s| s.saturating_add(grace_period_secs));
+    let premium_end_secs_opt = grace_end_secs_opt.map(|s| s.saturating_add(PREMIUM_PERIOD_SECS));
+
+    let is_expired = expiry_secs_opt
+        .map(|expiry| now_secs >= expiry)
+        .unwrap_or(false);
+
+    let is_in_grace_period = match (expiry_secs_opt, grace_end_secs_opt) {
+        (Some(expiry), Some(grace_end)) => now_secs >= expiry && now_secs < grace_end,
+        _ => false,
+    };
+
+    let is_in_premium_period = match (grace_end_secs_opt, premium_end_secs_opt) {
+        (Some(grace_end), Some(premium_end)) => now_secs >= grace_end && now_secs < premium_end,
+        _ => false,
+    };
+
+    let days_until_expiry =
+        expiry_secs_opt.map(|expiry| (expiry.saturating_sub(now_secs) / 86_400).max(0));
+
+    ExpiryResponse {
+        name: r.name,
+        expiry_date: expiry_secs_opt.and_then(unix_to_iso),
+        grace_period_end: grace_end_secs_opt.and_then(unix_to_iso),
+        premium_period_end: premium_end_secs_opt.and_then(unix_to_iso),
+        is_expired,
+        is_in_grace_period,
+        is_in_premium_period,
+        days_until_expiry,
+    }
 }
 
 fn u256_to_i64(u: U256) -> Option<i64> {
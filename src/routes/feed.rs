@@ -0,0 +1,83 @@
+//! feed.rs
+use crate::routes::expiry::{ExpiryResponse, expiry_response};
+use crate::services::feed::{FeedKind, FeedPage, name_feed};
+use crate::types::api::{AppState, CheckExpiryResponse, FeedQuery};
+use actix_web::{HttpResponse, web};
+use alloy::primitives::U256;
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedData {
+    pub indexed_block: Option<u64>,
+    pub next_cursor: Option<String>,
+    pub values: Vec<FeedResponse>,
+}
+
+// same fields as /api/expiry so the bot renders both the same way
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedResponse {
+    #[serde(flatten)]
+    pub expiry: ExpiryResponse,
+    // wei, highest base + premium the name has been registered for
+    pub max_registration_cost: Option<String>,
+}
+
+pub async fn expiring_feed(
+    query: web::Query<FeedQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    feed(FeedKind::Expiring, &query, &state).await
+}
+
+pub async fn released_feed(
+    query: web::Query<FeedQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    feed(FeedKind::Released, &query, &state).await
+}
+
+async fn feed(kind: FeedKind, query: &FeedQuery, state: &AppState) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    let now = Utc::now().timestamp();
+    match name_feed(state, network, kind, query, now).await {
+        Ok(page) => {
+            let grace_period_secs = network.constants().grace_period_secs as i64;
+            HttpResponse::Ok().json(prepare_response_data(page, now, grace_period_secs))
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+fn prepare_response_data(page: FeedPage, now_secs: i64, grace_period_secs: i64) -> FeedData {
+    let values = page
+        .rows
+        .into_iter()
+        .map(|row| FeedResponse {
+            // released names are available again but still worth showing dates for
+            expiry: expiry_response(
+                CheckExpiryResponse {
+                    name: row.name,
+                    available: false,
+                    expiry_date: Some(U256::from(row.expires.max(0))),
+                },
+                now_secs,
+                grace_period_secs,
+            ),
+            max_registration_cost: row.max_cost,
+        })
+        .collect();
+
+    FeedData {
+        indexed_block: page.indexed_block,
+        next_cursor: page.next_cursor,
+        values,
+    }
+}
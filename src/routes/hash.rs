@@ -0,0 +1,28 @@
+//! hash.rs
+use crate::services::hash::hash_names;
+use crate::services::labels::remember_names;
+use crate::types::api::{AppState, HashQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+
+// namehash, labelhash, token ids and DNS encoding for each name
+pub async fn hash(query: web::Query<HashQuery>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    let names: Vec<String> = query
+        .names
+        .split(",")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    match hash_names(&state.connection, network, &names).await {
+        Ok(values) => {
+            remember_names(&state.labels, &network.tld, &names);
+            HttpResponse::Ok().json(values)
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
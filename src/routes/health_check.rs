@@ -1,4 +1,4 @@
-use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web::HttpResponse;
 
 // #[get("/")]
 pub async fn hello() -> HttpResponse {
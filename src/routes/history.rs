@@ -0,0 +1,35 @@
+//! history.rs
+use crate::services::history::name_history;
+use crate::services::labels::remember_names;
+use crate::services::registrations::EventPosition;
+use crate::types::api::{AppState, HistoryQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+
+// registrations, renewals and transfers of one name, newest first
+pub async fn history(
+    name: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    remember_names(&state.labels, &network.tld, &[name.to_string()]);
+
+    let before = match query
+        .before
+        .as_deref()
+        .map(EventPosition::decode)
+        .transpose()
+    {
+        Ok(before) => before,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match name_history(&state.connection, network, &name, before, query.limit).await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
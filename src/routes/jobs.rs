@@ -0,0 +1,174 @@
+//! jobs.rs
+use crate::errors::CocoError;
+use crate::services::jobs::{
+    CSV_HEADER, create_job, csv_names, csv_row, get_job, job_results, ndjson_row,
+};
+use crate::types::api::{AppState, JobBody, JobResultsQuery, NameResult, ResultFormat};
+use actix_multipart::Multipart;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+// enough for a few hundred thousand names, the name limit applies after
+const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+// queues a batch check from a JSON body or a multipart CSV upload, poll
+// GET /jobs/{id} for progress
+pub async fn job(
+    req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let body = match read_job_body(&req, payload).await {
+        Ok(body) => body,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match create_job(state.get_ref(), &body).await {
+        Ok(job) => HttpResponse::Accepted().json(job),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+pub async fn job_status(id: web::Path<String>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match get_job(&state.connection, id).await {
+        Ok(job) => HttpResponse::Ok().json(job),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+// every checked name once the job has completed, as JSON, NDJSON or CSV
+pub async fn job_download(
+    id: web::Path<String>,
+    query: web::Query<JobResultsQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match job_results(&state.connection, id, query.available).await {
+        Ok(rows) => results_response("job", id, query.format, rows),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+/// Streams stored results as they're read. JSON is `{"id": .., "values": [..]}`
+/// like the other endpoints, CSV comes as a `<kind>-<id>.csv` attachment.
+pub fn results_response(
+    kind: &str,
+    id: Uuid,
+    format: ResultFormat,
+    rows: mpsc::Receiver<Result<NameResult, CocoError>>,
+) -> HttpResponse {
+    let (head, tail) = match format {
+        ResultFormat::Json => (format!("{{\"id\":\"{}\",\"values\":[", id), "]}"),
+        ResultFormat::Ndjson => (String::new(), ""),
+        ResultFormat::Csv => (CSV_HEADER.to_string(), ""),
+    };
+
+    // a read error ends the body early, so the client sees a broken download
+    let rows = futures::stream::unfold((rows, true), move |(mut rows, first)| async move {
+        let row = match rows.recv().await? {
+            Ok(row) => row,
+            Err(e) => return Some((Err(e), (rows, false))),
+        };
+        let bytes = match format {
+            ResultFormat::Json => {
+                let separator = if first { "" } else { "," };
+                format!(
+                    "{}{}",
+                    separator,
+                    serde_json::to_string(&row).unwrap_or_default()
+                )
+            }
+            ResultFormat::Ndjson => ndjson_row(&row),
+            ResultFormat::Csv => csv_row(&row),
+        };
+        Some((Ok(Bytes::from(bytes)), (rows, false)))
+    });
+    let body = futures::stream::once(async move { Ok::<_, CocoError>(Bytes::from(head)) })
+        .chain(rows)
+        .chain(futures::stream::once(async move {
+            Ok(Bytes::from_static(tail.as_bytes()))
+        }));
+
+    let mut response = HttpResponse::Ok();
+    match format {
+        ResultFormat::Json => response.content_type("application/json"),
+        ResultFormat::Ndjson => response.content_type("application/x-ndjson"),
+        ResultFormat::Csv => response.content_type("text/csv").insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}-{}.csv\"", kind, id),
+        )),
+    };
+    response.streaming(body)
+}
+
+async fn read_job_body(req: &HttpRequest, payload: web::Payload) -> Result<JobBody, CocoError> {
+    let multipart = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    if !multipart {
+        let data = read_limited(payload).await?;
+        return serde_json::from_slice(&data).map_err(|e| CocoError::InvalidUpload(e.to_string()));
+    }
+
+    // a `file` field with the CSV and an optional `network` field
+    let mut form = Multipart::new(req.headers(), payload);
+    let mut names = None;
+    let mut network = None;
+    while let Some(field) = form.next().await {
+        let field = field.map_err(|e| CocoError::InvalidUpload(e.to_string()))?;
+        match field.name() {
+            Some("file") => names = Some(csv_names(&read_limited(field).await?)?),
+            Some("network") => {
+                let data = read_limited(field).await?;
+                network = Some(String::from_utf8_lossy(&data).trim().to_string());
+            }
+            _ => continue,
+        }
+    }
+
+    Ok(JobBody {
+        names: names.ok_or_else(|| CocoError::InvalidUpload("no file field".to_string()))?,
+        network: network.filter(|n| !n.is_empty()),
+    })
+}
+
+async fn read_limited<S, B, E>(mut stream: S) -> Result<Vec<u8>, CocoError>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| CocoError::InvalidUpload(e.to_string()))?;
+        if data.len() + chunk.as_ref().len() > MAX_UPLOAD_BYTES {
+            return Err(CocoError::InvalidUpload(format!(
+                "uploads are limited to {} MiB",
+                MAX_UPLOAD_BYTES / 1024 / 1024
+            )));
+        }
+        data.extend_from_slice(chunk.as_ref());
+    }
+
+    Ok(data)
+}
+
+fn parse_id(id: &str) -> Result<Uuid, CocoError> {
+    id.parse().map_err(|_| CocoError::InvalidQueryInput)
+}
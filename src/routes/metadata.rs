@@ -0,0 +1,49 @@
+//! metadata.rs
+use crate::errors::CocoError;
+use crate::services::metadata::{token_image, token_metadata};
+use crate::types::api::{AppState, NetworkQuery};
+use actix_web::{HttpResponse, web};
+use alloy::primitives::U256;
+use std::sync::Arc;
+
+// token ids are decimal like marketplaces send them, or 0x hex
+fn parse_token_id(token_id: &str) -> Result<U256, CocoError> {
+    token_id
+        .trim()
+        .parse()
+        .map_err(|_| CocoError::UnknownToken(token_id.to_string()))
+}
+
+pub async fn metadata(
+    token_id: web::Path<String>,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let result = async {
+        let network = state.network(query.network.as_deref())?;
+        token_metadata(&state, network, parse_token_id(&token_id)?).await
+    }
+    .await;
+
+    match result {
+        Ok(metadata) => HttpResponse::Ok().json(metadata),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+pub async fn metadata_image(
+    token_id: web::Path<String>,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let result = async {
+        let network = state.network(query.network.as_deref())?;
+        token_image(&state, network, parse_token_id(&token_id)?).await
+    }
+    .await;
+
+    match result {
+        Ok(svg) => HttpResponse::Ok().content_type("image/svg+xml").body(svg),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
@@ -1,7 +1,21 @@
+pub mod avatar;
+pub mod cache_headers;
 pub mod check;
+pub mod constants;
+pub mod diagnostics;
 pub mod expiry;
+pub mod feed;
+pub mod hash;
 pub mod health_check;
 pub mod history;
+pub mod jobs;
+pub mod metadata;
 pub mod portfolio;
+pub mod profile;
 pub mod register;
+pub mod registrations;
+pub mod scan;
+pub mod stream;
+pub mod suggest;
+pub mod verify;
 pub mod watch;
@@ -1,16 +1,83 @@
-use crate::{
-    errors::CocoError,
-    types::api::{AddressQuery, AppState},
-};
+//! portfolio.rs
+use crate::errors::CocoError;
+use crate::routes::expiry::{ExpiryResponse, expiry_response};
+use crate::services::portfolio::{PortfolioPage, address_portfolio};
+use crate::types::api::{AddressQuery, AppState, CheckExpiryResponse};
 use actix_web::{HttpResponse, web};
-use alloy::primitives::{Address, address};
+use alloy::primitives::{Address, B256, U256};
+use chrono::Utc;
+use serde::Serialize;
 use std::sync::Arc;
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioData {
+    pub address: Address,
+    pub indexed_block: Option<u64>,
+    pub values: Vec<PortfolioResponse>,
+}
+
+// same fields as /api/expiry, plus the label hash for names without a label
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioResponse {
+    #[serde(flatten)]
+    pub expiry: ExpiryResponse,
+    pub label_hash: B256,
+}
+
 pub async fn check_portfolio(
     query_address: web::Query<AddressQuery>,
     state: web::Data<Arc<AppState>>,
 ) -> HttpResponse {
-    let query = &query_address.address;
+    let network = match state.network(query_address.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    let address: Address = match query_address.address.trim().parse() {
+        Ok(address) => address,
+        Err(_) => {
+            let e = CocoError::InvalidAddress;
+            return HttpResponse::build(e.status_code()).json(e.to_api_error());
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    match address_portfolio(&state.connection, network, address, now).await {
+        Ok(page) => {
+            let grace_period_secs = network.constants().grace_period_secs as i64;
+            HttpResponse::Ok().json(prepare_response_data(address, page, now, grace_period_secs))
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+fn prepare_response_data(
+    address: Address,
+    page: PortfolioPage,
+    now_secs: i64,
+    grace_period_secs: i64,
+) -> PortfolioData {
+    let values = page
+        .rows
+        .into_iter()
+        .map(|row| PortfolioResponse {
+            expiry: expiry_response(
+                CheckExpiryResponse {
+                    name: row.name,
+                    available: false,
+                    expiry_date: Some(U256::from(row.expires.max(0))),
+                },
+                now_secs,
+                grace_period_secs,
+            ),
+            label_hash: row.label_hash,
+        })
+        .collect();
 
-    HttpResponse::Ok().finish()
+    PortfolioData {
+        address,
+        indexed_block: page.indexed_block,
+        values,
+    }
 }
@@ -0,0 +1,25 @@
+//! profile.rs
+use crate::services::ens::fetch_profile;
+use crate::services::labels::remember_names;
+use crate::types::api::{AppState, NetworkQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+
+// everything a profile card needs for one name, in a single call
+pub async fn profile(
+    name: web::Path<String>,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    match fetch_profile(network, &name).await {
+        Ok(profile) => {
+            remember_names(&state.labels, &network.tld, &[name.to_string()]);
+            HttpResponse::Ok().json(profile)
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
@@ -1,8 +1,8 @@
 use crate::types::api::RegisterBody;
-use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web::{HttpResponse, web};
 
 // register, renew, transfer, set-records
 // #[post("/register")]
-pub async fn register(json: web::Json<Vec<RegisterBody>>) -> HttpResponse {
+pub async fn register(_json: web::Json<Vec<RegisterBody>>) -> HttpResponse {
     HttpResponse::Ok().finish()
 }
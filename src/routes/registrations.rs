@@ -0,0 +1,110 @@
+//! registrations.rs
+use crate::services::registrations::{
+    EventPosition, latest_registration, recent_registrations, stream_registrations,
+};
+use crate::types::api::{AppState, NetworkQuery, RegistrationResponse, RegistrationsQuery};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// proxies drop idle connections, a comment line every so often keeps it open
+const KEEPALIVE_SECS: u64 = 15;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationsData {
+    pub next_cursor: Option<String>,
+    pub values: Vec<RegistrationResponse>,
+}
+
+pub async fn registrations(
+    query: web::Query<RegistrationsQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    let before = match query
+        .before
+        .as_deref()
+        .map(EventPosition::decode)
+        .transpose()
+    {
+        Ok(before) => before,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match recent_registrations(&state.connection, network, before, query.limit).await {
+        Ok((values, next)) => HttpResponse::Ok().json(RegistrationsData {
+            next_cursor: next.map(|p| p.encode()),
+            values,
+        }),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+// Server-Sent Events, one `registration` event per new name. Reconnecting
+// clients resume from Last-Event-ID, new ones start at the newest registration.
+pub async fn registration_stream(
+    req: HttpRequest,
+    query: web::Query<NetworkQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    let resume = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| EventPosition::decode(v).ok());
+    let position = match resume {
+        Some(position) => Some(position),
+        None => match latest_registration(&state.connection, network).await {
+            Ok(position) => position,
+            Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+        },
+    };
+
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(stream_registrations(
+        state.get_ref().clone(),
+        network.name.clone(),
+        position,
+        tx,
+    ));
+
+    let keepalive = tokio::time::interval(Duration::from_secs(KEEPALIVE_SECS));
+    let body = futures::stream::unfold((rx, keepalive), |(mut rx, mut keepalive)| async move {
+        let chunk = tokio::select! {
+            registration = rx.recv() => sse_event(&registration?),
+            _ = keepalive.tick() => Bytes::from_static(b": keepalive\n\n"),
+        };
+        Some((Ok::<_, actix_web::Error>(chunk), (rx, keepalive)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+fn sse_event(registration: &RegistrationResponse) -> Bytes {
+    let id = EventPosition {
+        block_number: registration.block_number,
+        log_index: registration.log_index,
+    };
+    let data = serde_json::to_string(registration).unwrap_or_default();
+
+    Bytes::from(format!(
+        "id: {}\nevent: registration\ndata: {}\n\n",
+        id.encode(),
+        data
+    ))
+}
@@ -0,0 +1,49 @@
+//! scan.rs
+use crate::errors::CocoError;
+use crate::routes::jobs::results_response;
+use crate::services::scan::{create_scan, get_scan, scan_results};
+use crate::types::api::{AppState, ResultFormat, ScanBody, ScanResultsQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// queues a pattern scan, poll GET /scans/{id} for progress
+pub async fn scan(body: web::Json<ScanBody>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    match create_scan(state.get_ref(), &body).await {
+        Ok(scan) => HttpResponse::Accepted().json(scan),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+pub async fn scan_status(id: web::Path<String>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match get_scan(&state.connection, id).await {
+        Ok(scan) => HttpResponse::Ok().json(scan),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+// every checked name once the scan has completed
+pub async fn scan_download(
+    id: web::Path<String>,
+    query: web::Query<ScanResultsQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match scan_results(&state.connection, id, query.available).await {
+        Ok(rows) => results_response("scan", id, ResultFormat::Json, rows),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, CocoError> {
+    id.parse().map_err(|_| CocoError::InvalidQueryInput)
+}
@@ -0,0 +1,172 @@
+//! stream.rs
+use crate::errors::CocoError;
+use crate::types::api::{ApiError, AtBlock, BlockContext, BlockSelector};
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use futures::StreamExt;
+use futures::stream::LocalBoxStream;
+use serde::Serialize;
+use std::future::Future;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamFormat {
+    // one JSON object per line, the summary wrapped as {"summary": ...}
+    Ndjson,
+    // `result` events then a `summary` event
+    Sse,
+}
+
+impl StreamFormat {
+    /// SSE when the client asks for `text/event-stream`, NDJSON otherwise.
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let sse = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("text/event-stream"));
+        if sse { Self::Sse } else { Self::Ndjson }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Ndjson => "application/x-ndjson",
+            Self::Sse => "text/event-stream",
+        }
+    }
+}
+
+// sent last, with the error instead of the remaining rows if a chunk failed
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSummary {
+    pub block_number: Option<u64>,
+    pub block_timestamp: Option<u64>,
+    pub requested: usize,
+    // names that didn't normalise have no row
+    pub returned: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+// where a stream is up to: the first chunk picks the block, the rest run
+// against it concurrently
+enum Phase<F, R> {
+    First(std::vec::IntoIter<Vec<String>>, F),
+    Rest(LocalBoxStream<'static, Result<AtBlock<R>, CocoError>>),
+    Done,
+}
+
+/// Streams the rows `check` returns for `names`, then a summary. The first
+/// chunk reads the block `at` resolves to, the rest are pinned to it and run
+/// `concurrency` at a time, each chunk's rows sent as soon as it finishes.
+pub fn stream_rows<R, F, Fut>(
+    req: &HttpRequest,
+    names: Vec<String>,
+    chunk_size: usize,
+    concurrency: usize,
+    at: BlockSelector,
+    check: F,
+) -> HttpResponse
+where
+    R: Serialize + 'static,
+    F: FnMut(Vec<String>, BlockSelector) -> Fut + 'static,
+    Fut: Future<Output = Result<AtBlock<R>, CocoError>> + 'static,
+{
+    let format = StreamFormat::from_request(req);
+    let requested = names.len();
+    let chunks: Vec<Vec<String>> = names
+        .chunks(chunk_size.max(1))
+        .map(<[String]>::to_vec)
+        .collect();
+
+    let state = (Phase::First(chunks.into_iter(), check), None, 0);
+    let body = futures::stream::unfold(state, move |(phase, mut block, mut returned)| async move {
+        let (result, phase) = match phase {
+            Phase::First(mut chunks, mut check) => match chunks.next() {
+                Some(names) => match check(names, at).await {
+                    Ok(results) => {
+                        let at = BlockSelector::Number(results.block.number);
+                        let rest = futures::stream::iter(chunks)
+                            .map(move |names| check(names, at))
+                            .buffer_unordered(concurrency.max(1))
+                            .boxed_local();
+                        (Some(Ok(results)), Phase::Rest(rest))
+                    }
+                    Err(e) => (Some(Err(e)), Phase::Done),
+                },
+                None => (None, Phase::Done),
+            },
+            Phase::Rest(mut rest) => match rest.next().await {
+                Some(Ok(results)) => (Some(Ok(results)), Phase::Rest(rest)),
+                Some(Err(e)) => (Some(Err(e)), Phase::Done),
+                None => (None, Phase::Done),
+            },
+            Phase::Done => return None,
+        };
+
+        let bytes = match result {
+            Some(Ok(results)) => {
+                block = Some(results.block);
+                returned += results.rows.len();
+                let lines: Vec<u8> = results
+                    .rows
+                    .iter()
+                    .flat_map(|row| stream_event(format, "result", row))
+                    .collect();
+                Bytes::from(lines)
+            }
+            Some(Err(e)) => {
+                let summary = stream_summary(block, requested, returned, Some(e));
+                summary_event(format, &summary)
+            }
+            None => {
+                let summary = stream_summary(block, requested, returned, None);
+                summary_event(format, &summary)
+            }
+        };
+
+        Some((Ok::<_, actix_web::Error>(bytes), (phase, block, returned)))
+    });
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+/// One row or summary, framed for `format`.
+pub fn stream_event<T: Serialize>(format: StreamFormat, event: &str, value: &T) -> Vec<u8> {
+    let data = serde_json::to_string(value).unwrap_or_default();
+    match format {
+        StreamFormat::Ndjson => format!("{}\n", data).into_bytes(),
+        StreamFormat::Sse => format!("event: {}\ndata: {}\n\n", event, data).into_bytes(),
+    }
+}
+
+fn summary_event(format: StreamFormat, summary: &StreamSummary) -> Bytes {
+    let bytes = match format {
+        StreamFormat::Ndjson => stream_event(
+            format,
+            "summary",
+            &serde_json::json!({ "summary": summary }),
+        ),
+        StreamFormat::Sse => stream_event(format, "summary", summary),
+    };
+    Bytes::from(bytes)
+}
+
+fn stream_summary(
+    block: Option<BlockContext>,
+    requested: usize,
+    returned: usize,
+    error: Option<CocoError>,
+) -> StreamSummary {
+    StreamSummary {
+        block_number: block.map(|b| b.number),
+        block_timestamp: block.map(|b| b.timestamp),
+        requested,
+        returned,
+        error: error.map(|e| e.to_api_error()),
+    }
+}
@@ -0,0 +1,68 @@
+//! suggest.rs
+use crate::routes::check::Cost;
+use crate::services::labels::remember_names;
+use crate::services::suggest::{Suggestion, SuggestionKind, suggest_names};
+use crate::types::api::{AppState, AtBlock, SuggestQuery};
+use actix_web::{HttpResponse, web};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestData {
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub values: Vec<SuggestResponse>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestResponse {
+    pub name: String,
+    // which pattern produced the name
+    pub kind: SuggestionKind,
+    pub registeration_price: Cost,
+}
+
+// available alternatives for a taken name, priced like /api/check
+pub async fn suggest(
+    query: web::Query<SuggestQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+    match suggest_names(&state.connection, network, &query.name, query.limit).await {
+        Ok(results) => {
+            remember_names(
+                &state.labels,
+                &network.tld,
+                std::slice::from_ref(&query.name),
+            );
+            HttpResponse::Ok().json(prepare_response_data(results))
+        }
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
+
+fn prepare_response_data(results: AtBlock<Suggestion>) -> SuggestData {
+    let values = results
+        .rows
+        .into_iter()
+        .map(|s| SuggestResponse {
+            name: s.name,
+            kind: s.kind,
+            registeration_price: Cost {
+                base: s.price.base.to_string(),
+                premium: s.price.premium.to_string(),
+            },
+        })
+        .collect();
+
+    SuggestData {
+        block_number: results.block.number,
+        block_timestamp: results.block.timestamp,
+        values,
+    }
+}
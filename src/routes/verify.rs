@@ -0,0 +1,28 @@
+//! verify.rs
+use crate::services::proof::verify_names;
+use crate::types::api::{AppState, VerifyQuery};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
+
+// proves nameExpires/ownerOf from storage at a trusted block
+pub async fn verify(
+    query: web::Query<VerifyQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let names: Vec<String> = query
+        .names
+        .split(",")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let network = match state.network(query.network.as_deref()) {
+        Ok(network) => network,
+        Err(e) => return HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    };
+
+    match verify_names(network, &names, query.block_hash).await {
+        Ok(verified) => HttpResponse::Ok().json(verified),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
+}
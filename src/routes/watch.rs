@@ -1,7 +1,12 @@
-use crate::types::api::WatchBody;
-use actix_web::{HttpRequest, HttpResponse, web};
+use crate::services::watch::create_watch;
+use crate::types::api::{AppState, WatchBody};
+use actix_web::{HttpResponse, web};
+use std::sync::Arc;
 
 // watch
-pub async fn watch(names: web::Json<WatchBody>) -> HttpResponse {
-    HttpResponse::Ok().finish()
+pub async fn watch(body: web::Json<WatchBody>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    match create_watch(state.get_ref(), &body).await {
+        Ok(watch) => HttpResponse::Ok().json(watch),
+        Err(e) => HttpResponse::build(e.status_code()).json(e.to_api_error()),
+    }
 }
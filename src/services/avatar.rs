@@ -0,0 +1,499 @@
+//! avatar.rs
+//!
+//! ENSIP-12 avatars. The `avatar` text record is a URL, a data URI or an NFT
+//! reference like `eip155:1/erc721:<contract>/<token id>`. An NFT only counts
+//! as verified when the address the name resolves to owns it. Token URIs are
+//! set by whoever minted the NFT, so metadata is fetched with a client that
+//! won't reach private addresses and reads a capped amount.
+use crate::config::AvatarConfig;
+use crate::errors::CocoError;
+use crate::services::ens::{
+    ENSRegistry, PublicResolver, latest_block, normalise_and_hash_name_for,
+};
+use crate::types::alloy_providers::AppProvider;
+use crate::types::api::{AppState, AvatarResponse, NetworkState, NftAvatar, NftStandard};
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use alloy::sol;
+use data_encoding::BASE64;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::{Attempt, Policy};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+// inline SVGs make some metadata large, anything past this isn't a token's
+const MAX_METADATA_BYTES: usize = 1024 * 1024;
+const MAX_REDIRECTS: usize = 3;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract ERC721 {
+        function ownerOf(uint256 tokenId) external view returns (address);
+        function tokenURI(uint256 tokenId) external view returns (string memory);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract ERC1155 {
+        function balanceOf(address account, uint256 id) external view returns (uint256);
+        function uri(uint256 id) external view returns (string memory);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NftRef {
+    pub chain_id: u64,
+    pub standard: NftStandard,
+    pub contract: Address,
+    pub token_id: U256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AvatarUri {
+    Nft(NftRef),
+    // http(s), ipfs, ipns, ar or data URI
+    Url(String),
+}
+
+// what the chain says, before any metadata is fetched
+struct OnChainAvatar {
+    record: Option<String>,
+    uri: Option<AvatarUri>,
+    verified: Option<bool>,
+    token_uri: Option<String>,
+}
+
+pub fn parse_avatar_uri(record: &str) -> Result<AvatarUri, CocoError> {
+    let record = record.trim();
+    let invalid = |why: &str| CocoError::AvatarUnavailable(format!("{}: {}", record, why));
+
+    if let Some(nft) = strip_prefix_ignore_case(record, "eip155:") {
+        let (chain_id, rest) = nft
+            .split_once('/')
+            .ok_or_else(|| invalid("expected eip155:<chain>/<standard>:<contract>/<token>"))?;
+        let (standard, rest) = rest
+            .split_once(':')
+            .ok_or_else(|| invalid("missing token standard"))?;
+        let (contract, token_id) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("missing token id"))?;
+
+        return Ok(AvatarUri::Nft(NftRef {
+            chain_id: chain_id.parse().map_err(|_| invalid("bad chain id"))?,
+            standard: match standard.to_ascii_lowercase().as_str() {
+                "erc721" => NftStandard::Erc721,
+                "erc1155" => NftStandard::Erc1155,
+                _ => return Err(invalid("only erc721 and erc1155 are supported")),
+            },
+            contract: contract
+                .parse()
+                .map_err(|_| invalid("bad contract address"))?,
+            // decimal, or hex with a 0x prefix
+            token_id: token_id.parse().map_err(|_| invalid("bad token id"))?,
+        }));
+    }
+
+    let scheme = record.split_once(':').map(|(s, _)| s.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("http" | "https" | "ipfs" | "ipns" | "ar" | "data") => {
+            Ok(AvatarUri::Url(record.to_string()))
+        }
+        _ => Err(invalid("unsupported avatar URI")),
+    }
+}
+
+/// URL an `<img>` or HTTP client can load, rewriting ipfs, ipns and ar URIs
+/// through the configured gateways.
+pub fn gateway_url(uri: &str, config: &AvatarConfig) -> Option<String> {
+    let uri = uri.trim();
+    let (scheme, rest) = uri.split_once("://").unwrap_or(("", uri));
+    let ipfs_gateway = config.ipfs_gateway.trim_end_matches('/');
+
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "https" => Some(uri.to_string()),
+        "ipfs" => Some(format!(
+            "{}/ipfs/{}",
+            ipfs_gateway,
+            rest.trim_start_matches("ipfs/")
+        )),
+        "ipns" => Some(format!(
+            "{}/ipns/{}",
+            ipfs_gateway,
+            rest.trim_start_matches("ipns/")
+        )),
+        "ar" => Some(format!(
+            "{}/{}",
+            config.arweave_gateway.trim_end_matches('/'),
+            rest
+        )),
+        _ if strip_prefix_ignore_case(uri, "data:").is_some() => Some(uri.to_string()),
+        _ => None,
+    }
+}
+
+/// ERC-1155 metadata URIs carry an `{id}` placeholder for the token id.
+pub fn erc1155_uri(uri: &str, token_id: U256) -> String {
+    uri.replace("{id}", &format!("{:064x}", token_id))
+}
+
+/// Image from NFT metadata, inline SVG becomes a data URI.
+pub fn metadata_image(metadata: &serde_json::Value) -> Option<String> {
+    let field = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    if let Some(image) = field("image").or_else(|| field("image_url")) {
+        return Some(image.to_string());
+    }
+    field("image_data").map(|svg| {
+        format!(
+            "data:image/svg+xml;base64,{}",
+            BASE64.encode(svg.as_bytes())
+        )
+    })
+}
+
+pub async fn resolve_avatar(
+    state: &AppState,
+    network: &NetworkState,
+    name: &str,
+) -> Result<AvatarResponse, CocoError> {
+    let normalised = normalise_and_hash_name_for(name, &network.tld)?;
+    let node = normalised.name_hash;
+    let config = &state.app_config.avatar;
+
+    let onchain = network
+        .pool
+        .execute(|provider| async move { avatar_at(&provider, network, node).await })
+        .await?;
+
+    let (image, nft) = match &onchain.uri {
+        None => (None, None),
+        Some(AvatarUri::Url(uri)) => (gateway_url(uri, config), None),
+        Some(AvatarUri::Nft(nft)) => {
+            let image = match &onchain.token_uri {
+                Some(token_uri) => match fetch_metadata(state, token_uri).await {
+                    Ok(metadata) => {
+                        metadata_image(&metadata).and_then(|image| gateway_url(&image, config))
+                    }
+                    // the rest of the avatar is still worth returning
+                    Err(e) => {
+                        tracing::warn!("Avatar metadata for {}: {}", normalised.name, e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            let nft = NftAvatar {
+                chain_id: nft.chain_id,
+                standard: nft.standard,
+                contract: nft.contract,
+                token_id: nft.token_id.to_string(),
+                token_uri: onchain.token_uri.clone(),
+            };
+            (image, Some(nft))
+        }
+    };
+
+    Ok(AvatarResponse {
+        name: normalised.name,
+        record: onchain.record,
+        image,
+        nft,
+        verified: onchain.verified,
+    })
+}
+
+async fn avatar_at(
+    provider: &AppProvider,
+    network: &NetworkState,
+    node: B256,
+) -> Result<OnChainAvatar, CocoError> {
+    let mut avatar = OnChainAvatar {
+        record: None,
+        uri: None,
+        verified: None,
+        token_uri: None,
+    };
+
+    let block = BlockId::number(latest_block(provider).await?.number);
+    let registry = ENSRegistry::new(network.ens_contract_addresses.ens_registry, provider);
+    let resolver_address = registry.resolver(node).block(block).call().await?;
+    if resolver_address.is_zero() {
+        return Ok(avatar);
+    }
+
+    let resolver = PublicResolver::new(resolver_address, provider);
+    let (record, address) = provider
+        .multicall()
+        .block(block)
+        .add(resolver.text(node, "avatar".to_string()))
+        .add(resolver.addr_0(node))
+        .try_aggregate(false)
+        .await?;
+
+    let Some(record) = record.ok().filter(|r| !r.trim().is_empty()) else {
+        return Ok(avatar);
+    };
+    let address = address.ok().filter(|a| !a.is_zero());
+    let uri = match parse_avatar_uri(&record) {
+        Ok(uri) => uri,
+        // a record we can't read is returned as set, without an image
+        Err(e) => {
+            tracing::warn!("{}", e);
+            avatar.record = Some(record);
+            return Ok(avatar);
+        }
+    };
+    avatar.record = Some(record);
+
+    if let AvatarUri::Nft(nft) = &uri {
+        // an NFT on another chain can't be checked from this network's RPC
+        if nft.chain_id != provider.get_chain_id().await? {
+            avatar.verified = Some(false);
+            avatar.uri = Some(uri);
+            return Ok(avatar);
+        }
+
+        let (verified, token_uri) = match nft.standard {
+            NftStandard::Erc721 => {
+                let token = ERC721::new(nft.contract, provider);
+                let (owner, token_uri) = provider
+                    .multicall()
+                    .block(block)
+                    .add(token.ownerOf(nft.token_id))
+                    .add(token.tokenURI(nft.token_id))
+                    .try_aggregate(false)
+                    .await?;
+                (address.is_some() && owner.ok() == address, token_uri.ok())
+            }
+            NftStandard::Erc1155 => {
+                let token = ERC1155::new(nft.contract, provider);
+                let (balance, token_uri) = provider
+                    .multicall()
+                    .block(block)
+                    .add(token.balanceOf(address.unwrap_or_default(), nft.token_id))
+                    .add(token.uri(nft.token_id))
+                    .try_aggregate(false)
+                    .await?;
+                (
+                    address.is_some() && balance.is_ok_and(|b| b > U256::ZERO),
+                    token_uri.ok().map(|u| erc1155_uri(&u, nft.token_id)),
+                )
+            }
+        };
+        avatar.verified = Some(verified);
+        avatar.token_uri = token_uri.filter(|u| !u.trim().is_empty());
+    }
+
+    avatar.uri = Some(uri);
+    Ok(avatar)
+}
+
+async fn fetch_metadata(state: &AppState, token_uri: &str) -> Result<serde_json::Value, CocoError> {
+    let config = &state.app_config.avatar;
+    let unavailable = |why: String| CocoError::AvatarUnavailable(format!("{}: {}", token_uri, why));
+
+    let body = if let Some(body) = decode_data_uri(token_uri) {
+        body
+    } else {
+        let url = gateway_url(token_uri, config)
+            .and_then(|url| Url::parse(&url).ok())
+            .ok_or_else(|| unavailable("unsupported metadata URI".to_string()))?;
+        // token URIs are arbitrary, so only https and our own gateways are fetched
+        let gateways = gateway_hosts(config);
+        if !allowed_url(&url, &gateways) {
+            return Err(unavailable(
+                "metadata must be served over https from a public address".to_string(),
+            ));
+        }
+
+        let mut response = state
+            .http
+            .get(url)
+            .timeout(Duration::from_secs(config.metadata_timeout_secs))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| unavailable(e.to_string()))?;
+
+        let too_large = || unavailable(format!("metadata is over {} bytes", MAX_METADATA_BYTES));
+        if response
+            .content_length()
+            .is_some_and(|len| len > MAX_METADATA_BYTES as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| unavailable(e.to_string()))?
+        {
+            if body.len() + chunk.len() > MAX_METADATA_BYTES {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        body
+    };
+
+    serde_json::from_slice(&body).map_err(|e| unavailable(format!("metadata isn't JSON, {}", e)))
+}
+
+/// Client for fetching NFT metadata. Hosts resolving only to private,
+/// loopback or link-local addresses are refused, other than the configured
+/// gateways, and redirects are capped and checked like the first request.
+pub fn metadata_client(config: &AvatarConfig) -> reqwest::Client {
+    let gateways = gateway_hosts(config);
+    let redirect_gateways = gateways.clone();
+    let redirects = Policy::custom(move |attempt: Attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if !allowed_url(attempt.url(), &redirect_gateways) {
+            attempt.error("redirected to a disallowed address")
+        } else {
+            attempt.follow()
+        }
+    });
+
+    reqwest::Client::builder()
+        .redirect(redirects)
+        .dns_resolver(Arc::new(PublicDns { gateways }))
+        .build()
+        .expect("metadata client")
+}
+
+/// Whether an address is reachable from the public internet.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+// https anywhere public, or any scheme on a configured gateway. Names are
+// checked again when the resolver looks them up.
+fn allowed_url(url: &Url, gateways: &[String]) -> bool {
+    let gateway = url
+        .host_str()
+        .is_some_and(|h| gateways.iter().any(|g| g == h));
+    if gateway {
+        return true;
+    }
+    if url.scheme() != "https" {
+        return false;
+    }
+
+    // IP literals never reach the resolver
+    match url.host_str() {
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .map_or(true, is_public_ip),
+        None => false,
+    }
+}
+
+fn gateway_hosts(config: &AvatarConfig) -> Vec<String> {
+    [&config.ipfs_gateway, &config.arweave_gateway]
+        .into_iter()
+        .filter_map(|g| Url::parse(g).ok()?.host_str().map(str::to_string))
+        .collect()
+}
+
+// drops private addresses from lookups so a public name can't point inside
+struct PublicDns {
+    gateways: Vec<String>,
+}
+
+impl Resolve for PublicDns {
+    fn resolve(&self, name: Name) -> Resolving {
+        let gateway = self.gateways.iter().any(|g| g == name.as_str());
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| gateway || is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Payload of a `data:` URI, `None` for anything else.
+pub fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    let (header, data) = strip_prefix_ignore_case(uri, "data:")?.split_once(',')?;
+
+    if header.ends_with(";base64") {
+        BASE64.decode(data.trim().as_bytes()).ok()
+    } else {
+        Some(percent_decode(data))
+    }
+}
+
+fn percent_decode(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| data.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
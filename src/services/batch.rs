@@ -0,0 +1,421 @@
+//! batch.rs
+//!
+//! The worker and result storage shared by scans and jobs. Both queue a list
+//! of names that a worker claims and checks in chunks, saving results and
+//! progress together. Results are keyed by the scan or job and the name's
+//! position in its input, so re-running a chunk after a restart is a no-op,
+//! and a batch left `running` by a worker that died is picked up again where
+//! it stopped. Scans and jobs only say where their names come from.
+use crate::config::Config;
+use crate::errors::CocoError;
+use crate::services::ens::check_name_availability;
+use crate::services::multicall::in_background;
+use crate::types::api::{AppState, BlockSelector, CheckNameResponse, NameResult};
+use alloy::primitives::Address;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+// a running batch that hasn't saved progress for this long is taken over
+const STALE_SECS: f64 = 300.0;
+// tries per chunk when the node is unreachable, doubling the wait each time
+const CHUNK_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+// result rows read ahead of a slow download
+const DOWNLOAD_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchKind {
+    Scan,
+    Job,
+}
+
+impl BatchKind {
+    fn table(self) -> &'static str {
+        match self {
+            BatchKind::Scan => "scans",
+            BatchKind::Job => "jobs",
+        }
+    }
+
+    fn results_table(self) -> &'static str {
+        match self {
+            BatchKind::Scan => "scan_results",
+            BatchKind::Job => "job_results",
+        }
+    }
+
+    fn id_column(self) -> &'static str {
+        match self {
+            BatchKind::Scan => "scan_id",
+            BatchKind::Job => "job_id",
+        }
+    }
+}
+
+/// A scan or job a worker has taken on.
+#[derive(sqlx::FromRow)]
+pub struct Claim {
+    pub id: Uuid,
+    pub network: String,
+    pub total: i64,
+    pub checked: i64,
+}
+
+/// What a kind of batch supplies to the shared worker.
+pub trait BatchSource {
+    const KIND: BatchKind;
+
+    // names checked between progress updates
+    fn chunk_size(config: &Config) -> usize;
+
+    // how often the worker looks for queued batches
+    fn poll_interval_secs(config: &Config) -> u64;
+
+    /// Every name in input order, the worker skips those already checked.
+    fn names(
+        state: &AppState,
+        claim: &Claim,
+    ) -> impl Future<Output = Result<Vec<String>, CocoError>> + Send;
+}
+
+#[derive(sqlx::FromRow)]
+struct ResultRow {
+    name: String,
+    available: bool,
+    owner: Option<Vec<u8>>,
+    expires: Option<i64>,
+    base_cost: Option<String>,
+    premium: Option<String>,
+}
+
+/// Works through queued batches of one kind, checking for new ones every
+/// poll interval.
+pub async fn run_batch_worker<S: BatchSource>(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(S::poll_interval_secs(
+        &state.app_config,
+    )));
+
+    loop {
+        interval.tick().await;
+        loop {
+            match claim(&state.connection, S::KIND).await {
+                Ok(Some(claim)) => match run_batch::<S>(&state, &claim).await {
+                    Ok(()) => {}
+                    // left running, so it's claimed again once it goes stale
+                    Err(e) if e.is_transport_error() => {
+                        tracing::warn!(
+                            "{:?} {} paused, node unreachable: {:?}",
+                            S::KIND,
+                            claim.id,
+                            e
+                        );
+                    }
+                    Err(e) => {
+                        tracing::error!("{:?} {} failed: {:?}", S::KIND, claim.id, e);
+                        if let Err(e) = fail(&state.connection, S::KIND, claim.id, &e).await {
+                            tracing::error!(
+                                "Couldn't mark {:?} {} failed: {:?}",
+                                S::KIND,
+                                claim.id,
+                                e
+                            );
+                        }
+                    }
+                },
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Couldn't claim a {:?}: {:?}", S::KIND, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Checks `names` from `start` on, `chunk_size` at a time, handing each
+/// chunk's rows to `save` before the next is checked. An unreachable node is
+/// retried a few times, any other error ends the run.
+pub async fn check_chunks<C, CheckFut, S, SaveFut>(
+    names: &[String],
+    start: usize,
+    chunk_size: usize,
+    retry_delay: Duration,
+    mut check: C,
+    mut save: S,
+) -> Result<(), CocoError>
+where
+    C: FnMut(Vec<String>) -> CheckFut,
+    CheckFut: Future<Output = Result<Vec<CheckNameResponse>, CocoError>>,
+    S: FnMut(usize, Vec<String>, Vec<CheckNameResponse>) -> SaveFut,
+    SaveFut: Future<Output = Result<(), CocoError>>,
+{
+    let mut position = start.min(names.len());
+    for chunk in names[position..].chunks(chunk_size.max(1)) {
+        let mut attempt = 1;
+        let rows = loop {
+            match check(chunk.to_vec()).await {
+                Err(e) if e.is_transport_error() && attempt < CHUNK_ATTEMPTS => {
+                    tracing::warn!("Retrying chunk at {} after {:?}", position, e);
+                    tokio::time::sleep(retry_delay * 2u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                }
+                result => break result?,
+            }
+        };
+
+        save(position, chunk.to_vec(), rows).await?;
+        position += chunk.len();
+    }
+
+    Ok(())
+}
+
+// the oldest pending batch, or a running one whose worker went quiet
+async fn claim(db: &PgPool, kind: BatchKind) -> Result<Option<Claim>, CocoError> {
+    let claim = sqlx::query_as(&format!(
+        r#"
+        UPDATE {table} SET status = 'running', updated_at = now()
+        WHERE id = (
+            SELECT id FROM {table}
+            WHERE status = 'pending'
+                OR (status = 'running' AND updated_at < now() - make_interval(secs => $1))
+            ORDER BY created_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, network, total, checked
+        "#,
+        table = kind.table(),
+    ))
+    .bind(STALE_SECS)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(claim)
+}
+
+async fn run_batch<S: BatchSource>(state: &AppState, claim: &Claim) -> Result<(), CocoError> {
+    let network = state.network(Some(&claim.network))?;
+    let names = S::names(state, claim).await?;
+    let table = S::KIND.table();
+
+    // a scan's duplicates are only known once it's expanded
+    if names.len() as i64 != claim.total {
+        sqlx::query(&format!("UPDATE {table} SET total = $2 WHERE id = $1"))
+            .bind(claim.id)
+            .bind(names.len() as i64)
+            .execute(&state.connection)
+            .await?;
+    }
+
+    let chunk_size = S::chunk_size(&state.app_config).clamp(1, network.multicall.config.max_names);
+    let start = (claim.checked as usize).min(names.len());
+    tracing::info!(
+        "Running {:?} {} from {} of {} names",
+        S::KIND,
+        claim.id,
+        start,
+        names.len()
+    );
+
+    // multicalls take the background permits, interactive lookups keep theirs
+    in_background(check_chunks(
+        &names,
+        start,
+        chunk_size,
+        RETRY_DELAY,
+        |chunk| async move {
+            Ok(
+                check_name_availability(network, &chunk, BlockSelector::Latest)
+                    .await?
+                    .rows,
+            )
+        },
+        |start, chunk, rows| save_chunk(&state.connection, S::KIND, claim.id, start, chunk, rows),
+    ))
+    .await?;
+
+    sqlx::query(&format!(
+        r#"
+        UPDATE {table} SET status = 'completed', updated_at = now(), completed_at = now()
+        WHERE id = $1
+        "#
+    ))
+    .bind(claim.id)
+    .execute(&state.connection)
+    .await?;
+
+    Ok(())
+}
+
+// results and progress commit together so a restart never skips a chunk
+async fn save_chunk(
+    db: &PgPool,
+    kind: BatchKind,
+    id: Uuid,
+    start: usize,
+    names: Vec<String>,
+    rows: Vec<CheckNameResponse>,
+) -> Result<(), CocoError> {
+    let mut tx = db.begin().await?;
+    let available = save_results(&mut *tx, kind, id, start, &names, rows).await?;
+
+    sqlx::query(&format!(
+        r#"
+        UPDATE {table} SET checked = $2, available = available + $3, updated_at = now()
+        WHERE id = $1
+        "#,
+        table = kind.table(),
+    ))
+    .bind(id)
+    .bind((start + names.len()) as i64)
+    .bind(available)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn fail(db: &PgPool, kind: BatchKind, id: Uuid, error: &CocoError) -> Result<(), CocoError> {
+    sqlx::query(&format!(
+        r#"
+        UPDATE {table} SET status = 'failed', error = $2, updated_at = now(), completed_at = now()
+        WHERE id = $1
+        "#,
+        table = kind.table(),
+    ))
+    .bind(id)
+    .bind(error.to_string())
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the checked `rows` for `names`, which start at `start` in the
+/// input. Names that didn't normalise have no row and are skipped. Returns
+/// how many newly stored names are available, a re-run chunk adds none.
+pub async fn save_results<'e>(
+    executor: impl PgExecutor<'e>,
+    kind: BatchKind,
+    id: Uuid,
+    start: usize,
+    names: &[String],
+    rows: Vec<CheckNameResponse>,
+) -> Result<i64, CocoError> {
+    let mut by_name: HashMap<String, CheckNameResponse> =
+        rows.into_iter().map(|r| (r.name.clone(), r)).collect();
+
+    let mut positions = Vec::new();
+    let mut saved_names = Vec::new();
+    let mut available = Vec::new();
+    let mut owners: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut expires: Vec<Option<i64>> = Vec::new();
+    let mut base_costs: Vec<Option<String>> = Vec::new();
+    let mut premiums: Vec<Option<String>> = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let Some(row) = by_name.remove(name) else {
+            continue;
+        };
+        positions.push((start + i) as i64);
+        saved_names.push(row.name);
+        available.push(row.available);
+        owners.push(row.owner.map(|o| o.to_vec()));
+        expires.push(row.expires.and_then(|e| i64::try_from(e).ok()));
+        base_costs.push(row.price.as_ref().map(|p| p.base.to_string()));
+        premiums.push(row.price.as_ref().map(|p| p.premium.to_string()));
+    }
+
+    let newly_available = sqlx::query_scalar(&format!(
+        r#"
+        WITH inserted AS (
+            INSERT INTO {table} ({id_column}, position, name, available, owner, expires_at, base_cost, premium)
+            SELECT $1, r.position, r.name, r.available, r.owner,
+                CASE WHEN r.expires > 0 THEN to_timestamp(r.expires) END,
+                r.base_cost::numeric, r.premium::numeric
+            FROM UNNEST($2::bigint[], $3::varchar[], $4::bool[], $5::bytea[], $6::bigint[], $7::text[], $8::text[])
+                AS r(position, name, available, owner, expires, base_cost, premium)
+            ON CONFLICT DO NOTHING
+            RETURNING available
+        )
+        SELECT count(*) FROM inserted WHERE available
+        "#,
+        table = kind.results_table(),
+        id_column = kind.id_column(),
+    ))
+    .bind(id)
+    .bind(positions)
+    .bind(saved_names)
+    .bind(available)
+    .bind(owners)
+    .bind(expires)
+    .bind(base_costs)
+    .bind(premiums)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(newly_available)
+}
+
+/// Stored results in input order, optionally only the available or only the
+/// taken names. A background task reads them as they're sent on, so a large
+/// download is never held in memory, and stops if the receiver goes away.
+pub fn stream_results(
+    db: PgPool,
+    kind: BatchKind,
+    id: Uuid,
+    available: Option<bool>,
+) -> mpsc::Receiver<Result<NameResult, CocoError>> {
+    let (tx, rx) = mpsc::channel(DOWNLOAD_BUFFER);
+
+    tokio::spawn(async move {
+        let sql = format!(
+            r#"
+            SELECT name, available, owner, extract(epoch FROM expires_at)::bigint AS expires,
+                base_cost::text AS base_cost, premium::text AS premium
+            FROM {table}
+            WHERE {id_column} = $1 AND ($2::bool IS NULL OR available = $2)
+            ORDER BY position
+            "#,
+            table = kind.results_table(),
+            id_column = kind.id_column(),
+        );
+        let mut rows = sqlx::query_as::<_, ResultRow>(&sql)
+            .bind(id)
+            .bind(available)
+            .fetch(&db);
+
+        while let Some(row) = rows.next().await {
+            let row = row.map(name_result).map_err(CocoError::from);
+            let failed = row.is_err();
+            if tx.send(row).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+fn name_result(row: ResultRow) -> NameResult {
+    NameResult {
+        name: row.name,
+        available: row.available,
+        owner: row.owner.map(|o| Address::from_slice(&o)),
+        expiry_date: row.expires.and_then(unix_to_iso),
+        base_cost: row.base_cost,
+        premium: row.premium,
+    }
+}
+
+fn unix_to_iso(secs: i64) -> Option<String> {
+    Some(DateTime::<Utc>::from_timestamp(secs, 0)?.to_rfc3339())
+}
@@ -0,0 +1,133 @@
+//! blocks.rs
+//!
+//! Turns the `at_block` / `at_time` query parameters into a pinned block.
+//! Times are resolved by binary search over block timestamps to the last
+//! block at or before the requested time.
+use crate::errors::CocoError;
+use crate::services::ens::latest_block;
+use crate::types::alloy_providers::AppProvider;
+use crate::types::api::{BlockContext, BlockSelector};
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::future::Future;
+
+/// Validates the historical lookup parameters, at most one may be set.
+pub fn block_selector(
+    at_block: Option<u64>,
+    at_time: Option<&str>,
+) -> Result<BlockSelector, CocoError> {
+    match (at_block, at_time) {
+        (Some(_), Some(_)) => Err(CocoError::InvalidBlock(
+            "Use either at_block or at_time, not both".to_string(),
+        )),
+        (Some(number), None) => Ok(BlockSelector::Number(number)),
+        (None, Some(time)) => parse_time(time).map(BlockSelector::Time),
+        (None, None) => Ok(BlockSelector::Latest),
+    }
+}
+
+/// Accepts unix seconds, a `YYYY-MM-DD` date (midnight UTC) or RFC 3339.
+pub fn parse_time(time: &str) -> Result<u64, CocoError> {
+    let time = time.trim();
+    let parsed = if let Ok(secs) = time.parse::<i64>() {
+        Some(secs)
+    } else if let Ok(date) = NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0).map(|t| t.and_utc().timestamp())
+    } else {
+        DateTime::parse_from_rfc3339(time)
+            .ok()
+            .map(|t| t.timestamp())
+    };
+
+    let secs = parsed.ok_or_else(|| {
+        CocoError::InvalidBlock(format!(
+            "Couldn't read at_time {}, use unix seconds, YYYY-MM-DD or RFC 3339",
+            time
+        ))
+    })?;
+
+    if secs > Utc::now().timestamp() {
+        return Err(CocoError::InvalidBlock(
+            "at_time is in the future".to_string(),
+        ));
+    }
+
+    u64::try_from(secs).map_err(|_| CocoError::InvalidBlock("at_time is before 1970".to_string()))
+}
+
+pub async fn resolve_block(
+    provider: &AppProvider,
+    at: BlockSelector,
+) -> Result<BlockContext, CocoError> {
+    match at {
+        BlockSelector::Latest => latest_block(provider).await,
+        BlockSelector::Number(number) => {
+            let latest = latest_block(provider).await?;
+            if number > latest.number {
+                return Err(CocoError::InvalidBlock(format!(
+                    "Block {} is past the chain head {}",
+                    number, latest.number
+                )));
+            }
+            block_by_number(provider, number).await
+        }
+        BlockSelector::Time(timestamp) => {
+            let latest = latest_block(provider).await?;
+            let genesis = block_by_number(provider, 0).await?;
+            block_at_time(latest, genesis, timestamp, |number| {
+                block_by_number(provider, number)
+            })
+            .await
+        }
+    }
+}
+
+/// Last block with a timestamp at or before `timestamp`, searching between
+/// `genesis` and `latest` with `fetch` for the blocks in between.
+pub async fn block_at_time<F, Fut>(
+    latest: BlockContext,
+    genesis: BlockContext,
+    timestamp: u64,
+    fetch: F,
+) -> Result<BlockContext, CocoError>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<BlockContext, CocoError>>,
+{
+    if timestamp < genesis.timestamp {
+        return Err(CocoError::InvalidBlock(
+            "at_time is before the network's first block".to_string(),
+        ));
+    }
+    if timestamp >= latest.timestamp {
+        return Ok(latest);
+    }
+
+    // invariant: low.timestamp <= timestamp < high.timestamp
+    let mut low = genesis;
+    let mut high = latest;
+    while high.number - low.number > 1 {
+        let mid = fetch(low.number + (high.number - low.number) / 2).await?;
+        if mid.timestamp <= timestamp {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(low)
+}
+
+async fn block_by_number(provider: &AppProvider, number: u64) -> Result<BlockContext, CocoError> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(number))
+        .await?
+        .ok_or(CocoError::BlockNotFound)?;
+
+    Ok(BlockContext {
+        number: block.header.number,
+        hash: block.header.hash,
+        timestamp: block.header.timestamp,
+    })
+}
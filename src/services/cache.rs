@@ -0,0 +1,335 @@
+//! cache.rs
+//!
+//! Per-name response cache keyed by block. An entry is only served for the
+//! block it was read at, so a new block invalidates everything, and entries
+//! also expire after a TTL. Concurrent lookups for the same name at the same
+//! block wait on the first one instead of issuing their own RPC request.
+//! Postgres can be used as a second level shared between instances.
+use crate::config::ResponseCacheConfig;
+use crate::errors::CocoError;
+use crate::services::ens::NormalisedNameData;
+use crate::types::api::BlockContext;
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+pub struct ResponseCache<V> {
+    network: String,
+    // which lookup the values belong to, eg "check" or "expiry"
+    field: &'static str,
+    ttl: Duration,
+    db: Option<PgPool>,
+    inner: Mutex<CacheState<V>>,
+}
+
+struct CacheState<V> {
+    head: u64,
+    entries: HashMap<String, Slot<V>>,
+}
+
+enum Slot<V> {
+    Ready {
+        block: BlockContext,
+        value: V,
+        cached_at: Instant,
+    },
+    // the sender is dropped once the lookup finishes, successful or not
+    Pending {
+        block: BlockContext,
+        done: watch::Receiver<()>,
+    },
+}
+
+impl<V> Slot<V> {
+    fn block(&self) -> BlockContext {
+        match self {
+            Slot::Ready { block, .. } | Slot::Pending { block, .. } => *block,
+        }
+    }
+}
+
+impl<V> ResponseCache<V>
+where
+    V: Clone + Serialize + DeserializeOwned,
+{
+    pub fn new(
+        network: &str,
+        field: &'static str,
+        config: ResponseCacheConfig,
+        db: Option<PgPool>,
+    ) -> Self {
+        Self {
+            network: network.to_string(),
+            field,
+            ttl: Duration::from_secs(config.ttl_secs),
+            db,
+            inner: Mutex::new(CacheState {
+                head: 0,
+                entries: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Returns a value per name at `block`, calling `fetch` with the names
+    /// that aren't cached or already being looked up. `fetch` must return
+    /// rows in the order of the names it was given.
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        block: BlockContext,
+        names: &[NormalisedNameData],
+        fetch: F,
+    ) -> Result<Vec<V>, CocoError>
+    where
+        F: Fn(Vec<NormalisedNameData>) -> Fut,
+        Fut: Future<Output = Result<Vec<V>, CocoError>>,
+    {
+        let mut found: Vec<Option<V>> = vec![None; names.len()];
+        let mut waiting = Vec::new();
+        let mut misses = Vec::new();
+        let (done_tx, done_rx) = watch::channel(());
+
+        {
+            let mut state = self.lock();
+            state.advance(block);
+
+            for (idx, name) in names.iter().enumerate() {
+                match state.entries.get(&name.name) {
+                    Some(Slot::Ready {
+                        block: at,
+                        value,
+                        cached_at,
+                    }) if at.hash == block.hash && cached_at.elapsed() < self.ttl => {
+                        found[idx] = Some(value.clone());
+                    }
+                    Some(Slot::Pending { block: at, done }) if at.hash == block.hash => {
+                        waiting.push((idx, done.clone()));
+                    }
+                    _ => {
+                        state.entries.insert(
+                            name.name.clone(),
+                            Slot::Pending {
+                                block,
+                                done: done_rx.clone(),
+                            },
+                        );
+                        misses.push(idx);
+                    }
+                }
+            }
+        }
+
+        if !misses.is_empty() {
+            let stored = self.load_stored(block, names, &misses).await;
+            misses.retain(|idx| match stored.get(&names[*idx].name) {
+                Some(value) => {
+                    found[*idx] = Some(value.clone());
+                    false
+                }
+                None => true,
+            });
+            self.settle(block, stored.into_iter().collect());
+        }
+
+        if !misses.is_empty() {
+            let batch: Vec<NormalisedNameData> =
+                misses.iter().map(|idx| names[*idx].clone()).collect();
+
+            match fetch(batch).await {
+                Ok(rows) => {
+                    let fetched: Vec<(String, V)> = misses
+                        .iter()
+                        .zip(rows)
+                        .map(|(idx, row)| {
+                            found[*idx] = Some(row.clone());
+                            (names[*idx].name.clone(), row)
+                        })
+                        .collect();
+
+                    self.store(block, &fetched).await;
+                    self.settle(block, fetched);
+                }
+                Err(e) => {
+                    self.abandon(block, names, &misses);
+                    return Err(e);
+                }
+            }
+        }
+
+        // wakes anyone who coalesced onto our lookups
+        drop(done_tx);
+
+        let mut retry = Vec::new();
+        for (idx, mut done) in waiting {
+            let _ = done.changed().await;
+            match self.ready_value(block, &names[idx].name) {
+                Some(value) => found[idx] = Some(value),
+                // the lookup we waited on failed, try it ourselves
+                None => retry.push(idx),
+            }
+        }
+
+        if !retry.is_empty() {
+            let batch: Vec<NormalisedNameData> =
+                retry.iter().map(|idx| names[*idx].clone()).collect();
+            let rows = fetch(batch).await?;
+            let fetched: Vec<(String, V)> = retry
+                .iter()
+                .zip(rows)
+                .map(|(idx, row)| {
+                    found[*idx] = Some(row.clone());
+                    (names[*idx].name.clone(), row)
+                })
+                .collect();
+            self.settle(block, fetched);
+        }
+
+        Ok(found.into_iter().flatten().collect())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState<V>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn ready_value(&self, block: BlockContext, name: &str) -> Option<V> {
+        match self.lock().entries.get(name) {
+            Some(Slot::Ready {
+                block: at, value, ..
+            }) if at.hash == block.hash => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn settle(&self, block: BlockContext, values: Vec<(String, V)>) {
+        let mut state = self.lock();
+        let now = Instant::now();
+
+        for (name, value) in values {
+            // a slower lookup for an older block mustn't clobber newer data
+            if state
+                .entries
+                .get(&name)
+                .is_some_and(|slot| slot.block().number > block.number)
+            {
+                continue;
+            }
+
+            state.entries.insert(
+                name,
+                Slot::Ready {
+                    block,
+                    value,
+                    cached_at: now,
+                },
+            );
+        }
+    }
+
+    fn abandon(&self, block: BlockContext, names: &[NormalisedNameData], misses: &[usize]) {
+        let mut state = self.lock();
+        for idx in misses {
+            let name = &names[*idx].name;
+            if matches!(
+                state.entries.get(name),
+                Some(Slot::Pending { block: at, .. }) if at.hash == block.hash
+            ) {
+                state.entries.remove(name);
+            }
+        }
+    }
+
+    async fn load_stored(
+        &self,
+        block: BlockContext,
+        names: &[NormalisedNameData],
+        misses: &[usize],
+    ) -> HashMap<String, V> {
+        let Some(db) = &self.db else {
+            return HashMap::new();
+        };
+        let wanted: Vec<String> = misses.iter().map(|idx| names[*idx].name.clone()).collect();
+
+        let rows: Result<Vec<(String, serde_json::Value)>, sqlx::Error> = sqlx::query_as(
+            r#"
+            SELECT name, value
+            FROM response_cache
+            WHERE network = $1 AND field = $2 AND name = ANY($3)
+                AND block_hash = $4 AND cached_at > now() - make_interval(secs => $5)
+            "#,
+        )
+        .bind(&self.network)
+        .bind(self.field)
+        .bind(&wanted)
+        .bind(block.hash.as_slice())
+        .bind(self.ttl.as_secs_f64())
+        .fetch_all(db)
+        .await;
+
+        // the shared cache is an optimisation, fall back to the rpc if it's down
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, serde_json::from_value(value).ok()?)))
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Response cache read failed: {:?}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    async fn store(&self, block: BlockContext, values: &[(String, V)]) {
+        let Some(db) = &self.db else {
+            return;
+        };
+
+        let mut names = Vec::with_capacity(values.len());
+        let mut json = Vec::with_capacity(values.len());
+        for (name, value) in values {
+            if let Ok(value) = serde_json::to_value(value) {
+                names.push(name.clone());
+                json.push(value);
+            }
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO response_cache (network, field, name, block_number, block_hash, value, cached_at)
+            SELECT $1, $2, name, $3, $4, value, now()
+            FROM UNNEST($5::text[], $6::jsonb[]) AS t(name, value)
+            ON CONFLICT (network, field, name) DO UPDATE
+            SET block_number = EXCLUDED.block_number,
+                block_hash = EXCLUDED.block_hash,
+                value = EXCLUDED.value,
+                cached_at = EXCLUDED.cached_at
+            WHERE response_cache.block_number <= EXCLUDED.block_number
+            "#,
+        )
+        .bind(&self.network)
+        .bind(self.field)
+        .bind(block.number as i64)
+        .bind(block.hash.as_slice())
+        .bind(&names)
+        .bind(&json)
+        .execute(db)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Response cache write failed: {:?}", e);
+        }
+    }
+}
+
+impl<V> CacheState<V> {
+    // everything read before the new head is stale
+    fn advance(&mut self, block: BlockContext) {
+        if block.number > self.head {
+            self.head = block.number;
+            self.entries
+                .retain(|_, slot| slot.block().number >= block.number);
+        }
+    }
+}
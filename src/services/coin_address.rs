@@ -0,0 +1,198 @@
+//! coin_address.rs
+//!
+//! ENSIP-9 / ENSIP-11 multichain addresses. Resolvers store each coin's
+//! address in its binary form (a script for bitcoin-likes, raw bytes for the
+//! rest), this turns them back into the text form wallets show.
+use alloy::primitives::Address;
+
+pub const ETH_COIN_TYPE: u64 = 60;
+// ENSIP-11: EVM chains use their chain id with the top bit set
+const EVM_COIN_TYPE_FLAG: u64 = 0x8000_0000;
+
+pub struct Coin {
+    pub symbol: &'static str,
+    pub coin_type: u64,
+}
+
+pub const fn evm_coin_type(chain_id: u64) -> u64 {
+    EVM_COIN_TYPE_FLAG | chain_id
+}
+
+/// Coins shown on a profile besides ETH, which has its own `addr` call.
+pub const PROFILE_COINS: [Coin; 8] = [
+    Coin {
+        symbol: "BTC",
+        coin_type: 0,
+    },
+    Coin {
+        symbol: "LTC",
+        coin_type: 2,
+    },
+    Coin {
+        symbol: "DOGE",
+        coin_type: 3,
+    },
+    Coin {
+        symbol: "SOL",
+        coin_type: 501,
+    },
+    Coin {
+        symbol: "OP",
+        coin_type: evm_coin_type(10),
+    },
+    Coin {
+        symbol: "BASE",
+        coin_type: evm_coin_type(8453),
+    },
+    Coin {
+        symbol: "ARB1",
+        coin_type: evm_coin_type(42161),
+    },
+    Coin {
+        symbol: "POL",
+        coin_type: evm_coin_type(137),
+    },
+];
+
+struct BitcoinParams {
+    p2pkh_version: u8,
+    p2sh_version: u8,
+    // segwit human readable part, dogecoin has no segwit
+    hrp: Option<&'static str>,
+}
+
+const BTC: BitcoinParams = BitcoinParams {
+    p2pkh_version: 0x00,
+    p2sh_version: 0x05,
+    hrp: Some("bc"),
+};
+const LTC: BitcoinParams = BitcoinParams {
+    p2pkh_version: 0x30,
+    p2sh_version: 0x32,
+    hrp: Some("ltc"),
+};
+const DOGE: BitcoinParams = BitcoinParams {
+    p2pkh_version: 0x1e,
+    p2sh_version: 0x16,
+    hrp: None,
+};
+
+/// Text form of a resolver's `addr(node, coinType)` bytes, `None` when the
+/// coin isn't supported or the bytes aren't a valid address for it.
+pub fn format_address(coin_type: u64, bytes: &[u8]) -> Option<String> {
+    match coin_type {
+        0 => bitcoin_address(bytes, &BTC),
+        2 => bitcoin_address(bytes, &LTC),
+        3 => bitcoin_address(bytes, &DOGE),
+        501 => (bytes.len() == 32).then(|| bs58::encode(bytes).into_string()),
+        ETH_COIN_TYPE => evm_address(bytes),
+        t if t & EVM_COIN_TYPE_FLAG != 0 => evm_address(bytes),
+        _ => None,
+    }
+}
+
+fn evm_address(bytes: &[u8]) -> Option<String> {
+    // checksummed by Display
+    (bytes.len() == 20).then(|| Address::from_slice(bytes).to_string())
+}
+
+fn bitcoin_address(script: &[u8], params: &BitcoinParams) -> Option<String> {
+    match script {
+        // OP_DUP OP_HASH160 <20> OP_EQUALVERIFY OP_CHECKSIG
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => {
+            Some(base58check(params.p2pkh_version, hash))
+        }
+        // OP_HASH160 <20> OP_EQUAL
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => {
+            Some(base58check(params.p2sh_version, hash))
+        }
+        // OP_0..OP_16 <program>
+        [version, len, program @ ..]
+            if (*version == 0x00 || (0x51..=0x60).contains(version))
+                && *len as usize == program.len()
+                && (2..=40).contains(&program.len()) =>
+        {
+            let witness_version = if *version == 0x00 { 0 } else { version - 0x50 };
+            segwit_address(params.hrp?, witness_version, program)
+        }
+        _ => None,
+    }
+}
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(payload.len() + 1);
+    data.push(version);
+    data.extend_from_slice(payload);
+    bs58::encode(data).with_check().into_string()
+}
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+// BIP-173, and BIP-350 (bech32m) from witness version 1
+fn segwit_address(hrp: &str, witness_version: u8, program: &[u8]) -> Option<String> {
+    let mut data = vec![witness_version];
+    data.extend(convert_bits(program));
+
+    let constant = if witness_version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&data);
+    values.extend([0u8; 6]);
+    let polymod = bech32_polymod(&values) ^ constant;
+
+    let checksum = (0..6).map(|i| ((polymod >> (5 * (5 - i))) & 31) as u8);
+    let encoded: String = data
+        .into_iter()
+        .chain(checksum)
+        .map(|v| BECH32_CHARSET[v as usize] as char)
+        .collect();
+
+    Some(format!("{}1{}", hrp, encoded))
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+
+    values.iter().fold(1u32, |chk, value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+        (0..5)
+            .filter(|i| (top >> i) & 1 == 1)
+            .fold(chk, |chk, i| chk ^ GENERATOR[i])
+    })
+}
+
+// regroups 8 bit bytes into padded 5 bit words
+fn convert_bits(data: &[u8]) -> Vec<u8> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut out = Vec::with_capacity(data.len() * 8 / 5 + 1);
+
+    for byte in data {
+        acc = ((acc << 8) | u32::from(*byte)) & 0xffff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        out.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    out
+}
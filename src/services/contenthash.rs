@@ -0,0 +1,393 @@
+//! contenthash.rs
+//!
+//! ENSIP-7 contenthash codec. A value is a multicodec protocol prefix
+//! followed by the protocol's content id, eg `0xe301` + an IPFS CIDv1. This
+//! turns them into URIs like `ipfs://Qm...` and back into bytes.
+use crate::errors::CocoError;
+use data_encoding::{BASE32_NOPAD_NOCASE, BASE64URL_NOPAD};
+
+// multicodecs inside a CID
+const CID_V1: u64 = 0x01;
+const DAG_PB: u64 = 0x70;
+const LIBP2P_KEY: u64 = 0x72;
+const SWARM_MANIFEST: u64 = 0xfa;
+
+// multihashes
+const IDENTITY: u64 = 0x00;
+const SHA2_256: u64 = 0x12;
+const KECCAK_256: u64 = 0x1b;
+
+const ARWEAVE_TX_LEN: usize = 32;
+const ONION_LEN: usize = 16;
+const ONION3_LEN: usize = 56;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Ipfs,
+    Ipns,
+    Swarm,
+    Arweave,
+    // v2 hidden services, 16 characters
+    Onion,
+    // v3 hidden services, 56 characters
+    Onion3,
+}
+
+impl Protocol {
+    const ALL: [Protocol; 6] = [
+        Protocol::Ipfs,
+        Protocol::Ipns,
+        Protocol::Swarm,
+        Protocol::Arweave,
+        Protocol::Onion,
+        Protocol::Onion3,
+    ];
+
+    fn codec(self) -> u64 {
+        match self {
+            Protocol::Ipfs => 0xe3,
+            Protocol::Ipns => 0xe5,
+            Protocol::Swarm => 0xe4,
+            Protocol::Arweave => 0xb2_9910,
+            Protocol::Onion => 0x01bc,
+            Protocol::Onion3 => 0x01bd,
+        }
+    }
+
+    pub fn scheme(self) -> &'static str {
+        match self {
+            Protocol::Ipfs => "ipfs",
+            Protocol::Ipns => "ipns",
+            Protocol::Swarm => "bzz",
+            Protocol::Arweave => "ar",
+            Protocol::Onion => "onion",
+            Protocol::Onion3 => "onion3",
+        }
+    }
+}
+
+/// URI for a contenthash, eg `ipfs://Qm...` or `bzz://<hex>`.
+pub fn decode(bytes: &[u8]) -> Result<String, CocoError> {
+    if bytes.is_empty() {
+        return Err(invalid("contenthash is empty"));
+    }
+
+    let (codec, value) = read_varint(bytes).map_err(|e| invalid(&format!("protocol {}", e)))?;
+    let protocol = Protocol::ALL
+        .into_iter()
+        .find(|p| p.codec() == codec)
+        .ok_or_else(|| invalid(&format!("unsupported protocol codec 0x{:x}", codec)))?;
+
+    let content = match protocol {
+        Protocol::Ipfs => {
+            let cid = Cid::parse(value).map_err(|e| invalid(&e))?;
+            if cid.codec == DAG_PB && cid.hash == SHA2_256 && cid.digest.len() == 32 {
+                // the familiar Qm... form
+                bs58::encode(cid.multihash).into_string()
+            } else {
+                format!("b{}", base32_lower(value))
+            }
+        }
+        Protocol::Ipns => {
+            let cid = Cid::parse(value).map_err(|e| invalid(&e))?;
+            if cid.hash == IDENTITY && cid.codec != LIBP2P_KEY {
+                // legacy records inline a DNSLink name
+                String::from_utf8(cid.digest.to_vec())
+                    .map_err(|_| invalid("ipns name is not valid utf-8"))?
+            } else if cid.codec == LIBP2P_KEY {
+                format!("k{}", base36_encode(value))
+            } else {
+                format!("b{}", base32_lower(value))
+            }
+        }
+        Protocol::Swarm => {
+            let cid = Cid::parse(value).map_err(|e| invalid(&e))?;
+            if cid.codec != SWARM_MANIFEST || cid.hash != KECCAK_256 || cid.digest.len() != 32 {
+                return Err(invalid(
+                    "swarm contenthash must be a swarm-manifest CID with a keccak-256 hash",
+                ));
+            }
+            alloy::hex::encode(cid.digest)
+        }
+        Protocol::Arweave => {
+            expect_len(protocol, value.len(), ARWEAVE_TX_LEN)?;
+            BASE64URL_NOPAD.encode(value)
+        }
+        Protocol::Onion | Protocol::Onion3 => {
+            let len = if protocol == Protocol::Onion {
+                ONION_LEN
+            } else {
+                ONION3_LEN
+            };
+            expect_len(protocol, value.len(), len)?;
+            let address = std::str::from_utf8(value)
+                .ok()
+                .filter(|a| is_onion_address(a))
+                .ok_or_else(|| invalid("onion address must be base32 characters"))?;
+            address.to_string()
+        }
+    };
+
+    Ok(format!("{}://{}", protocol.scheme(), content))
+}
+
+/// Contenthash bytes for a URI, the inverse of [`decode`].
+pub fn encode(uri: &str) -> Result<Vec<u8>, CocoError> {
+    let uri = uri.trim();
+    let (scheme, value) = uri
+        .split_once("://")
+        .ok_or_else(|| invalid(&format!("{} is not a URI, expected eg ipfs://<cid>", uri)))?;
+    let value = value.trim_end_matches('/');
+    if value.is_empty() {
+        return Err(invalid(&format!("{} has no content id", uri)));
+    }
+
+    let scheme = scheme.to_ascii_lowercase();
+    let mut protocol = Protocol::ALL
+        .into_iter()
+        .find(|p| p.scheme() == scheme)
+        .ok_or_else(|| {
+            let supported: Vec<&str> = Protocol::ALL.iter().map(|p| p.scheme()).collect();
+            invalid(&format!(
+                "unsupported scheme {}, expected one of {}",
+                scheme,
+                supported.join(", ")
+            ))
+        })?;
+
+    let content = match protocol {
+        Protocol::Ipfs => parse_cid_text(value, DAG_PB)?,
+        Protocol::Ipns => {
+            if value.contains('.') {
+                return Err(invalid(
+                    "ipns DNSLink names are no longer supported, use the IPNS key instead",
+                ));
+            }
+            parse_cid_text(value, LIBP2P_KEY)?
+        }
+        Protocol::Swarm => {
+            let hash = alloy::hex::decode(value)
+                .ok()
+                .filter(|h| h.len() == 32)
+                .ok_or_else(|| invalid("swarm hash must be 64 hex characters"))?;
+            let mut cid = Vec::with_capacity(37);
+            for v in [CID_V1, SWARM_MANIFEST, KECCAK_256, 32] {
+                write_varint(&mut cid, v);
+            }
+            cid.extend(hash);
+            cid
+        }
+        Protocol::Arweave => {
+            let tx = BASE64URL_NOPAD
+                .decode(value.as_bytes())
+                .map_err(|_| invalid("arweave transaction id must be base64url"))?;
+            expect_len(protocol, tx.len(), ARWEAVE_TX_LEN)?;
+            tx
+        }
+        Protocol::Onion | Protocol::Onion3 => {
+            let address = value.trim_end_matches(".onion").to_ascii_lowercase();
+            // onion:// is commonly used for v3 addresses too
+            if address.len() == ONION3_LEN {
+                protocol = Protocol::Onion3;
+            }
+            let len = if protocol == Protocol::Onion {
+                ONION_LEN
+            } else {
+                ONION3_LEN
+            };
+            expect_len(protocol, address.len(), len)?;
+            if !is_onion_address(&address) {
+                return Err(invalid("onion address must be base32 characters"));
+            }
+            address.into_bytes()
+        }
+    };
+
+    let mut out = Vec::with_capacity(content.len() + 4);
+    write_varint(&mut out, protocol.codec());
+    out.extend(content);
+    Ok(out)
+}
+
+// a CIDv1 split into its parts
+struct Cid<'a> {
+    codec: u64,
+    multihash: &'a [u8],
+    hash: u64,
+    digest: &'a [u8],
+}
+
+impl<'a> Cid<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self, String> {
+        if bytes.starts_with(&[SHA2_256 as u8, 32]) {
+            return Err("bare CIDv0 multihash, contenthash needs a CIDv1".to_string());
+        }
+
+        let (version, rest) = read_varint(bytes).map_err(|e| format!("CID version {}", e))?;
+        if version != CID_V1 {
+            return Err(format!("unsupported CID version {}", version));
+        }
+        let (codec, multihash) = read_varint(rest).map_err(|e| format!("CID codec {}", e))?;
+        let (hash, rest) = read_varint(multihash).map_err(|e| format!("multihash code {}", e))?;
+        let (len, digest) = read_varint(rest).map_err(|e| format!("multihash length {}", e))?;
+
+        if digest.len() as u64 != len {
+            return Err(format!(
+                "multihash says {} digest bytes, found {}",
+                len,
+                digest.len()
+            ));
+        }
+
+        Ok(Cid {
+            codec,
+            multihash,
+            hash,
+            digest,
+        })
+    }
+}
+
+// CID text to CIDv1 bytes. Bare base58 multihashes (Qm... CIDv0s and peer
+// ids) are wrapped with `codec`, everything else needs a multibase prefix.
+fn parse_cid_text(text: &str, codec: u64) -> Result<Vec<u8>, CocoError> {
+    let bad = |why: &str| invalid(&format!("{} is not a valid CID: {}", text, why));
+
+    if text.starts_with("Qm") || text.starts_with('1') {
+        let multihash = bs58::decode(text)
+            .into_vec()
+            .map_err(|_| bad("base58 decoding failed"))?;
+        let mut cid = Vec::with_capacity(multihash.len() + 2);
+        write_varint(&mut cid, CID_V1);
+        write_varint(&mut cid, codec);
+        cid.extend(multihash);
+        Cid::parse(&cid).map_err(|e| bad(&e))?;
+        return Ok(cid);
+    }
+
+    let mut chars = text.chars();
+    let cid = match chars.next() {
+        Some('b') | Some('B') => BASE32_NOPAD_NOCASE
+            .decode(chars.as_str().as_bytes())
+            .map_err(|_| bad("base32 decoding failed"))?,
+        Some('k') | Some('K') => {
+            base36_decode(chars.as_str()).ok_or_else(|| bad("base36 decoding failed"))?
+        }
+        Some('z') => bs58::decode(chars.as_str())
+            .into_vec()
+            .map_err(|_| bad("base58 decoding failed"))?,
+        Some('f') | Some('F') => {
+            alloy::hex::decode(chars.as_str()).map_err(|_| bad("base16 decoding failed"))?
+        }
+        _ => return Err(bad("unknown multibase prefix")),
+    };
+
+    Cid::parse(&cid).map_err(|e| bad(&e))?;
+    Ok(cid)
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), &'static str> {
+    let mut value: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        // multiformats caps varints at 9 bytes
+        if i == 9 {
+            return Err("varint is too long");
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+
+    Err("varint is truncated")
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn base32_lower(data: &[u8]) -> String {
+    BASE32_NOPAD_NOCASE.encode(data).to_ascii_lowercase()
+}
+
+const BASE36_ALPHABET: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// big number base conversion, leading zero bytes become leading '0's
+fn base36_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // little endian base 36 digits
+    let mut digits: Vec<u32> = Vec::new();
+    for byte in &data[zeros..] {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += *digit << 8;
+            *digit = carry % 36;
+            carry /= 36;
+        }
+        while carry > 0 {
+            digits.push(carry % 36);
+            carry /= 36;
+        }
+    }
+
+    std::iter::repeat_n('0', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|d| BASE36_ALPHABET[*d as usize] as char),
+        )
+        .collect()
+}
+
+fn base36_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.to_ascii_lowercase();
+    let zeros = text.bytes().take_while(|c| *c == b'0').count();
+    // little endian bytes
+    let mut bytes: Vec<u32> = Vec::new();
+    for c in text.bytes().skip(zeros) {
+        let mut carry = BASE36_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte * 36;
+            *byte = carry & 0xff;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry & 0xff);
+            carry >>= 8;
+        }
+    }
+
+    Some(
+        std::iter::repeat_n(0u8, zeros)
+            .chain(bytes.iter().rev().map(|b| *b as u8))
+            .collect(),
+    )
+}
+
+fn is_onion_address(address: &str) -> bool {
+    address
+        .bytes()
+        .all(|c| c.is_ascii_lowercase() || (b'2'..=b'7').contains(&c))
+}
+
+fn expect_len(protocol: Protocol, found: usize, expected: usize) -> Result<(), CocoError> {
+    if found == expected {
+        return Ok(());
+    }
+
+    Err(invalid(&format!(
+        "{} content should be {} bytes, found {}",
+        protocol.scheme(),
+        expected,
+        found
+    )))
+}
+
+fn invalid(reason: &str) -> CocoError {
+    CocoError::InvalidContenthash(reason.to_string())
+}
@@ -1,33 +1,89 @@
 use crate::errors::CocoError;
+use crate::services::blocks::resolve_block;
+use crate::services::coin_address::{PROFILE_COINS, format_address};
+use crate::services::contenthash;
 use crate::services::ens::BaseRegistrarImplementation::BaseRegistrarImplementationInstance;
+use crate::services::ens::ENSRegistry::ENSRegistryInstance;
 use crate::services::ens::ETHRegistrarController::ETHRegistrarControllerInstance;
+use crate::services::ens::PublicResolver::PublicResolverInstance;
+use crate::services::multicall::{Multicall, chunked};
 use crate::types::{
     alloy_providers::AppProvider,
-    api::{AppState, CheckExpiryResponse, CheckNameResponse, PriceResponse},
+    api::{
+        AtBlock, BlockContext, BlockSelector, CheckExpiryResponse, CheckNameResponse, CoinAddress,
+        NetworkState, PriceResponse, ProfileResponse, QuorumResult,
+    },
+    ens::{ContractConstants, EnsContractAddresses},
 };
 use alloy::{
-    primitives::{Address, B256, FixedBytes, U256, keccak256},
+    eips::{BlockId, BlockNumberOrTag},
+    hex,
+    primitives::{Address, B256, Bytes, FixedBytes, U256, keccak256},
     providers::{MulticallError, Network, Provider},
     sol,
 };
 use alloy_ens::namehash;
+use futures::future::try_join_all;
+use std::collections::BTreeMap;
+
+// text records shown on a profile card
+pub const PROFILE_TEXT_KEYS: [&str; 5] =
+    ["avatar", "url", "com.twitter", "com.github", "description"];
 
 // ENS REGISTRY
-// sol! {
-//     #[allow(missing_docs)]
-//     #[sol(rpc)]
-//     contract ENSRegistry {
-//         function owner(bytes32 node) public view returns (address);
-//     }
-// }
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract ENSRegistry {
+        function owner(bytes32 node) public view returns (address);
+        function resolver(bytes32 node) public view returns (address);
+
+        event NewOwner(bytes32 indexed node, bytes32 indexed label, address owner);
+        event Transfer(bytes32 indexed node, address owner);
+        event NewResolver(bytes32 indexed node, address resolver);
+        event NewTTL(bytes32 indexed node, uint64 ttl);
+    }
+}
+
+// NAME WRAPPER
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract NameWrapper {
+        event NameWrapped(bytes32 indexed node, bytes name, address owner, uint32 fuses, uint64 expiry);
+        event NameUnwrapped(bytes32 indexed node, address owner);
+        event FusesSet(bytes32 indexed node, uint32 fuses);
+        event ExpiryExtended(bytes32 indexed node, uint64 expiry);
+        event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value);
+    }
+}
+
+// PUBLIC RESOLVER
+// addr is overloaded, alloy names the ETH one addr_0 and the ENSIP-9 one addr_1
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract PublicResolver {
+        function addr(bytes32 node) external view returns (address);
+        function addr(bytes32 node, uint256 coinType) external view returns (bytes memory);
+        function text(bytes32 node, string calldata key) external view returns (string memory);
+        function contenthash(bytes32 node) external view returns (bytes memory);
+        function name(bytes32 node) external view returns (string memory);
+    }
+}
 
 // BASE REGISTRY
 sol! {
     #[allow(missing_docs)]
     #[sol(rpc)]
     contract BaseRegistrarImplementation {
+        function GRACE_PERIOD() external view returns (uint256);
         function nameExpires(uint256 id) external view returns (uint256);
         function ownerOf(uint256 tokenId) external view returns (address);
+
+        event NameRegistered(uint256 indexed id, address indexed owner, uint256 expires);
+        event NameRenewed(uint256 indexed id, uint256 expires);
+        event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
     }
 }
 
@@ -46,46 +102,196 @@ sol! {
 
     function rentPrice(string calldata label, uint256 duration) public view returns (Price price);
 
+    function prices() external view returns (address);
+
+    function MIN_REGISTRATION_DURATION() external view returns (uint256);
+
+    function minCommitmentAge() external view returns (uint256);
+
+    function maxCommitmentAge() external view returns (uint256);
+
+    event NameRegistered(string name, bytes32 indexed label, address indexed owner, uint256 baseCost, uint256 premium, uint256 expires);
+    event NameRenewed(string name, bytes32 indexed label, uint256 cost, uint256 expires);
+
+    }
+}
+
+// PRICE ORACLE (StablePriceOracle / ExponentialPremiumPriceOracle)
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract PriceOracle {
+        function usdOracle() external view returns (address);
+    }
+}
+
+// CHAINLINK ETH/USD FEED
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract AggregatorInterface {
+        function latestAnswer() external view returns (int256);
     }
 }
 
 pub async fn check_name_availability(
-    state: &AppState,
+    network: &NetworkState,
     names: &[String],
-) -> Result<Vec<CheckNameResponse>, CocoError> {
-    let mut normalised_names = Vec::new();
-    let mut failures = 0;
-
-    // normalise names
-    for name in names.into_iter() {
-        match normalise_and_hash_name(&name) {
-            Ok(data) => normalised_names.push(data),
-            Err(_) => failures += 1,
+    at: BlockSelector,
+) -> Result<AtBlock<CheckNameResponse>, CocoError> {
+    check_name_limit(network, names)?;
+    let normalised_names = normalise_names(names, &network.tld);
+
+    // block lookup and reads share one endpoint so the pinned block exists there
+    network
+        .pool
+        .execute(|provider| {
+            let normalised_names = &normalised_names;
+            async move {
+                let block = resolve_block(&provider, at).await?;
+                // only the head is worth caching, history would evict it
+                if at != BlockSelector::Latest {
+                    return availability_at(&provider, network, normalised_names, block).await;
+                }
+
+                let rows = network
+                    .check_cache
+                    .get_or_fetch(block, normalised_names, |misses| {
+                        let provider = &provider;
+                        async move {
+                            let fetched =
+                                availability_at(provider, network, &misses, block).await?;
+                            Ok(fetched.rows)
+                        }
+                    })
+                    .await?;
+
+                Ok(AtBlock { block, rows })
+            }
+        })
+        .await
+}
+
+pub async fn check_name_expiry(
+    network: &NetworkState,
+    names: &[String],
+    at: BlockSelector,
+) -> Result<AtBlock<CheckExpiryResponse>, CocoError> {
+    check_name_limit(network, names)?;
+    let normalised_names = normalise_names(names, &network.tld);
+
+    network
+        .pool
+        .execute(|provider| {
+            let normalised_names = &normalised_names;
+            async move {
+                let block = resolve_block(&provider, at).await?;
+                if at != BlockSelector::Latest {
+                    return expiry_at(&provider, network, normalised_names, block).await;
+                }
+
+                let rows = network
+                    .expiry_cache
+                    .get_or_fetch(block, normalised_names, |misses| {
+                        let provider = &provider;
+                        async move {
+                            let fetched = expiry_at(provider, network, &misses, block).await?;
+                            Ok(fetched.rows)
+                        }
+                    })
+                    .await?;
+
+                Ok(AtBlock { block, rows })
+            }
+        })
+        .await
+}
+
+/// Runs the availability check against `quorum_size` providers at the same
+/// block and only answers when they all agree.
+pub async fn check_name_availability_quorum(
+    network: &NetworkState,
+    names: &[String],
+    at: BlockSelector,
+) -> Result<AtBlock<CheckNameResponse>, CocoError> {
+    check_name_limit(network, names)?;
+    let normalised_names = normalise_names(names, &network.tld);
+    let providers = network.pool.quorum_providers()?;
+
+    let block = match at {
+        BlockSelector::Latest => {
+            // pin to the lowest head so every provider has the block
+            let heads = try_join_all(providers.iter().map(|(_, p)| p.get_block_number())).await?;
+            let number = heads.into_iter().min().ok_or(CocoError::BlockNotFound)?;
+            let header = providers[0]
+                .1
+                .get_block_by_number(BlockNumberOrTag::Number(number))
+                .await?
+                .ok_or(CocoError::BlockNotFound)?;
+            BlockContext {
+                number,
+                hash: header.header.hash,
+                timestamp: header.header.timestamp,
+            }
         }
+        at => resolve_block(&providers[0].1, at).await?,
+    };
+    let number = block.number;
+
+    let answers = try_join_all(
+        providers
+            .iter()
+            .map(|(_, p)| availability_at(p, network, &normalised_names, block)),
+    )
+    .await?;
+
+    if answers.windows(2).all(|pair| pair[0].rows == pair[1].rows) {
+        return Ok(answers
+            .into_iter()
+            .next()
+            .expect("quorum_size is at least one"));
     }
 
+    Err(CocoError::QuorumMismatch {
+        block: number,
+        results: providers
+            .into_iter()
+            .zip(answers)
+            .map(|((endpoint, _), answer)| QuorumResult {
+                endpoint,
+                values: answer.rows,
+            })
+            .collect(),
+    })
+}
+
+async fn availability_at(
+    provider: &AppProvider,
+    network: &NetworkState,
+    normalised_names: &[NormalisedNameData],
+    block: BlockContext,
+) -> Result<AtBlock<CheckNameResponse>, CocoError> {
     let labels: Vec<String> = normalised_names.iter().map(|n| n.label.clone()).collect();
-    let namehashes: Vec<B256> = normalised_names.iter().map(|n| n.name_hash).collect();
     let labelhashes: Vec<B256> = normalised_names.iter().map(|n| n.label_hash).collect();
 
-    // let ens_registry = ENSRegistry::new(state.ens_contract_addresses.ens_registry, &state.provider);
-    let base_registrar = BaseRegistrarImplementation::new(
-        state.ens_contract_addresses.base_registrar,
-        &state.provider,
-    );
+    // let ens_registry = ENSRegistry::new(network.ens_contract_addresses.ens_registry, provider);
+    let base_registrar =
+        BaseRegistrarImplementation::new(network.ens_contract_addresses.base_registrar, provider);
     let controller = ETHRegistrarController::new(
-        state.ens_contract_addresses.registrar_controller,
-        &state.provider,
+        network.ens_contract_addresses.registrar_controller,
+        provider,
     );
 
-    // for availability_result in
+    // every multicall reads the same block so the results agree with each other
+    let block_id = BlockId::number(block.number);
+    let multicall = &network.multicall;
+
     let (availabilities, prices, owners, expiries) = tokio::try_join!(
-        fetch_availability(&state.provider, &controller, &labels),
-        fetch_rent_prices(&state.provider, &controller, &labels),
-        fetch_owners(&state.provider, &base_registrar, &labelhashes),
-        fetch_expires(&state.provider, &base_registrar, &labelhashes)
-    )
-    .map_err(|e| CocoError::Ens(e))?;
+        fetch_availability(provider, &controller, &labels, block_id, multicall),
+        fetch_rent_prices(provider, &controller, &labels, block_id, multicall),
+        fetch_owners(provider, &base_registrar, &labelhashes, block_id, multicall),
+        fetch_expires(provider, &base_registrar, &labelhashes, block_id, multicall)
+    )?;
 
     // format response for handler
     let mut out = Vec::with_capacity(normalised_names.len());
@@ -104,47 +310,38 @@ pub async fn check_name_availability(
             } else {
                 None
             },
-            owner: if available { None } else { Some(owners[i]) },
+            owner: if available { None } else { owners[i] },
             expires: if available { None } else { Some(expiries[i]) },
         })
     }
 
-    Ok(out)
+    Ok(AtBlock { block, rows: out })
 }
 
-pub async fn check_name_expiry(
-    state: &AppState,
-    names: &[String],
-) -> Result<Vec<CheckExpiryResponse>, CocoError> {
-    let mut normalised_names = Vec::new();
-    let mut failures = 0;
-
-    // normalise names
-    for name in names.into_iter() {
-        match normalise_and_hash_name(&name) {
-            Ok(data) => normalised_names.push(data),
-            Err(_) => failures += 1,
-        }
-    }
-
+async fn expiry_at(
+    provider: &AppProvider,
+    network: &NetworkState,
+    normalised_names: &[NormalisedNameData],
+    block: BlockContext,
+) -> Result<AtBlock<CheckExpiryResponse>, CocoError> {
     let labels: Vec<String> = normalised_names.iter().map(|n| n.label.clone()).collect();
     let labelhashes: Vec<B256> = normalised_names.iter().map(|n| n.label_hash).collect();
 
-    let base_registrar = BaseRegistrarImplementation::new(
-        state.ens_contract_addresses.base_registrar,
-        &state.provider,
-    );
+    let base_registrar =
+        BaseRegistrarImplementation::new(network.ens_contract_addresses.base_registrar, provider);
 
     let controller = ETHRegistrarController::new(
-        state.ens_contract_addresses.registrar_controller,
-        &state.provider,
+        network.ens_contract_addresses.registrar_controller,
+        provider,
     );
 
+    let block_id = BlockId::number(block.number);
+    let multicall = &network.multicall;
+
     let (availabilities, expiries) = tokio::try_join!(
-        fetch_availability(&state.provider, &controller, &labels),
-        fetch_expires(&state.provider, &base_registrar, &labelhashes)
-    )
-    .map_err(|e| CocoError::Ens(e))?;
+        fetch_availability(provider, &controller, &labels, block_id, multicall),
+        fetch_expires(provider, &base_registrar, &labelhashes, block_id, multicall)
+    )?;
 
     let mut out = Vec::with_capacity(normalised_names.len());
 
@@ -158,29 +355,294 @@ pub async fn check_name_expiry(
         })
     }
 
-    Ok(out)
+    Ok(AtBlock { block, rows: out })
+}
+
+/// Reads the deployment's timing constants in one multicall.
+pub async fn load_contract_constants(
+    provider: &AppProvider,
+    addresses: &EnsContractAddresses,
+) -> Result<ContractConstants, CocoError> {
+    let base_registrar = BaseRegistrarImplementation::new(addresses.base_registrar, provider);
+    let controller = ETHRegistrarController::new(addresses.registrar_controller, provider);
+
+    let (grace_period, min_duration) = provider
+        .multicall()
+        .add(base_registrar.GRACE_PERIOD())
+        .add(controller.MIN_REGISTRATION_DURATION())
+        .aggregate()
+        .await?;
+
+    // controllers without commit/reveal (Basenames) revert on these
+    let (min_commitment_age, max_commitment_age) = provider
+        .multicall()
+        .add(controller.minCommitmentAge())
+        .add(controller.maxCommitmentAge())
+        .try_aggregate(false)
+        .await?;
+
+    Ok(ContractConstants {
+        grace_period_secs: grace_period.saturating_to(),
+        min_registration_duration_secs: min_duration.saturating_to(),
+        min_commitment_age_secs: min_commitment_age.ok().map(|age| age.saturating_to()),
+        max_commitment_age_secs: max_commitment_age.ok().map(|age| age.saturating_to()),
+    })
+}
+
+/// Re-reads the contract constants and swaps them into the network's cache.
+pub async fn refresh_contract_constants(
+    network: &NetworkState,
+) -> Result<ContractConstants, CocoError> {
+    let addresses = &network.ens_contract_addresses;
+    let constants = network
+        .pool
+        .execute(|provider| async move { load_contract_constants(&provider, addresses).await })
+        .await?;
+
+    *network
+        .contract_constants
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = constants;
+
+    tracing::info!(
+        "Contract constants refreshed for {}: {:?}",
+        network.name,
+        constants
+    );
+    Ok(constants)
+}
+
+/// Number and timestamp of the latest block, used to pin reads and as "now".
+pub async fn latest_block(provider: &AppProvider) -> Result<BlockContext, CocoError> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .await?
+        .ok_or(CocoError::BlockNotFound)?;
+
+    Ok(BlockContext {
+        number: block.header.number,
+        hash: block.header.hash,
+        timestamp: block.header.timestamp,
+    })
+}
+
+/// Resolver records, multichain addresses and primary name status for one
+/// name, every read pinned to the same block.
+pub async fn fetch_profile(
+    network: &NetworkState,
+    name: &str,
+) -> Result<ProfileResponse, CocoError> {
+    let normalised = normalise_and_hash_name_for(name, &network.tld)?;
+
+    network
+        .pool
+        .execute(|provider| {
+            let normalised = &normalised;
+            async move { profile_at(&provider, network, normalised).await }
+        })
+        .await
+}
+
+async fn profile_at(
+    provider: &AppProvider,
+    network: &NetworkState,
+    normalised: &NormalisedNameData,
+) -> Result<ProfileResponse, CocoError> {
+    let block = latest_block(provider).await?;
+    let block_id = BlockId::number(block.number);
+    let multicall = &network.multicall;
+    let node = normalised.name_hash;
+
+    let registry = ENSRegistry::new(network.ens_contract_addresses.ens_registry, provider);
+    let resolver_address = registry.resolver(node).block(block_id).call().await?;
+
+    let mut profile = ProfileResponse {
+        name: normalised.name.clone(),
+        node,
+        block_number: block.number,
+        resolver: None,
+        address: None,
+        addresses: Vec::new(),
+        texts: BTreeMap::new(),
+        contenthash: None,
+        content_uri: None,
+        primary_name: None,
+        is_primary: false,
+    };
+    if resolver_address.is_zero() {
+        return Ok(profile);
+    }
+
+    let resolver = PublicResolver::new(resolver_address, provider);
+    let coin_types: Vec<u64> = PROFILE_COINS.iter().map(|c| c.coin_type).collect();
+
+    let ((address, contenthash), coin_addresses, texts) = tokio::try_join!(
+        fetch_resolver_records(provider, &resolver, node, block_id),
+        fetch_coin_addresses(provider, &resolver, node, &coin_types, block_id, multicall),
+        fetch_texts(
+            provider,
+            &resolver,
+            node,
+            &PROFILE_TEXT_KEYS,
+            block_id,
+            multicall
+        )
+    )?;
+
+    let address = address.filter(|a| !a.is_zero());
+    if let Some(address) = address {
+        profile.primary_name = fetch_primary_name(provider, &registry, address, block_id).await?;
+    }
+
+    profile.resolver = Some(resolver_address);
+    profile.address = address;
+    profile.addresses = PROFILE_COINS
+        .iter()
+        .zip(coin_addresses)
+        .filter_map(|(coin, bytes)| {
+            Some(CoinAddress {
+                coin: coin.symbol,
+                coin_type: coin.coin_type,
+                address: format_address(coin.coin_type, &bytes?)?,
+            })
+        })
+        .collect();
+    profile.texts = PROFILE_TEXT_KEYS
+        .iter()
+        .zip(texts)
+        .filter_map(|(key, value)| Some((key.to_string(), value.filter(|v| !v.is_empty())?)))
+        .collect();
+    if let Some(contenthash) = contenthash.filter(|c| !c.is_empty()) {
+        profile.content_uri = contenthash::decode(&contenthash).ok();
+        profile.contenthash = Some(hex::encode_prefixed(&contenthash));
+    }
+    profile.is_primary = profile.primary_name.as_deref() == Some(normalised.name.as_str());
+
+    Ok(profile)
+}
+
+// resolvers that don't implement a record revert, which reads as unset
+async fn fetch_resolver_records<P, N>(
+    provider: &AppProvider,
+    resolver: &PublicResolverInstance<P, N>,
+    node: B256,
+    block: BlockId,
+) -> Result<(Option<Address>, Option<Bytes>), MulticallError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    let (address, contenthash) = provider
+        .multicall()
+        .block(block)
+        .add(resolver.addr_0(node))
+        .add(resolver.contenthash(node))
+        .try_aggregate(false)
+        .await?;
+
+    Ok((address.ok(), contenthash.ok()))
+}
+
+async fn fetch_coin_addresses<P, N>(
+    provider: &AppProvider,
+    resolver: &PublicResolverInstance<P, N>,
+    node: B256,
+    coin_types: &[u64],
+    block: BlockId,
+    multicall: &Multicall,
+) -> Result<Vec<Option<Bytes>>, MulticallError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    chunked(coin_types, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for coin_type in chunk {
+            multicall = multicall.add_dynamic(resolver.addr_1(node, U256::from(*coin_type)));
+        }
+        let addresses = multicall.try_aggregate(false).await?;
+        Ok(addresses.into_iter().map(Result::ok).collect())
+    })
+    .await
+}
+
+async fn fetch_texts<P, N>(
+    provider: &AppProvider,
+    resolver: &PublicResolverInstance<P, N>,
+    node: B256,
+    keys: &[&str],
+    block: BlockId,
+    multicall: &Multicall,
+) -> Result<Vec<Option<String>>, MulticallError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    chunked(keys, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for key in chunk {
+            multicall = multicall.add_dynamic(resolver.text(node, key.to_string()));
+        }
+        let texts = multicall.try_aggregate(false).await?;
+        Ok(texts.into_iter().map(Result::ok).collect())
+    })
+    .await
+}
+
+// the reverse record of `address`, read from <hex address>.addr.reverse
+async fn fetch_primary_name<P, N>(
+    provider: &AppProvider,
+    registry: &ENSRegistryInstance<P, N>,
+    address: Address,
+    block: BlockId,
+) -> Result<Option<String>, CocoError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    let reverse_node = namehash(&format!("{}.addr.reverse", hex::encode(address)));
+    let reverse_resolver = registry.resolver(reverse_node).block(block).call().await?;
+    if reverse_resolver.is_zero() {
+        return Ok(None);
+    }
+
+    let name = PublicResolver::new(reverse_resolver, provider)
+        .name(reverse_node)
+        .block(block)
+        .call()
+        .await
+        .ok();
+
+    Ok(name.filter(|n| !n.is_empty()))
 }
 
 async fn fetch_availability<P, N>(
     provider: &AppProvider,
     controller: &ETHRegistrarControllerInstance<P, N>,
-    labels: &Vec<String>,
+    labels: &[String],
+    block: BlockId,
+    multicall: &Multicall,
 ) -> Result<Vec<bool>, MulticallError>
 where
     P: Provider<N>,
     N: Network,
 {
-    let mut multicall = provider.multicall().dynamic();
-    for label in labels {
-        multicall = multicall.add_dynamic(controller.available(label.to_string()));
-    }
-    multicall.aggregate().await
+    chunked(labels, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for label in chunk {
+            multicall = multicall.add_dynamic(controller.available(label.to_string()));
+        }
+        multicall.aggregate().await
+    })
+    .await
 }
 
 async fn fetch_rent_prices<P, N>(
     provider: &AppProvider,
     controller: &ETHRegistrarControllerInstance<P, N>,
-    labels: &Vec<String>,
+    labels: &[String],
+    block: BlockId,
+    multicall: &Multicall,
 ) -> Result<Vec<ETHRegistrarController::Price>, MulticallError>
 where
     P: Provider<N>,
@@ -188,56 +650,97 @@ where
 {
     let duration = U256::from(365u64 * 24 * 60 * 60); // example: 1 year in seconds
 
-    let mut multicall = provider.multicall().dynamic();
-    for label in labels {
-        multicall = multicall.add_dynamic(controller.rentPrice(label.to_string(), duration));
-    }
-
-    multicall.aggregate().await
+    chunked(labels, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for label in chunk {
+            multicall = multicall.add_dynamic(controller.rentPrice(label.to_string(), duration));
+        }
+        multicall.aggregate().await
+    })
+    .await
 }
 
 async fn fetch_owners<P, N>(
     provider: &AppProvider,
     base_registrar: &BaseRegistrarImplementationInstance<P, N>,
     label_hashes: &[B256],
-) -> Result<Vec<Address>, MulticallError>
+    block: BlockId,
+    multicall: &Multicall,
+) -> Result<Vec<Option<Address>>, MulticallError>
 where
     P: Provider<N>,
     N: Network,
 {
-    let mut multicall = provider.multicall().dynamic();
-    for label_hash in label_hashes {
-        let id = U256::from_be_bytes(**label_hash);
-        multicall = multicall.add_dynamic(base_registrar.ownerOf(id));
-    }
-    multicall.aggregate().await
+    chunked(label_hashes, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for label_hash in chunk {
+            let id = U256::from_be_bytes(**label_hash);
+            multicall = multicall.add_dynamic(base_registrar.ownerOf(id));
+        }
+        // ownerOf reverts for expired and never registered names, which
+        // would otherwise fail the whole batch
+        let owners = multicall.try_aggregate(false).await?;
+        Ok(owners.into_iter().map(Result::ok).collect())
+    })
+    .await
 }
 
 async fn fetch_expires<P, N>(
     provider: &AppProvider,
     base_registrar: &BaseRegistrarImplementationInstance<P, N>,
     label_hashes: &[B256],
+    block: BlockId,
+    multicall: &Multicall,
 ) -> Result<Vec<U256>, MulticallError>
 where
     P: Provider<N>,
     N: Network,
 {
-    let mut multicall = provider.multicall().dynamic();
-    for label_hash in label_hashes {
-        let id = U256::from_be_bytes(**label_hash);
-        multicall = multicall.add_dynamic(base_registrar.nameExpires(id));
+    chunked(label_hashes, multicall, |chunk| async move {
+        let mut multicall = provider.multicall().dynamic().block(block);
+        for label_hash in chunk {
+            let id = U256::from_be_bytes(**label_hash);
+            multicall = multicall.add_dynamic(base_registrar.nameExpires(id));
+        }
+        multicall.aggregate().await
+    })
+    .await
+}
+
+pub(crate) fn check_name_limit(network: &NetworkState, names: &[String]) -> Result<(), CocoError> {
+    let limit = network.multicall.config.max_names;
+    if names.len() > limit {
+        return Err(CocoError::TooManyNames {
+            requested: names.len(),
+            limit,
+        });
     }
-    multicall.aggregate().await
+
+    Ok(())
+}
+
+// names that fail to normalise are dropped from the batch
+pub(crate) fn normalise_names(names: &[String], tld: &str) -> Vec<NormalisedNameData> {
+    names
+        .iter()
+        .filter_map(|name| normalise_and_hash_name_for(name, tld).ok())
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct NormalisedNameData {
+    pub name: String,
+    pub label: String,
+    pub name_hash: FixedBytes<32>,
+    pub label_hash: FixedBytes<32>,
 }
 
-struct NormalisedNameData {
-    name: String,
-    label: String,
-    name_hash: FixedBytes<32>,
-    label_hash: FixedBytes<32>,
+pub fn normalise_and_hash_name(name: &str) -> Result<NormalisedNameData, CocoError> {
+    normalise_and_hash_name_for(name, "eth")
 }
 
-pub fn normalise_and_hash_name(name: &String) -> Result<NormalisedNameData, CocoError> {
+/// Normalises a 2LD under `tld`, eg `alice` -> `alice.eth` or `alice.base.eth`.
+pub fn normalise_and_hash_name_for(name: &str, tld: &str) -> Result<NormalisedNameData, CocoError> {
     // if name is empty
     if name.trim().is_empty() {
         return Err(CocoError::InvalidName("No name provided".to_string()));
@@ -245,35 +748,34 @@ pub fn normalise_and_hash_name(name: &String) -> Result<NormalisedNameData, Coco
 
     // get label and extension
     let mut trimmed_name = name.trim().to_lowercase();
+    let suffix = format!(".{}", tld);
 
-    if !trimmed_name.ends_with(".eth") {
-        trimmed_name = trimmed_name + ".eth";
+    if !trimmed_name.ends_with(&suffix) {
+        trimmed_name += &suffix;
     }
 
-    let label_and_ext = trimmed_name.split(".").collect::<Vec<&str>>();
+    let label = &trimmed_name[..trimmed_name.len() - suffix.len()];
 
     // currently support only 2LD eg alice.eth
-    if label_and_ext.len() > 2 {
-        return Err(CocoError::InvalidName(
-            format!("Only 2LD names supported currently. {} is invalid", name).to_string(),
-        ));
+    if label.contains('.') {
+        return Err(CocoError::InvalidName(format!(
+            "Only 2LD names supported currently. {} is invalid",
+            name
+        )));
     }
 
-    let label = label_and_ext[0];
-    let ext = label_and_ext[1];
-
-    if label.trim().is_empty() || ext.to_lowercase() != "eth" {
-        return Err(CocoError::InvalidName(
-            format!("Something wrong with {}. Can't seem to normalise it", name).to_string(),
-        ));
+    if label.trim().is_empty() {
+        return Err(CocoError::InvalidName(format!(
+            "Something wrong with {}. Can't seem to normalise it",
+            name
+        )));
     }
 
-    let name_hash = namehash(&name);
+    let name_hash = namehash(&trimmed_name);
     let label_hash = keccak256(label.as_bytes());
 
-    println!("{:?}", trimmed_name);
     Ok(NormalisedNameData {
-        name: trimmed_name.to_string(),
+        name: trimmed_name.clone(),
         label: label.to_string(),
         name_hash,
         label_hash,
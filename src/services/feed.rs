@@ -0,0 +1,191 @@
+//! feed.rs
+//!
+//! Expiring-soon and recently-released names, read from the indexer's
+//! registration and renewal tables. A name's current expiry is its latest
+//! registration or renewal, so re-registered names drop out of the released
+//! feed by themselves and reorg rollbacks need no extra bookkeeping. Names
+//! with no entry in the labels table can't be shown and are skipped.
+use crate::errors::CocoError;
+use crate::services::indexer::indexed_block;
+use crate::types::api::{AppState, FeedQuery, NetworkState};
+use alloy::primitives::B256;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+const MAX_WINDOW_DAYS: u32 = 365;
+const DEFAULT_EXPIRING_WINDOW_DAYS: u32 = 30;
+const DEFAULT_RELEASED_WINDOW_DAYS: u32 = 7;
+const WEI_PER_ETH: f64 = 1e18;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedKind {
+    // expiry is within the window ahead
+    Expiring,
+    // grace period ended within the window behind
+    Released,
+}
+
+#[derive(Debug)]
+pub struct FeedEntry {
+    pub name: String,
+    pub expires: i64,
+    // highest base + premium paid for the name, in wei
+    pub max_cost: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct FeedPage {
+    // last block the indexer has processed, so callers can tell how fresh this is
+    pub indexed_block: Option<u64>,
+    pub rows: Vec<FeedEntry>,
+    pub next_cursor: Option<String>,
+}
+
+/// Position after the last row of a page, as `<expiry secs>-<label hash>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeedCursor {
+    pub expires: i64,
+    pub label_hash: B256,
+}
+
+impl FeedCursor {
+    pub fn encode(&self) -> String {
+        format!("{}-{}", self.expires, self.label_hash)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, CocoError> {
+        let (expires, label_hash) = cursor.split_once('-').ok_or(CocoError::InvalidQueryInput)?;
+
+        Ok(Self {
+            expires: expires.parse().map_err(|_| CocoError::InvalidQueryInput)?,
+            label_hash: label_hash
+                .parse()
+                .map_err(|_| CocoError::InvalidQueryInput)?,
+        })
+    }
+}
+
+/// One page of the feed relative to `now` (unix seconds).
+pub async fn name_feed(
+    state: &AppState,
+    network: &NetworkState,
+    kind: FeedKind,
+    query: &FeedQuery,
+    now: i64,
+) -> Result<FeedPage, CocoError> {
+    let window_days = query.window_days.unwrap_or(match kind {
+        FeedKind::Expiring => DEFAULT_EXPIRING_WINDOW_DAYS,
+        FeedKind::Released => DEFAULT_RELEASED_WINDOW_DAYS,
+    });
+    if window_days == 0 || window_days > MAX_WINDOW_DAYS {
+        return Err(CocoError::InvalidQueryInput);
+    }
+    let min_cost_wei = match query.min_cost {
+        Some(eth) if !eth.is_finite() || eth < 0.0 => return Err(CocoError::InvalidQueryInput),
+        Some(eth) => Some(format!("{:.0}", eth * WEI_PER_ETH)),
+        None => None,
+    };
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(FeedCursor::decode)
+        .transpose()?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // released names are found by expiry too, shifted back by the grace period
+    let window_secs = i64::from(window_days) * 24 * 60 * 60;
+    let grace_period_secs = network.constants().grace_period_secs as i64;
+    let (from, to, order, after) = match kind {
+        FeedKind::Expiring => (now, now + window_secs, "ASC", ">"),
+        FeedKind::Released => (
+            now - grace_period_secs - window_secs,
+            now - grace_period_secs,
+            "DESC",
+            "<",
+        ),
+    };
+
+    let sql = format!(
+        r#"
+        WITH in_window AS (
+            SELECT label_hash, expires_at, block_number, log_index
+            FROM name_registrations
+            WHERE network = $1 AND expires_at >= to_timestamp($2) AND expires_at < to_timestamp($3)
+            UNION ALL
+            SELECT label_hash, expires_at, block_number, log_index
+            FROM name_renewals
+            WHERE network = $1 AND expires_at >= to_timestamp($2) AND expires_at < to_timestamp($3)
+        ), current AS (
+            -- only the latest registration or renewal sets the expiry
+            SELECT w.label_hash, extract(epoch FROM w.expires_at)::bigint AS expires
+            FROM in_window w
+            WHERE NOT EXISTS (
+                SELECT 1 FROM name_registrations r
+                WHERE r.network = $1 AND r.label_hash = w.label_hash
+                    AND (r.block_number, r.log_index) > (w.block_number, w.log_index)
+            ) AND NOT EXISTS (
+                SELECT 1 FROM name_renewals r
+                WHERE r.network = $1 AND r.label_hash = w.label_hash
+                    AND (r.block_number, r.log_index) > (w.block_number, w.log_index)
+            )
+        ), named AS (
+            SELECT c.label_hash, c.expires,
+                (SELECT label FROM labels l WHERE l.label_hash = c.label_hash) AS label,
+                (SELECT max(base_cost + COALESCE(premium, 0)) FROM name_registrations r
+                 WHERE r.network = $1 AND r.label_hash = c.label_hash) AS max_cost
+            FROM current c
+        )
+        SELECT label, label_hash, expires, max_cost::text
+        FROM named
+        WHERE label IS NOT NULL
+            AND ($4::int IS NULL OR char_length(label) >= $4)
+            AND ($5::int IS NULL OR char_length(label) <= $5)
+            AND ($6::text IS NULL OR label ~ $6)
+            AND ($7::numeric IS NULL OR max_cost >= $7::numeric)
+            AND ($8::bigint IS NULL OR (expires, label_hash) {after} ($8, $9))
+        ORDER BY expires {order}, label_hash {order}
+        LIMIT $10
+        "#
+    );
+
+    let mut rows: Vec<(String, Vec<u8>, i64, Option<String>)> = sqlx::query_as(&sql)
+        .bind(&network.name)
+        .bind(from)
+        .bind(to)
+        .bind(query.min_length.map(|l| l as i32))
+        .bind(query.max_length.map(|l| l as i32))
+        .bind(query.char_class.map(|c| c.pattern()))
+        .bind(min_cost_wei)
+        .bind(cursor.map(|c| c.expires))
+        .bind(cursor.map(|c| c.label_hash.to_vec()))
+        // one extra row tells us whether there's another page
+        .bind(i64::from(limit) + 1)
+        .fetch_all(&state.connection)
+        .await?;
+
+    let next_cursor = if rows.len() > limit as usize {
+        rows.truncate(limit as usize);
+        rows.last().map(|(_, label_hash, expires, _)| {
+            FeedCursor {
+                expires: *expires,
+                label_hash: B256::from_slice(label_hash),
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    Ok(FeedPage {
+        indexed_block: indexed_block(&state.connection, &network.name).await?,
+        rows: rows
+            .into_iter()
+            .map(|(label, _, expires, max_cost)| FeedEntry {
+                name: format!("{}.{}", label, network.tld),
+                expires,
+                max_cost,
+            })
+            .collect(),
+        next_cursor,
+    })
+}
@@ -0,0 +1,105 @@
+//! hash.rs
+//!
+//! Namehash, labelhash and token ids for names, the values developers keep
+//! working out by hand. Labels written as `[<labelhash>]`, the way ENS shows
+//! unknown labels, are hashed as the hash they carry and looked up in the
+//! labels table.
+use crate::errors::CocoError;
+use crate::services::ens::{check_name_limit, normalise_and_hash_name_for};
+use crate::services::labels::label_for_hash;
+use crate::types::api::{HashResponse, NetworkState};
+use alloy::hex;
+use alloy::primitives::{B256, U256, keccak256};
+use alloy_ens::namehash;
+use sqlx::PgPool;
+
+// DNS wire format caps labels at 255 bytes, longer ones go in as their hash
+const MAX_DNS_LABEL_BYTES: usize = 255;
+
+pub async fn hash_names(
+    db: &PgPool,
+    network: &NetworkState,
+    names: &[String],
+) -> Result<Vec<HashResponse>, CocoError> {
+    check_name_limit(network, names)?;
+
+    let mut out = Vec::with_capacity(names.len());
+    for name in names {
+        let mut hashed = hash_name(name, &network.tld)?;
+        if hashed.label.is_none() {
+            hashed.label = label_for_hash(db, hashed.label_hash).await?;
+        }
+        out.push(hashed);
+    }
+
+    Ok(out)
+}
+
+/// Hashes for one 2LD under `tld`. `label` is left empty for `[<labelhash>]`
+/// input since the label itself isn't known.
+pub fn hash_name(name: &str, tld: &str) -> Result<HashResponse, CocoError> {
+    let trimmed = name.trim().to_lowercase();
+    let suffix = format!(".{}", tld);
+    let label = trimmed.strip_suffix(&suffix).unwrap_or(&trimmed);
+
+    let (label, label_hash, name) = match encoded_label_hash(label) {
+        Some(label_hash) => (
+            None,
+            label_hash,
+            format!("[{}]{}", hex::encode(label_hash), suffix),
+        ),
+        None => {
+            let normalised = normalise_and_hash_name_for(name, tld)?;
+            (
+                Some(normalised.label),
+                normalised.label_hash,
+                normalised.name,
+            )
+        }
+    };
+
+    // namehash(label.tld) = keccak256(namehash(tld) ++ labelhash)
+    let mut node_input = namehash(tld).to_vec();
+    node_input.extend_from_slice(label_hash.as_slice());
+    let node = keccak256(node_input);
+
+    Ok(HashResponse {
+        dns_encoded: hex::encode_prefixed(dns_encode(&name)),
+        token_id: U256::from_be_bytes(*label_hash).to_string(),
+        token_id_hex: label_hash,
+        wrapped_token_id: U256::from_be_bytes(*node).to_string(),
+        name,
+        label,
+        label_hash,
+        namehash: node,
+    })
+}
+
+/// The hash in a `[<64 hex chars>]` label, `None` for ordinary labels.
+pub fn encoded_label_hash(label: &str) -> Option<B256> {
+    let hash = label.strip_prefix('[')?.strip_suffix(']')?;
+    let hash = hash.strip_prefix("0x").unwrap_or(hash);
+    if hash.len() != 64 {
+        return None;
+    }
+
+    hash.parse().ok()
+}
+
+/// DNS wire format, each label prefixed by its length and a closing zero byte.
+pub fn dns_encode(name: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        if label.len() > MAX_DNS_LABEL_BYTES {
+            let encoded = format!("[{}]", hex::encode(keccak256(label.as_bytes())));
+            out.push(encoded.len() as u8);
+            out.extend_from_slice(encoded.as_bytes());
+        } else {
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+    }
+    out.push(0);
+
+    out
+}
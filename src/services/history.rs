@@ -0,0 +1,122 @@
+//! history.rs
+//!
+//! Everything the indexer saw happen to one name: registrations, renewals and
+//! registrar transfers, newest first. Names can be given as `[<label hash>]`
+//! too, so tokens nobody has a label for still have a history.
+use crate::errors::CocoError;
+use crate::services::hash::hash_name;
+use crate::services::labels::{display_name, label_for_hash};
+use crate::services::registrations::EventPosition;
+use crate::types::api::{HistoryEvent, HistoryEventKind, HistoryResponse, NetworkState};
+use alloy::primitives::{Address, B256};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+    kind: String,
+    owner: Option<Vec<u8>>,
+    from_address: Option<Vec<u8>>,
+    to_address: Option<Vec<u8>>,
+    expires: Option<i64>,
+    cost: Option<String>,
+    block_number: i64,
+    tx_hash: Vec<u8>,
+    log_index: i64,
+}
+
+/// One page of a name's events, starting below `before` when given.
+pub async fn name_history(
+    db: &PgPool,
+    network: &NetworkState,
+    name: &str,
+    before: Option<EventPosition>,
+    limit: Option<u32>,
+) -> Result<HistoryResponse, CocoError> {
+    let hashed = hash_name(name, &network.tld)?;
+    let label = match hashed.label {
+        Some(label) => Some(label),
+        None => label_for_hash(db, hashed.label_hash).await?,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let rows: Vec<HistoryRow> = sqlx::query_as(
+        r#"
+        SELECT * FROM (
+            SELECT 'registered' AS kind, owner, NULL::bytea AS from_address, NULL::bytea AS to_address,
+                extract(epoch FROM expires_at)::bigint AS expires,
+                (base_cost + COALESCE(premium, 0))::text AS cost,
+                block_number, tx_hash, log_index
+            FROM name_registrations
+            WHERE network = $1 AND label_hash = $2
+            UNION ALL
+            SELECT 'renewed', NULL, NULL, NULL,
+                extract(epoch FROM expires_at)::bigint, cost::text,
+                block_number, tx_hash, log_index
+            FROM name_renewals
+            WHERE network = $1 AND label_hash = $2
+            UNION ALL
+            SELECT 'transferred', NULL, from_address, to_address, NULL, NULL,
+                block_number, tx_hash, log_index
+            FROM name_transfers
+            WHERE network = $1 AND label_hash = $2
+        ) events
+        WHERE $3::bigint IS NULL OR (block_number, log_index) < ($3, $4)
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(&network.name)
+    .bind(hashed.label_hash.as_slice())
+    .bind(before.map(|p| p.block_number as i64))
+    .bind(before.map(|p| p.log_index as i64))
+    .bind(i64::from(limit))
+    .fetch_all(db)
+    .await?;
+
+    // a short page is the last one
+    let next_cursor = (rows.len() == limit as usize)
+        .then(|| rows.last())
+        .flatten()
+        .map(|r| {
+            EventPosition {
+                block_number: r.block_number as u64,
+                log_index: r.log_index as u64,
+            }
+            .encode()
+        });
+
+    Ok(HistoryResponse {
+        name: display_name(label.as_deref(), hashed.label_hash, &network.tld),
+        label_hash: hashed.label_hash,
+        next_cursor,
+        events: rows.into_iter().map(history_event).collect(),
+    })
+}
+
+fn history_event(row: HistoryRow) -> HistoryEvent {
+    let address = |bytes: Option<Vec<u8>>| bytes.map(|b| Address::from_slice(&b));
+
+    HistoryEvent {
+        kind: match row.kind.as_str() {
+            "registered" => HistoryEventKind::Registered,
+            "renewed" => HistoryEventKind::Renewed,
+            _ => HistoryEventKind::Transferred,
+        },
+        owner: address(row.owner),
+        from: address(row.from_address),
+        to: address(row.to_address),
+        expiry_date: row.expires.and_then(unix_to_iso),
+        cost: row.cost,
+        block_number: row.block_number as u64,
+        tx_hash: B256::from_slice(&row.tx_hash),
+        log_index: row.log_index as u64,
+    }
+}
+
+fn unix_to_iso(secs: i64) -> Option<String> {
+    Some(DateTime::<Utc>::from_timestamp(secs, 0)?.to_rfc3339())
+}
@@ -0,0 +1,1024 @@
+//! indexer.rs
+//!
+//! Backfills and follows ENS name events from eth_getLogs into Postgres:
+//! registrations, renewals and transfers from the base registrar (with labels
+//! and costs from the controller), NameWrapper events and registry events.
+//! Each network keeps a checkpoint of the last block it has indexed, written
+//! in the same transaction as the events so a crash never skips or doubles a
+//! range. Runs as the `coco-indexer` binary.
+//!
+//! Each range must start on a block whose parent is the checkpoint's hash.
+//! When it doesn't, the stored block hashes are walked back to the newest one
+//! still on the canonical chain and everything after it is rolled back,
+//! including the watch updates and notifications derived from those events.
+//! Notifications are only released `confirmations` blocks behind the head.
+//! Labels seen in controller and wrapper events go into the `labels` table,
+//! which is left alone on rollback since a label's hash never changes.
+use crate::errors::CocoError;
+use crate::services::ens::{
+    BaseRegistrarImplementation, ENSRegistry, ETHRegistrarController, NameWrapper,
+};
+use crate::services::labels::save_labels;
+use crate::types::api::{AppState, LabelSource, NetworkState};
+use crate::types::ens::EnsContractAddresses;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Header, Log};
+use alloy::sol_types::SolEvent;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+const EVENT_TABLES: [&str; 5] = [
+    "name_registrations",
+    "name_renewals",
+    "name_transfers",
+    "wrapper_events",
+    "registry_events",
+];
+// how many stored block hashes to check when looking for the fork point
+const MAX_REORG_SEARCH: i64 = 1_000;
+const HEADER_CONCURRENCY: usize = 8;
+/// Postgres channel notified with the network name when registrations land.
+pub const REGISTRATIONS_CHANNEL: &str = "name_registrations";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameEvent {
+    Registered {
+        label_hash: B256,
+        owner: Address,
+        expires: i64,
+    },
+    // emitted after the registrar event in the same transaction. Its owner is
+    // the registrant, the registrar's is the NameWrapper for wrapped names
+    ControllerRegistered {
+        label: String,
+        label_hash: B256,
+        owner: Address,
+        base_cost: U256,
+        premium: U256,
+    },
+    Renewed {
+        label_hash: B256,
+        expires: i64,
+    },
+    ControllerRenewed {
+        label: String,
+        label_hash: B256,
+        cost: U256,
+    },
+    Transfer {
+        label_hash: B256,
+        from: Address,
+        to: Address,
+    },
+    Wrapper(WrapperEvent),
+    Registry(RegistryEvent),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrapperEvent {
+    pub node: B256,
+    pub kind: &'static str,
+    pub name: Option<String>,
+    pub owner: Option<Address>,
+    pub fuses: Option<u32>,
+    pub expires: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryEvent {
+    pub node: B256,
+    pub kind: &'static str,
+    pub label_hash: Option<B256>,
+    pub owner: Option<Address>,
+    pub resolver: Option<Address>,
+    pub ttl: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedLog {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub block_timestamp: Option<u64>,
+    pub tx_hash: B256,
+    pub log_index: u64,
+    pub event: NameEvent,
+}
+
+#[derive(Debug, PartialEq)]
+enum Progress {
+    Behind,
+    CaughtUp,
+}
+
+/// Indexes every configured network until the process is stopped.
+pub async fn run_indexer(state: Arc<AppState>) {
+    let tasks: Vec<_> = state
+        .networks
+        .keys()
+        .cloned()
+        .map(|network| tokio::spawn(index_network(state.clone(), network)))
+        .collect();
+
+    futures::future::join_all(tasks).await;
+}
+
+async fn index_network(state: Arc<AppState>, network: String) {
+    let poll_interval = Duration::from_secs(state.app_config.indexer.poll_interval_secs);
+
+    loop {
+        match index_next_range(&state, &network).await {
+            // still backfilling, go straight on to the next range
+            Ok(Progress::Behind) => continue,
+            Ok(Progress::CaughtUp) => {}
+            Err(e) => tracing::error!("Indexing {} failed: {:?}", network, e),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+async fn index_next_range(state: &AppState, network_name: &str) -> Result<Progress, CocoError> {
+    let network = state.network(Some(network_name))?;
+    let db = &state.connection;
+
+    let checkpoint = load_checkpoint(db, network_name).await?;
+    let from = match checkpoint {
+        Some((block, _)) => block + 1,
+        None => network.index_from_block,
+    };
+    let head = network
+        .pool
+        .execute(|provider| async move { Ok(provider.get_block_number().await?) })
+        .await?;
+
+    // the next range has to build on the block we stopped at
+    if let Some((block, Some(hash))) = checkpoint
+        && from <= head
+    {
+        let first = fetch_header(network, from).await?;
+        if first.parent_hash != hash {
+            let ancestor = find_common_ancestor(db, network, block).await?;
+            tracing::warn!(
+                "Reorg on {} at block {}, rolling back to {:?}",
+                network_name,
+                from,
+                ancestor.map(|(number, _)| number)
+            );
+            rollback_to(db, network_name, ancestor).await?;
+            return Ok(Progress::Behind);
+        }
+    }
+
+    if let Some((block, _)) = checkpoint {
+        let confirmed = block.min(head.saturating_sub(state.app_config.indexer.confirmations));
+        release_notifications(db, network_name, confirmed).await?;
+    }
+
+    if from > head {
+        return Ok(Progress::CaughtUp);
+    }
+
+    let batch_blocks = state.app_config.indexer.batch_blocks.max(1);
+    let mut to = head.min(from + batch_blocks - 1);
+    let logs = loop {
+        match fetch_logs(network, from, to).await {
+            Ok(logs) => break logs,
+            // providers cap block ranges and result sizes differently
+            Err(e) if to > from && !e.is_transport_error() => {
+                tracing::warn!(
+                    "Shrinking {} log range {}..={}: {}",
+                    network_name,
+                    from,
+                    to,
+                    e
+                );
+                to = from + (to - from) / 2;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    let to_hash = fetch_header(network, to).await?.hash;
+
+    let mut events: Vec<IndexedLog> = logs
+        .iter()
+        .filter_map(|log| decode_log(&network.ens_contract_addresses, log))
+        .collect();
+
+    let Some(blocks) = collect_block_hashes(&events, (to, to_hash)) else {
+        // the chain moved between the log and header reads, read it again
+        tracing::warn!(
+            "{} blocks {}..={} changed while indexing, retrying",
+            network_name,
+            from,
+            to
+        );
+        return Ok(Progress::Behind);
+    };
+    fill_block_timestamps(network, &mut events).await?;
+
+    let mut tx = db.begin().await?;
+    store_events(&mut tx, network_name, &events).await?;
+    save_labels(&mut *tx, &event_labels(&events), LabelSource::Event).await?;
+    store_block_hashes(&mut tx, network_name, &blocks).await?;
+    save_checkpoint(&mut tx, network_name, to, to_hash).await?;
+    // wakes the api's registration streams once this commits
+    if events
+        .iter()
+        .any(|e| matches!(e.event, NameEvent::Registered { .. }))
+    {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(REGISTRATIONS_CHANNEL)
+            .bind(network_name)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    tracing::info!(
+        "Indexed {} blocks {}..={}, {} events",
+        network_name,
+        from,
+        to,
+        events.len()
+    );
+
+    Ok(if to >= head {
+        Progress::CaughtUp
+    } else {
+        Progress::Behind
+    })
+}
+
+/// Hash of every block the events came from plus the range's last block,
+/// `None` if any two of them disagree about a block's hash.
+pub fn collect_block_hashes(
+    events: &[IndexedLog],
+    last: (u64, B256),
+) -> Option<BTreeMap<u64, B256>> {
+    let mut blocks = BTreeMap::new();
+
+    for (number, hash) in events
+        .iter()
+        .map(|e| (e.block_number, e.block_hash))
+        .chain([last])
+    {
+        if *blocks.entry(number).or_insert(hash) != hash {
+            return None;
+        }
+    }
+
+    Some(blocks)
+}
+
+/// Labels the events give away: controller labels and every label of a
+/// wrapped name.
+pub fn event_labels(events: &[IndexedLog]) -> Vec<String> {
+    let mut labels = BTreeSet::new();
+
+    for event in events {
+        match &event.event {
+            NameEvent::ControllerRegistered { label, .. }
+            | NameEvent::ControllerRenewed { label, .. } => {
+                labels.insert(label.clone());
+            }
+            NameEvent::Wrapper(WrapperEvent {
+                name: Some(name), ..
+            }) => labels.extend(name.split('.').filter(|l| !l.is_empty()).map(String::from)),
+            _ => {}
+        }
+    }
+
+    labels.into_iter().collect()
+}
+
+async fn fetch_header(network: &NetworkState, number: u64) -> Result<Header, CocoError> {
+    network
+        .pool
+        .execute(|provider| async move {
+            let block = provider
+                .get_block_by_number(number.into())
+                .await?
+                .ok_or(CocoError::BlockNotFound)?;
+            Ok(block.header)
+        })
+        .await
+}
+
+// registration durations need the block time, which not every node puts on logs
+async fn fill_block_timestamps(
+    network: &NetworkState,
+    events: &mut [IndexedLog],
+) -> Result<(), CocoError> {
+    let missing: BTreeSet<u64> = events
+        .iter()
+        .filter(|e| e.block_timestamp.is_none() && matches!(e.event, NameEvent::Registered { .. }))
+        .map(|e| e.block_number)
+        .collect();
+
+    let timestamps: HashMap<u64, u64> = futures::stream::iter(missing)
+        .map(|number| async move {
+            fetch_header(network, number)
+                .await
+                .map(|header| (number, header.timestamp))
+        })
+        .buffer_unordered(HEADER_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    for event in events.iter_mut() {
+        event.block_timestamp = event
+            .block_timestamp
+            .or_else(|| timestamps.get(&event.block_number).copied());
+    }
+
+    Ok(())
+}
+
+// newest stored block whose hash is still canonical, `None` if none are
+async fn find_common_ancestor(
+    db: &PgPool,
+    network: &NetworkState,
+    checkpoint: u64,
+) -> Result<Option<(u64, B256)>, CocoError> {
+    let stored: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+        r#"
+        SELECT block_number, block_hash
+        FROM indexed_blocks
+        WHERE network = $1 AND block_number <= $2
+        ORDER BY block_number DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(&network.name)
+    .bind(checkpoint as i64)
+    .bind(MAX_REORG_SEARCH)
+    .fetch_all(db)
+    .await?;
+
+    for (number, hash) in stored {
+        let number = number as u64;
+        let canonical = fetch_header(network, number).await?.hash;
+        if canonical.as_slice() == hash.as_slice() {
+            return Ok(Some((number, canonical)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Removes everything indexed after `ancestor`, restores the watches the
+/// removed events updated and withdraws their notifications. With no
+/// ancestor the network is indexed again from scratch.
+async fn rollback_to(
+    db: &PgPool,
+    network: &str,
+    ancestor: Option<(u64, B256)>,
+) -> Result<(), CocoError> {
+    let after = ancestor.map_or(-1, |(number, _)| number as i64);
+    let mut tx = db.begin().await?;
+
+    for table in EVENT_TABLES {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE network = $1 AND block_number > $2",
+            table
+        ))
+        .bind(network)
+        .bind(after)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // the earliest rolled back change holds the values from before the fork
+    sqlx::query(
+        r#"
+        UPDATE watch_list w
+        SET expires_at = c.previous_expires_at,
+            trigger_at = c.previous_trigger_at,
+            updated_at = now()
+        FROM (
+            SELECT DISTINCT ON (watch_id) watch_id, previous_expires_at, previous_trigger_at
+            FROM watch_changes
+            WHERE network = $1 AND block_number > $2
+            ORDER BY watch_id, block_number, log_index
+        ) c
+        WHERE w.id = c.watch_id
+        "#,
+    )
+    .bind(network)
+    .bind(after)
+    .execute(&mut *tx)
+    .await?;
+
+    for query in [
+        "DELETE FROM watch_changes WHERE network = $1 AND block_number > $2",
+        "DELETE FROM watch_notifications WHERE network = $1 AND block_number > $2 AND released_at IS NULL",
+        // already delivered, the bot follows up with a correction. Fulfilments
+        // come from the sweeper, not from events, so a reorg doesn't undo them
+        "UPDATE watch_notifications SET retracted_at = now() WHERE network = $1 AND block_number > $2 AND retracted_at IS NULL AND tx_hash IS NOT NULL",
+        "DELETE FROM indexed_blocks WHERE network = $1 AND block_number > $2",
+    ] {
+        sqlx::query(query)
+            .bind(network)
+            .bind(after)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    match ancestor {
+        Some((number, hash)) => save_checkpoint(&mut tx, network, number, hash).await?,
+        None => {
+            sqlx::query("DELETE FROM indexer_checkpoints WHERE network = $1")
+                .bind(network)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn release_notifications(db: &PgPool, network: &str, upto: u64) -> Result<(), CocoError> {
+    let released = sqlx::query(
+        r#"
+        UPDATE watch_notifications
+        SET released_at = now()
+        WHERE network = $1 AND released_at IS NULL AND block_number <= $2
+        "#,
+    )
+    .bind(network)
+    .bind(upto as i64)
+    .execute(db)
+    .await?;
+
+    if released.rows_affected() > 0 {
+        tracing::info!(
+            "Released {} {} watch notifications",
+            released.rows_affected(),
+            network
+        );
+    }
+
+    Ok(())
+}
+
+async fn fetch_logs(network: &NetworkState, from: u64, to: u64) -> Result<Vec<Log>, CocoError> {
+    let contracts = &network.ens_contract_addresses;
+    let mut addresses = vec![
+        contracts.ens_registry,
+        contracts.base_registrar,
+        contracts.registrar_controller,
+    ];
+    addresses.extend(contracts.name_wrapper);
+
+    let filter = Filter::new()
+        .address(addresses)
+        .from_block(from)
+        .to_block(to);
+
+    network
+        .pool
+        .execute(|provider| {
+            let filter = &filter;
+            async move { Ok(provider.get_logs(filter).await?) }
+        })
+        .await
+}
+
+/// Decodes a log from one of the indexed contracts, `None` for anything else.
+pub fn decode_log(contracts: &EnsContractAddresses, log: &Log) -> Option<IndexedLog> {
+    let topic = *log.topic0()?;
+    let address = log.address();
+
+    let event = if address == contracts.base_registrar {
+        decode_registrar_log(topic, log)
+    } else if address == contracts.registrar_controller {
+        decode_controller_log(topic, log)
+    } else if Some(address) == contracts.name_wrapper {
+        decode_wrapper_log(topic, log)
+    } else if address == contracts.ens_registry {
+        decode_registry_log(topic, log)
+    } else {
+        None
+    }?;
+
+    Some(IndexedLog {
+        block_number: log.block_number?,
+        block_hash: log.block_hash?,
+        block_timestamp: log.block_timestamp,
+        tx_hash: log.transaction_hash?,
+        log_index: log.log_index?,
+        event,
+    })
+}
+
+fn decode_registrar_log(topic: B256, log: &Log) -> Option<NameEvent> {
+    use BaseRegistrarImplementation::{NameRegistered, NameRenewed, Transfer};
+
+    if topic == NameRegistered::SIGNATURE_HASH {
+        let e = decode::<NameRegistered>(log)?;
+        Some(NameEvent::Registered {
+            label_hash: e.id.into(),
+            owner: e.owner,
+            expires: e.expires.saturating_to(),
+        })
+    } else if topic == NameRenewed::SIGNATURE_HASH {
+        let e = decode::<NameRenewed>(log)?;
+        Some(NameEvent::Renewed {
+            label_hash: e.id.into(),
+            expires: e.expires.saturating_to(),
+        })
+    } else if topic == Transfer::SIGNATURE_HASH {
+        let e = decode::<Transfer>(log)?;
+        Some(NameEvent::Transfer {
+            label_hash: e.tokenId.into(),
+            from: e.from,
+            to: e.to,
+        })
+    } else {
+        None
+    }
+}
+
+fn decode_controller_log(topic: B256, log: &Log) -> Option<NameEvent> {
+    use ETHRegistrarController::{NameRegistered, NameRenewed};
+
+    if topic == NameRegistered::SIGNATURE_HASH {
+        let e = decode::<NameRegistered>(log)?;
+        Some(NameEvent::ControllerRegistered {
+            label: e.name,
+            label_hash: e.label,
+            owner: e.owner,
+            base_cost: e.baseCost,
+            premium: e.premium,
+        })
+    } else if topic == NameRenewed::SIGNATURE_HASH {
+        let e = decode::<NameRenewed>(log)?;
+        Some(NameEvent::ControllerRenewed {
+            label: e.name,
+            label_hash: e.label,
+            cost: e.cost,
+        })
+    } else {
+        None
+    }
+}
+
+fn decode_wrapper_log(topic: B256, log: &Log) -> Option<NameEvent> {
+    use NameWrapper::{ExpiryExtended, FusesSet, NameUnwrapped, NameWrapped, TransferSingle};
+
+    let wrapper_event = |node: B256, kind: &'static str| WrapperEvent {
+        node,
+        kind,
+        name: None,
+        owner: None,
+        fuses: None,
+        expires: None,
+    };
+
+    if topic == NameWrapped::SIGNATURE_HASH {
+        let e = decode::<NameWrapped>(log)?;
+        Some(NameEvent::Wrapper(WrapperEvent {
+            node: e.node,
+            kind: "wrapped",
+            name: decode_dns_name(&e.name),
+            owner: Some(e.owner),
+            fuses: Some(e.fuses),
+            expires: Some(e.expiry.min(i64::MAX as u64) as i64),
+        }))
+    } else if topic == NameUnwrapped::SIGNATURE_HASH {
+        let e = decode::<NameUnwrapped>(log)?;
+        Some(NameEvent::Wrapper(WrapperEvent {
+            owner: Some(e.owner),
+            ..wrapper_event(e.node, "unwrapped")
+        }))
+    } else if topic == FusesSet::SIGNATURE_HASH {
+        let e = decode::<FusesSet>(log)?;
+        Some(NameEvent::Wrapper(WrapperEvent {
+            fuses: Some(e.fuses),
+            ..wrapper_event(e.node, "fuses_set")
+        }))
+    } else if topic == ExpiryExtended::SIGNATURE_HASH {
+        let e = decode::<ExpiryExtended>(log)?;
+        Some(NameEvent::Wrapper(WrapperEvent {
+            expires: Some(e.expiry.min(i64::MAX as u64) as i64),
+            ..wrapper_event(e.node, "expiry_extended")
+        }))
+    } else if topic == TransferSingle::SIGNATURE_HASH {
+        // wrapped token ids are the namehash
+        let e = decode::<TransferSingle>(log)?;
+        Some(NameEvent::Wrapper(WrapperEvent {
+            owner: Some(e.to),
+            ..wrapper_event(e.id.into(), "transfer")
+        }))
+    } else {
+        None
+    }
+}
+
+fn decode_registry_log(topic: B256, log: &Log) -> Option<NameEvent> {
+    use ENSRegistry::{NewOwner, NewResolver, NewTTL, Transfer};
+
+    let registry_event = |node: B256, kind: &'static str| RegistryEvent {
+        node,
+        kind,
+        label_hash: None,
+        owner: None,
+        resolver: None,
+        ttl: None,
+    };
+
+    if topic == NewOwner::SIGNATURE_HASH {
+        let e = decode::<NewOwner>(log)?;
+        Some(NameEvent::Registry(RegistryEvent {
+            label_hash: Some(e.label),
+            owner: Some(e.owner),
+            ..registry_event(e.node, "new_owner")
+        }))
+    } else if topic == Transfer::SIGNATURE_HASH {
+        let e = decode::<Transfer>(log)?;
+        Some(NameEvent::Registry(RegistryEvent {
+            owner: Some(e.owner),
+            ..registry_event(e.node, "transfer")
+        }))
+    } else if topic == NewResolver::SIGNATURE_HASH {
+        let e = decode::<NewResolver>(log)?;
+        Some(NameEvent::Registry(RegistryEvent {
+            resolver: Some(e.resolver),
+            ..registry_event(e.node, "new_resolver")
+        }))
+    } else if topic == NewTTL::SIGNATURE_HASH {
+        let e = decode::<NewTTL>(log)?;
+        Some(NameEvent::Registry(RegistryEvent {
+            ttl: Some(e.ttl),
+            ..registry_event(e.node, "new_ttl")
+        }))
+    } else {
+        None
+    }
+}
+
+fn decode<E: SolEvent>(log: &Log) -> Option<E> {
+    log.log_decode::<E>().ok().map(|decoded| decoded.inner.data)
+}
+
+// NameWrapper emits names in DNS wire format, eg "\x05alice\x03eth\x00"
+fn decode_dns_name(bytes: &[u8]) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut rest = bytes;
+
+    loop {
+        let (&len, tail) = rest.split_first()?;
+        if len == 0 {
+            break;
+        }
+        let label = tail.get(..len as usize)?;
+        labels.push(String::from_utf8(label.to_vec()).ok()?);
+        rest = &tail[len as usize..];
+    }
+
+    Some(labels.join("."))
+}
+
+/// Last block the indexer has processed, so callers can tell how fresh
+/// the tables are.
+pub async fn indexed_block(db: &PgPool, network: &str) -> Result<Option<u64>, CocoError> {
+    let block: Option<i64> =
+        sqlx::query_scalar("SELECT block_number FROM indexer_checkpoints WHERE network = $1")
+            .bind(network)
+            .fetch_optional(db)
+            .await?;
+
+    Ok(block.map(|b| b as u64))
+}
+
+// checkpoints written before reorg tracking have no hash
+async fn load_checkpoint(
+    db: &PgPool,
+    network: &str,
+) -> Result<Option<(u64, Option<B256>)>, CocoError> {
+    let row: Option<(i64, Option<Vec<u8>>)> = sqlx::query_as(
+        "SELECT block_number, block_hash FROM indexer_checkpoints WHERE network = $1",
+    )
+    .bind(network)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|(block, hash)| {
+        (
+            block as u64,
+            hash.and_then(|h| B256::try_from(h.as_slice()).ok()),
+        )
+    }))
+}
+
+async fn save_checkpoint(
+    tx: &mut Transaction<'_, Postgres>,
+    network: &str,
+    block: u64,
+    hash: B256,
+) -> Result<(), CocoError> {
+    sqlx::query(
+        r#"
+        INSERT INTO indexer_checkpoints (network, block_number, block_hash, updated_at)
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (network) DO UPDATE
+        SET block_number = EXCLUDED.block_number,
+            block_hash = EXCLUDED.block_hash,
+            updated_at = now()
+        "#,
+    )
+    .bind(network)
+    .bind(block as i64)
+    .bind(hash.as_slice())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn store_block_hashes(
+    tx: &mut Transaction<'_, Postgres>,
+    network: &str,
+    blocks: &BTreeMap<u64, B256>,
+) -> Result<(), CocoError> {
+    let numbers: Vec<i64> = blocks.keys().map(|n| *n as i64).collect();
+    let hashes: Vec<Vec<u8>> = blocks.values().map(|h| h.to_vec()).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO indexed_blocks (network, block_number, block_hash)
+        SELECT $1, block_number, block_hash
+        FROM UNNEST($2::bigint[], $3::bytea[]) AS t(block_number, block_hash)
+        ON CONFLICT (network, block_number) DO UPDATE
+        SET block_hash = EXCLUDED.block_hash
+        "#,
+    )
+    .bind(network)
+    .bind(&numbers)
+    .bind(&hashes)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Queues a notification for every active watch on the name and, when the
+/// event sets a new expiry, moves the watches' expiry and trigger to it. The
+/// sweeper refines the trigger from there. The values replaced are kept in
+/// `watch_changes` so a reorg can put them back.
+async fn derive_watch_updates(
+    tx: &mut Transaction<'_, Postgres>,
+    network: &str,
+    log: &IndexedLog,
+    label_hash: B256,
+    kind: &'static str,
+    expires: Option<i64>,
+) -> Result<(), CocoError> {
+    sqlx::query(
+        r#"
+        INSERT INTO watch_notifications (watch_id, network, kind, block_number, tx_hash, log_index)
+        SELECT id, $1, $3::watch_notification_kind, $4, $5, $6
+        FROM watch_list
+        WHERE network = $1 AND label_hash = $2 AND status = 'active'
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(network)
+    .bind(label_hash.as_slice())
+    .bind(kind)
+    .bind(log.block_number as i64)
+    .bind(log.tx_hash.as_slice())
+    .bind(log.log_index as i64)
+    .execute(&mut **tx)
+    .await?;
+
+    let Some(expires) = expires else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        r#"
+        WITH previous AS (
+            SELECT id, expires_at, trigger_at
+            FROM watch_list
+            WHERE network = $1 AND label_hash = $2 AND status = 'active'
+            FOR UPDATE
+        ), changed AS (
+            UPDATE watch_list w
+            SET expires_at = to_timestamp($3), trigger_at = to_timestamp($3), updated_at = now()
+            FROM previous p
+            WHERE w.id = p.id
+            RETURNING p.id, p.expires_at, p.trigger_at
+        )
+        INSERT INTO watch_changes
+            (watch_id, network, previous_expires_at, previous_trigger_at, block_number, tx_hash, log_index)
+        SELECT id, $1, expires_at, trigger_at, $4, $5, $6
+        FROM changed
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(network)
+    .bind(label_hash.as_slice())
+    .bind(expires)
+    .bind(log.block_number as i64)
+    .bind(log.tx_hash.as_slice())
+    .bind(log.log_index as i64)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn store_events(
+    tx: &mut Transaction<'_, Postgres>,
+    network: &str,
+    events: &[IndexedLog],
+) -> Result<(), CocoError> {
+    for log in events {
+        let block = log.block_number as i64;
+        let tx_hash = log.tx_hash.as_slice();
+        let log_index = log.log_index as i64;
+
+        let query = match &log.event {
+            NameEvent::Registered {
+                label_hash,
+                owner,
+                expires,
+            } => sqlx::query(
+                r#"
+                INSERT INTO name_registrations
+                    (network, label_hash, owner, expires_at, block_number, tx_hash, log_index, registered_at)
+                VALUES ($1, $2, $3, to_timestamp($4), $5, $6, $7, to_timestamp($8))
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(network)
+            .bind(label_hash.as_slice())
+            .bind(owner.as_slice())
+            .bind(expires)
+            .bind(block)
+            .bind(tx_hash)
+            .bind(log_index)
+            .bind(log.block_timestamp.map(|t| t as i64)),
+            NameEvent::ControllerRegistered {
+                label,
+                label_hash,
+                owner,
+                base_cost,
+                premium,
+            } => sqlx::query(
+                r#"
+                UPDATE name_registrations
+                SET label = $3, owner = $4, base_cost = $5::numeric, premium = $6::numeric
+                WHERE network = $1 AND label_hash = $2 AND tx_hash = $7
+                "#,
+            )
+            .bind(network)
+            .bind(label_hash.as_slice())
+            .bind(label)
+            .bind(owner.as_slice())
+            .bind(base_cost.to_string())
+            .bind(premium.to_string())
+            .bind(tx_hash),
+            NameEvent::Renewed {
+                label_hash,
+                expires,
+            } => sqlx::query(
+                r#"
+                INSERT INTO name_renewals
+                    (network, label_hash, expires_at, block_number, tx_hash, log_index)
+                VALUES ($1, $2, to_timestamp($3), $4, $5, $6)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(network)
+            .bind(label_hash.as_slice())
+            .bind(expires)
+            .bind(block)
+            .bind(tx_hash)
+            .bind(log_index),
+            NameEvent::ControllerRenewed {
+                label,
+                label_hash,
+                cost,
+            } => sqlx::query(
+                r#"
+                UPDATE name_renewals
+                SET label = $3, cost = $4::numeric
+                WHERE network = $1 AND label_hash = $2 AND tx_hash = $5
+                "#,
+            )
+            .bind(network)
+            .bind(label_hash.as_slice())
+            .bind(label)
+            .bind(cost.to_string())
+            .bind(tx_hash),
+            NameEvent::Transfer {
+                label_hash,
+                from,
+                to,
+            } => sqlx::query(
+                r#"
+                INSERT INTO name_transfers
+                    (network, label_hash, from_address, to_address, block_number, tx_hash, log_index)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(network)
+            .bind(label_hash.as_slice())
+            .bind(from.as_slice())
+            .bind(to.as_slice())
+            .bind(block)
+            .bind(tx_hash)
+            .bind(log_index),
+            NameEvent::Wrapper(WrapperEvent {
+                node,
+                kind,
+                name,
+                owner,
+                fuses,
+                expires,
+            }) => sqlx::query(
+                r#"
+                INSERT INTO wrapper_events
+                    (network, node, kind, name, owner, fuses, expires_at, block_number, tx_hash, log_index)
+                VALUES ($1, $2, $3, $4, $5, $6, to_timestamp($7), $8, $9, $10)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(network)
+            .bind(node.as_slice())
+            .bind(*kind)
+            .bind(name)
+            .bind(owner.as_ref().map(|o| o.as_slice()))
+            .bind(fuses.map(i64::from))
+            .bind(expires)
+            .bind(block)
+            .bind(tx_hash)
+            .bind(log_index),
+            NameEvent::Registry(RegistryEvent {
+                node,
+                kind,
+                label_hash,
+                owner,
+                resolver,
+                ttl,
+            }) => sqlx::query(
+                r#"
+                INSERT INTO registry_events
+                    (network, node, kind, label_hash, owner, resolver, ttl, block_number, tx_hash, log_index)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(network)
+            .bind(node.as_slice())
+            .bind(*kind)
+            .bind(label_hash.as_ref().map(|h| h.as_slice()))
+            .bind(owner.as_ref().map(|o| o.as_slice()))
+            .bind(resolver.as_ref().map(|r| r.as_slice()))
+            .bind(ttl.map(|t| t.min(i64::MAX as u64) as i64))
+            .bind(block)
+            .bind(tx_hash)
+            .bind(log_index),
+        };
+
+        let inserted = query.execute(&mut **tx).await?.rows_affected() > 0;
+        if !inserted {
+            continue;
+        }
+
+        match &log.event {
+            NameEvent::Registered {
+                label_hash,
+                expires,
+                ..
+            } => {
+                derive_watch_updates(tx, network, log, *label_hash, "registered", Some(*expires))
+                    .await?
+            }
+            NameEvent::Renewed {
+                label_hash,
+                expires,
+            } => {
+                derive_watch_updates(tx, network, log, *label_hash, "renewed", Some(*expires))
+                    .await?
+            }
+            // mints come with a registration already
+            NameEvent::Transfer {
+                label_hash, from, ..
+            } if !from.is_zero() => {
+                derive_watch_updates(tx, network, log, *label_hash, "transferred", None).await?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! jobs.rs
+//!
+//! Batch availability checks over name lists too long for /api/check. Names
+//! are normalised and stored when the job is queued, so a bad name fails the
+//! request rather than the job. The batch worker then checks them in upload
+//! order.
+use crate::config::Config;
+use crate::errors::CocoError;
+use crate::services::batch::{BatchKind, BatchSource, Claim, stream_results};
+use crate::services::ens::normalise_and_hash_name_for;
+use crate::types::api::{AppState, JobBody, JobResponse, JobStatus, NameResult};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+pub const CSV_HEADER: &str = "name,available,owner,expiry_date,base_cost,premium\n";
+
+/// Jobs for the batch worker.
+pub struct Jobs;
+
+impl BatchSource for Jobs {
+    const KIND: BatchKind = BatchKind::Job;
+
+    fn chunk_size(config: &Config) -> usize {
+        config.jobs.chunk_size
+    }
+
+    fn poll_interval_secs(config: &Config) -> u64 {
+        config.jobs.poll_interval_secs
+    }
+
+    async fn names(state: &AppState, claim: &Claim) -> Result<Vec<String>, CocoError> {
+        let names =
+            sqlx::query_scalar("SELECT name FROM job_names WHERE job_id = $1 ORDER BY position")
+                .bind(claim.id)
+                .fetch_all(&state.connection)
+                .await?;
+
+        Ok(names)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct JobRow {
+    id: Uuid,
+    network: String,
+    status: JobStatus,
+    total: i64,
+    checked: i64,
+    available: i64,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+const JOB_COLUMNS: &str = r#"
+    id, network, status, total, checked, available, error, created_at, completed_at
+"#;
+
+pub async fn create_job(state: &AppState, body: &JobBody) -> Result<JobResponse, CocoError> {
+    let network = state.network(body.network.as_deref())?;
+    let names = job_names(&body.names, &network.tld, state.app_config.jobs.max_names)?;
+
+    let mut tx = state.connection.begin().await?;
+    let row: JobRow = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO jobs (id, network, status, total)
+        VALUES ($1, $2, 'pending', $3)
+        RETURNING {JOB_COLUMNS}
+        "#
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&network.name)
+    .bind(names.len() as i64)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO job_names (job_id, position, name)
+        SELECT $1, n.position - 1, n.name
+        FROM UNNEST($2::varchar[]) WITH ORDINALITY AS n(name, position)
+        "#,
+    )
+    .bind(row.id)
+    .bind(&names)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(job_response(row))
+}
+
+pub async fn get_job(db: &PgPool, id: Uuid) -> Result<JobResponse, CocoError> {
+    let row: Option<JobRow> =
+        sqlx::query_as(&format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = $1"))
+            .bind(id)
+            .fetch_optional(db)
+            .await?;
+
+    row.map(job_response).ok_or(CocoError::JobNotFound(id))
+}
+
+/// Names a job will check: normalised, without blanks or repeats, in upload
+/// order. Any name that doesn't normalise fails the whole upload.
+pub fn job_names(names: &[String], tld: &str, limit: usize) -> Result<Vec<String>, CocoError> {
+    if names.len() > limit {
+        return Err(CocoError::TooManyNames {
+            requested: names.len(),
+            limit,
+        });
+    }
+
+    let mut seen = HashSet::new();
+    let mut normalised = Vec::new();
+    for name in names.iter().filter(|n| !n.trim().is_empty()) {
+        let name = normalise_and_hash_name_for(name, tld)?.name;
+        if seen.insert(name.clone()) {
+            normalised.push(name);
+        }
+    }
+    if normalised.is_empty() {
+        return Err(CocoError::InvalidUpload("no names to check".to_string()));
+    }
+
+    Ok(normalised)
+}
+
+/// Results of a completed job in upload order, optionally only the available
+/// or only the taken names.
+pub async fn job_results(
+    db: &PgPool,
+    id: Uuid,
+    available: Option<bool>,
+) -> Result<mpsc::Receiver<Result<NameResult, CocoError>>, CocoError> {
+    let job = get_job(db, id).await?;
+    match job.status {
+        JobStatus::Completed => Ok(stream_results(db.clone(), BatchKind::Job, id, available)),
+        JobStatus::Failed => Err(CocoError::JobFailed(id, job.error.unwrap_or_default())),
+        JobStatus::Pending | JobStatus::Running => Err(CocoError::JobNotFinished(id)),
+    }
+}
+
+/// Names from an uploaded CSV, one per row from the first column. A header
+/// row of `name` is skipped, as are blank rows.
+pub fn csv_names(data: &[u8]) -> Result<Vec<String>, CocoError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+
+    let mut names = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record =
+            record.map_err(|e| CocoError::InvalidUpload(format!("row {}: {}", i + 1, e)))?;
+        let Some(name) = record.get(0).filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        if i == 0 && name.eq_ignore_ascii_case("name") {
+            continue;
+        }
+        names.push(name.to_string());
+    }
+
+    Ok(names)
+}
+
+/// One result as a CSV row under `CSV_HEADER`, empty cells where a value
+/// doesn't apply.
+pub fn csv_row(r: &NameResult) -> String {
+    // six fields into memory, so writes can't fail
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            r.name.clone(),
+            r.available.to_string(),
+            r.owner.map(|o| o.to_string()).unwrap_or_default(),
+            r.expiry_date.clone().unwrap_or_default(),
+            r.base_cost.clone().unwrap_or_default(),
+            r.premium.clone().unwrap_or_default(),
+        ])
+        .expect("csv record");
+
+    String::from_utf8(writer.into_inner().expect("csv flush")).unwrap_or_default()
+}
+
+/// One result as a line of newline-delimited JSON.
+pub fn ndjson_row(r: &NameResult) -> String {
+    serde_json::to_string(r).unwrap_or_default() + "\n"
+}
+
+fn job_response(row: JobRow) -> JobResponse {
+    JobResponse {
+        id: row.id,
+        network: row.network,
+        status: row.status,
+        total: row.total as u64,
+        checked: row.checked as u64,
+        available: row.available as u64,
+        error: row.error,
+        created_at: row.created_at.to_rfc3339(),
+        completed_at: row.completed_at.map(|at| at.to_rfc3339()),
+    }
+}
@@ -0,0 +1,141 @@
+//! labels.rs
+//!
+//! Label hash to label lookups. Events and token ids only carry the hash, so
+//! the `labels` table collects every label coco comes across: controller and
+//! wrapper events from the indexer, imported wordlists and names people
+//! query. Anything still unknown is shown as `[<label hash>]`.
+use crate::errors::CocoError;
+use crate::services::ens::normalise_names;
+use crate::types::api::LabelSource;
+use alloy::hex;
+use alloy::primitives::{B256, keccak256};
+use sqlx::{PgExecutor, PgPool};
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::mpsc;
+
+// batches waiting for the writer, past this new ones are dropped
+const QUEUE_CAPACITY: usize = 256;
+// labels the writer gathers into one insert
+const WRITE_BATCH: usize = 1000;
+
+/// Labels of queried names on their way to `run_label_writer`.
+#[derive(Clone)]
+pub struct LabelQueue(mpsc::Sender<Vec<String>>);
+
+impl LabelQueue {
+    pub fn new() -> (Self, mpsc::Receiver<Vec<String>>) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        (Self(tx), rx)
+    }
+}
+
+/// Records labels, keeping whatever was there first. Returns how many were new.
+pub async fn save_labels<'e>(
+    executor: impl PgExecutor<'e>,
+    labels: &[String],
+    source: LabelSource,
+) -> Result<u64, CocoError> {
+    if labels.is_empty() {
+        return Ok(0);
+    }
+    let hashes: Vec<Vec<u8>> = labels
+        .iter()
+        .map(|l| keccak256(l.as_bytes()).to_vec())
+        .collect();
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO labels (label_hash, label, source)
+        SELECT label_hash, label, $3 FROM UNNEST($1::bytea[], $2::varchar[]) AS l(label_hash, label)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(hashes)
+    .bind(labels)
+    .bind(source)
+    .execute(executor)
+    .await?
+    .rows_affected();
+
+    Ok(inserted)
+}
+
+/// Queues the labels of names a request has already accepted. A full queue
+/// drops them rather than hold up or pile work behind the request.
+pub fn remember_names(queue: &LabelQueue, tld: &str, names: &[String]) {
+    let labels: BTreeSet<String> = normalise_names(names, tld)
+        .into_iter()
+        .map(|n| n.label)
+        .collect();
+    if labels.is_empty() {
+        return;
+    }
+
+    // closed only where nothing writes labels, eg the indexer process
+    if let Err(mpsc::error::TrySendError::Full(_)) = queue.0.try_send(labels.into_iter().collect())
+    {
+        tracing::debug!("Label queue is full, dropping queried labels");
+    }
+}
+
+/// Saves queued labels, one insert per batch, until every sender is gone.
+pub async fn run_label_writer(db: PgPool, mut rx: mpsc::Receiver<Vec<String>>) {
+    while let Some(first) = rx.recv().await {
+        let mut labels: BTreeSet<String> = first.into_iter().collect();
+        while labels.len() < WRITE_BATCH {
+            match rx.try_recv() {
+                Ok(more) => labels.extend(more),
+                Err(_) => break,
+            }
+        }
+
+        let labels: Vec<String> = labels.into_iter().collect();
+        if let Err(e) = save_labels(&db, &labels, LabelSource::Query).await {
+            tracing::warn!("Couldn't save queried labels: {:?}", e);
+        }
+    }
+}
+
+/// Labels in a wordlist file, one per line. Blank lines, `#` comments and
+/// anything with a dot are skipped.
+pub fn wordlist_labels(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().to_lowercase())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.contains('.'))
+        .collect()
+}
+
+pub async fn label_for_hash(db: &PgPool, label_hash: B256) -> Result<Option<String>, CocoError> {
+    let label = sqlx::query_scalar("SELECT label FROM labels WHERE label_hash = $1")
+        .bind(label_hash.as_slice())
+        .fetch_optional(db)
+        .await?;
+
+    Ok(label)
+}
+
+pub async fn lookup_labels(
+    db: &PgPool,
+    label_hashes: &[B256],
+) -> Result<HashMap<B256, String>, CocoError> {
+    let hashes: Vec<&[u8]> = label_hashes.iter().map(|h| h.as_slice()).collect();
+
+    let rows: Vec<(Vec<u8>, String)> =
+        sqlx::query_as("SELECT label_hash, label FROM labels WHERE label_hash = ANY($1)")
+            .bind(hashes)
+            .fetch_all(db)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(hash, label)| (B256::from_slice(&hash), label))
+        .collect())
+}
+
+/// `label.tld`, or `[<label hash>].tld` when the label isn't known.
+pub fn display_name(label: Option<&str>, label_hash: B256, tld: &str) -> String {
+    match label {
+        Some(label) => format!("{}.{}", label, tld),
+        None => format!("[{}].{}", hex::encode(label_hash), tld),
+    }
+}
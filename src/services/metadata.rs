@@ -0,0 +1,259 @@
+//! metadata.rs
+//!
+//! ERC-721 / ERC-1155 metadata for name tokens. Registrar token ids are the
+//! label hash and NameWrapper ids the namehash, neither can be reversed, so
+//! labels come from the labels table. Tokens with an unknown label
+//! are still served, named `[<label hash>].eth` like the ENS app does.
+use crate::errors::CocoError;
+use crate::services::ens::{
+    BaseRegistrarImplementation, latest_block, normalise_and_hash_name_for,
+};
+use crate::services::labels::{display_name, label_for_hash};
+use crate::types::api::{AppState, MetadataAttribute, NetworkState, NftMetadataResponse};
+use alloy::eips::BlockId;
+use alloy::primitives::{B256, U256};
+use data_encoding::BASE64;
+use serde_json::json;
+use sqlx::PgPool;
+
+// longest name drawn on the card before it's cut with an ellipsis
+const MAX_CARD_CHARS: usize = 40;
+
+/// Full name for a NameWrapper token id, from its NameWrapped events.
+pub async fn wrapped_name(
+    db: &PgPool,
+    network: &NetworkState,
+    node: B256,
+) -> Result<Option<String>, CocoError> {
+    let name = sqlx::query_scalar(
+        r#"
+        SELECT name FROM wrapper_events
+        WHERE network = $1 AND node = $2 AND name IS NOT NULL
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(&network.name)
+    .bind(node.as_slice())
+    .fetch_optional(db)
+    .await?;
+
+    Ok(name)
+}
+
+// what the token's metadata and image are built from
+struct TokenInfo {
+    name: String,
+    // past the grace period, anyone can register it again
+    released: bool,
+    attributes: Vec<MetadataAttribute>,
+}
+
+pub async fn token_metadata(
+    state: &AppState,
+    network: &NetworkState,
+    token_id: U256,
+) -> Result<NftMetadataResponse, CocoError> {
+    let token = token_info(state, network, token_id).await?;
+
+    let description = if token.released {
+        format!("{}, an expired ENS name.", token.name)
+    } else {
+        format!("{}, an ENS name.", token.name)
+    };
+    let svg = render_card(&token.name, token.released);
+
+    Ok(NftMetadataResponse {
+        name: token.name,
+        description,
+        image: format!(
+            "data:image/svg+xml;base64,{}",
+            BASE64.encode(svg.as_bytes())
+        ),
+        attributes: token.attributes,
+    })
+}
+
+/// The token's SVG card on its own.
+pub async fn token_image(
+    state: &AppState,
+    network: &NetworkState,
+    token_id: U256,
+) -> Result<String, CocoError> {
+    let token = token_info(state, network, token_id).await?;
+    Ok(render_card(&token.name, token.released))
+}
+
+async fn token_info(
+    state: &AppState,
+    network: &NetworkState,
+    token_id: U256,
+) -> Result<TokenInfo, CocoError> {
+    let id = B256::from(token_id);
+
+    // registrar ids first, then wrapped 2LDs whose label hash we can work out
+    let (label, label_hash) = match label_for_hash(&state.connection, id).await? {
+        Some(label) => (Some(label), id),
+        None => match wrapped_name(&state.connection, network, id)
+            .await?
+            .and_then(|name| normalise_and_hash_name_for(&name, &network.tld).ok())
+        {
+            Some(normalised) => (Some(normalised.label), normalised.label_hash),
+            None => (None, id),
+        },
+    };
+
+    let (now, expires) = network
+        .pool
+        .execute(|provider| async move {
+            let block = latest_block(&provider).await?;
+            let registrar = BaseRegistrarImplementation::new(
+                network.ens_contract_addresses.base_registrar,
+                &provider,
+            );
+            let expires = registrar
+                .nameExpires(U256::from_be_bytes(*label_hash))
+                .block(BlockId::number(block.number))
+                .call()
+                .await?;
+            Ok((block.timestamp, expires))
+        })
+        .await?;
+
+    // never registered, or a token id from some other contract
+    let expires: u64 = expires.try_into().unwrap_or(u64::MAX);
+    if expires == 0 {
+        return Err(CocoError::UnknownToken(token_id.to_string()));
+    }
+
+    let name = display_name(label.as_deref(), label_hash, &network.tld);
+    let grace_period_secs = network.constants().grace_period_secs;
+    let released = now >= expires.saturating_add(grace_period_secs);
+
+    let (created, registered) = registration_dates(&state.connection, network, label_hash).await?;
+
+    let mut attributes = Vec::new();
+    if let Some(label) = &label {
+        attributes.push(MetadataAttribute {
+            trait_type: "Length",
+            display_type: Some("number"),
+            value: json!(label.chars().count()),
+        });
+        attributes.push(MetadataAttribute {
+            trait_type: "Character Set",
+            display_type: None,
+            value: json!(character_set(label)),
+        });
+    }
+    for (trait_type, date) in [
+        ("Created Date", created),
+        ("Registration Date", registered),
+        ("Expiration Date", Some(expires as i64)),
+    ] {
+        if let Some(date) = date {
+            attributes.push(MetadataAttribute {
+                trait_type,
+                display_type: Some("date"),
+                value: json!(date),
+            });
+        }
+    }
+
+    Ok(TokenInfo {
+        name,
+        released,
+        attributes,
+    })
+}
+
+// first and latest registration block times
+async fn registration_dates(
+    db: &PgPool,
+    network: &NetworkState,
+    label_hash: B256,
+) -> Result<(Option<i64>, Option<i64>), CocoError> {
+    let dates = sqlx::query_as(
+        r#"
+        SELECT
+            extract(epoch FROM min(registered_at))::bigint,
+            extract(epoch FROM (array_agg(registered_at ORDER BY block_number DESC, log_index DESC))[1])::bigint
+        FROM name_registrations
+        WHERE network = $1 AND label_hash = $2
+        "#,
+    )
+    .bind(&network.name)
+    .bind(label_hash.as_slice())
+    .fetch_one(db)
+    .await?;
+
+    Ok(dates)
+}
+
+/// Character set trait, matching the feed's char classes.
+pub fn character_set(label: &str) -> &'static str {
+    if !label.is_ascii() {
+        "unicode"
+    } else if label.bytes().all(|b| b.is_ascii_digit()) {
+        "digit"
+    } else if label.bytes().all(|b| b.is_ascii_lowercase()) {
+        "letter"
+    } else if label
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    {
+        "alphanumeric"
+    } else {
+        "mixed"
+    }
+}
+
+/// The token's image, a 270px card with the name along the bottom.
+pub fn render_card(name: &str, released: bool) -> String {
+    let chars = name.chars().count();
+    let shown: String = if chars > MAX_CARD_CHARS {
+        name.chars()
+            .take(MAX_CARD_CHARS - 1)
+            .chain(std::iter::once('…'))
+            .collect()
+    } else {
+        name.to_string()
+    };
+    // shrink long names to fit the card width
+    let font_size = match shown.chars().count() {
+        0..=12 => 32,
+        13..=18 => 24,
+        19..=26 => 18,
+        _ => 12,
+    };
+    let (from, to) = if released {
+        ("#c4c4c4", "#8a8a8a")
+    } else {
+        ("#44bcf0", "#a099ff")
+    };
+    let status = if released {
+        r##"<text x="30" y="70" font-size="14" fill="#fff" font-family="sans-serif">Expired</text>"##
+    } else {
+        ""
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="270" height="270" viewBox="0 0 270 270"><defs><linearGradient id="bg" x1="0" y1="0" x2="270" y2="270" gradientUnits="userSpaceOnUse"><stop stop-color="{from}"/><stop offset="1" stop-color="{to}"/></linearGradient></defs><rect width="270" height="270" fill="url(#bg)"/><circle cx="45" cy="42" r="15" fill="none" stroke="#fff" stroke-width="3"/>{status}<text x="30" y="231" font-size="{font_size}" fill="#fff" font-family="sans-serif" font-weight="bold">{name}</text></svg>"##,
+        name = escape_xml(&shown),
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
@@ -1 +1,24 @@
+pub mod avatar;
+pub mod batch;
+pub mod blocks;
+pub mod cache;
+pub mod coin_address;
+pub mod contenthash;
 pub mod ens;
+pub mod feed;
+pub mod hash;
+pub mod history;
+pub mod indexer;
+pub mod jobs;
+pub mod labels;
+pub mod metadata;
+pub mod multicall;
+pub mod pattern;
+pub mod portfolio;
+pub mod premium;
+pub mod proof;
+pub mod provider_pool;
+pub mod registrations;
+pub mod scan;
+pub mod suggest;
+pub mod watch;
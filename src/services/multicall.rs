@@ -0,0 +1,146 @@
+//! multicall.rs
+use crate::config::MulticallConfig;
+use alloy::providers::MulticallError;
+use futures::{StreamExt, TryStreamExt, stream};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    // which budget chunks take permits from, see `in_background`
+    static LANE: Lane;
+    // the pool attempt the chunks belong to, see `QueueClock`
+    static QUEUE: Arc<QueueClock>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lane {
+    Interactive,
+    Background,
+}
+
+/// A network's multicall settings and the permits its chunks share. Every
+/// chunk in flight holds one, whichever lookup, provider or request it's
+/// part of, so `concurrency` caps the network as a whole. Batch scans and
+/// jobs have their own `background_concurrency` so they can't starve
+/// interactive lookups.
+pub struct Multicall {
+    pub config: MulticallConfig,
+    permits: Semaphore,
+    background_permits: Semaphore,
+}
+
+impl Multicall {
+    pub fn new(config: MulticallConfig) -> Self {
+        Self {
+            permits: Semaphore::new(config.concurrency.max(1)),
+            background_permits: Semaphore::new(config.background_concurrency.max(1)),
+            config,
+        }
+    }
+
+    fn permits(&self, lane: Lane) -> &Semaphore {
+        match lane {
+            Lane::Interactive => &self.permits,
+            Lane::Background => &self.background_permits,
+        }
+    }
+}
+
+/// Runs `fut` with its chunks taking permits from the background budget.
+pub async fn in_background<F: Future>(fut: F) -> F::Output {
+    LANE.scope(Lane::Background, fut).await
+}
+
+/// Time a provider pool attempt spent with chunks waiting for permits. The
+/// pool leaves it out of its request timeout and latency, a local queue
+/// says nothing about the endpoint.
+#[derive(Default)]
+pub struct QueueClock {
+    state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    waiting: usize,
+    since: Option<Instant>,
+    waited: Duration,
+}
+
+// held while a chunk waits for its permit
+struct Waiting<'a>(&'a QueueClock);
+
+impl QueueClock {
+    /// Runs `fut` with its chunks' permit waits counted on this clock.
+    pub async fn run<F: Future>(self: &Arc<Self>, fut: F) -> F::Output {
+        QUEUE.scope(self.clone(), fut).await
+    }
+
+    /// Time spent queueing so far, overlapping waits counted once.
+    pub fn waited(&self) -> Duration {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.waited + state.since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    fn start(&self) -> Waiting<'_> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.waiting == 0 {
+            state.since = Some(Instant::now());
+        }
+        state.waiting += 1;
+        Waiting(self)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.waiting -= 1;
+        if state.waiting == 0
+            && let Some(since) = state.since.take()
+        {
+            state.waited += since.elapsed();
+        }
+    }
+}
+
+/// Runs `call` over `items` in chunks of `chunk_size`, each one waiting for a
+/// permit from `multicall`, from the background budget under
+/// [`in_background`]. Results are merged back in input order.
+pub async fn chunked<'a, T, R, F, Fut>(
+    items: &'a [T],
+    multicall: &Multicall,
+    call: F,
+) -> Result<Vec<R>, MulticallError>
+where
+    F: Fn(&'a [T]) -> Fut,
+    Fut: Future<Output = Result<Vec<R>, MulticallError>>,
+{
+    // futures are lazy, building them up front keeps `call` out of the stream's
+    // type, which spawned callers need to be provably Send
+    let calls: Vec<_> = items
+        .chunks(multicall.config.chunk_size.max(1))
+        .map(|chunk| {
+            let call = call(chunk);
+            async move {
+                let lane = LANE.try_with(|lane| *lane).unwrap_or(Lane::Interactive);
+                let queue = QUEUE.try_with(Arc::clone).ok();
+                let waiting = queue.as_deref().map(QueueClock::start);
+                // the semaphore is never closed, so this always holds a permit
+                let _permit = multicall.permits(lane).acquire().await;
+                drop(waiting);
+                call.await
+            }
+        })
+        .collect();
+    // the permits do the limiting, buffered (not buffer_unordered) keeps chunk order
+    let in_flight = calls.len().max(1);
+    let chunks: Vec<Vec<R>> = stream::iter(calls)
+        .buffered(in_flight)
+        .try_collect()
+        .await?;
+
+    Ok(chunks.into_iter().flatten().collect())
+}
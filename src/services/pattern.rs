@@ -0,0 +1,303 @@
+//! pattern.rs
+//!
+//! The pattern language scans are written in. A pattern is a run of tokens,
+//! each standing for one character:
+//!
+//! - `D` a digit, `L` a letter, `E` an emoji from `EMOJI_SET`
+//! - `[...]` one character from a set, with ranges like `[a-f0-3]`
+//! - anything else is itself, eg the `0x` in `0xDDD`
+//!
+//! Any token can be followed by `{n}` or `{n,m}` to repeat it. `DDD` is the
+//! 999 club, `D{4}` the 10k club and `LLL` every 3-letter name. Palindromes
+//! only expand the first half of each label and mirror it, so a palindrome
+//! `D{5}` is 1,000 labels to generate rather than 100,000 to filter.
+use crate::errors::CocoError;
+use std::collections::HashSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+pub const EMOJI_SET: [&str; 10] = ["🔥", "💎", "🚀", "👑", "🦊", "🌈", "🐸", "🍀", "💀", "👻"];
+// longest repeat, anything past it is far beyond what a scan could cover
+const MAX_REPEAT: usize = 32;
+// combinations of repeat counts, each one is expanded separately
+const MAX_SHAPES: usize = 1_000;
+// characters across every set in a pattern, counted from the ranges before
+// any set is built
+const MAX_SET_CHARS: u32 = 65_536;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    choices: Vec<String>,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, CocoError> {
+        let invalid = |why: String| CocoError::InvalidPattern(format!("{}: {}", pattern, why));
+        let mut chars = pattern.trim().chars().peekable();
+        let mut tokens = Vec::new();
+        let mut set_budget = MAX_SET_CHARS;
+
+        while let Some(c) = chars.next() {
+            let choices = match c {
+                'D' => ('0'..='9').map(String::from).collect(),
+                'L' => ('a'..='z').map(String::from).collect(),
+                'E' => EMOJI_SET.iter().map(|e| e.to_string()).collect(),
+                '[' => parse_set(&mut chars, &mut set_budget).map_err(invalid)?,
+                '{' => return Err(invalid("{ has nothing to repeat".to_string())),
+                c if c.is_uppercase() => {
+                    return Err(invalid(format!("unknown class {}, use D, L or E", c)));
+                }
+                c if !is_label_char(c) => {
+                    return Err(invalid(format!("{:?} can't be part of a name", c)));
+                }
+                c => vec![c.to_string()],
+            };
+            let (min, max) = if chars.next_if_eq(&'{').is_some() {
+                parse_repeat(&mut chars).map_err(invalid)?
+            } else {
+                (1, 1)
+            };
+            tokens.push(Token { choices, min, max });
+        }
+
+        if tokens.is_empty() {
+            return Err(invalid("pattern is empty".to_string()));
+        }
+        let shapes = tokens
+            .iter()
+            .fold(1usize, |n, t| n.saturating_mul(t.max - t.min + 1));
+        if shapes > MAX_SHAPES {
+            return Err(invalid("too many repeat ranges".to_string()));
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// How many labels the pattern expands to, before duplicates are removed.
+    pub fn count(&self, palindrome: bool) -> u64 {
+        self.shapes()
+            .iter()
+            .filter(|shape| !shape.is_empty())
+            .map(|shape| {
+                free_positions(shape, palindrome)
+                    .iter()
+                    .fold(1u64, |n, choices| n.saturating_mul(choices.len() as u64))
+            })
+            .fold(0u64, u64::saturating_add)
+    }
+
+    /// Every label the pattern matches, shortest first, as long as there are
+    /// no more than `limit` of them.
+    pub fn expand(&self, palindrome: bool, limit: usize) -> Result<Vec<String>, CocoError> {
+        let count = self.count(palindrome);
+        if count > limit as u64 {
+            return Err(CocoError::TooManyNames {
+                requested: usize::try_from(count).unwrap_or(usize::MAX),
+                limit,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        let mut labels = Vec::new();
+        for shape in self.shapes() {
+            let free = free_positions(&shape, palindrome);
+            if free.is_empty() || free.iter().any(|choices| choices.is_empty()) {
+                continue;
+            }
+
+            // odometer over the free positions, last one turning fastest
+            let mut picks = vec![0; free.len()];
+            'shape: loop {
+                let mut parts: Vec<&str> = picks.iter().zip(&free).map(|(&i, c)| c[i]).collect();
+                if palindrome {
+                    let mirrored: Vec<&str> =
+                        parts[..shape.len() / 2].iter().rev().copied().collect();
+                    parts.extend(mirrored);
+                }
+                let label = parts.concat();
+                if seen.insert(label.clone()) {
+                    labels.push(label);
+                }
+
+                let mut position = free.len();
+                loop {
+                    if position == 0 {
+                        break 'shape;
+                    }
+                    position -= 1;
+                    picks[position] += 1;
+                    if picks[position] < free[position].len() {
+                        break;
+                    }
+                    picks[position] = 0;
+                }
+            }
+        }
+
+        Ok(labels)
+    }
+
+    // one list of choices per character, for every combination of repeat
+    // counts, shortest first
+    fn shapes(&self) -> Vec<Vec<&[String]>> {
+        let mut repeats: Vec<Vec<usize>> = vec![Vec::new()];
+        for token in &self.tokens {
+            repeats = repeats
+                .into_iter()
+                .flat_map(|counts| {
+                    (token.min..=token.max).map(move |n| {
+                        let mut counts = counts.clone();
+                        counts.push(n);
+                        counts
+                    })
+                })
+                .collect();
+        }
+
+        let mut shapes: Vec<Vec<&[String]>> = repeats
+            .into_iter()
+            .map(|counts| {
+                self.tokens
+                    .iter()
+                    .zip(counts)
+                    .flat_map(|(token, n)| std::iter::repeat_n(token.choices.as_slice(), n))
+                    .collect()
+            })
+            .collect();
+        shapes.sort_by_key(|shape| shape.len());
+
+        shapes
+    }
+}
+
+// positions that can be picked freely: all of them, or for palindromes the
+// first half, each limited to what its mirror position allows too
+fn free_positions<'a>(shape: &[&'a [String]], palindrome: bool) -> Vec<Vec<&'a str>> {
+    if !palindrome {
+        return shape
+            .iter()
+            .map(|choices| choices.iter().map(String::as_str).collect())
+            .collect();
+    }
+
+    let len = shape.len();
+    (0..len.div_ceil(2))
+        .map(|i| {
+            let (choices, mirror) = (shape[i], shape[len - 1 - i]);
+            // usually the same token on both sides
+            if std::ptr::eq(choices, mirror) {
+                return choices.iter().map(String::as_str).collect();
+            }
+            let mirror: HashSet<&String> = mirror.iter().collect();
+            choices
+                .iter()
+                .filter(|c| mirror.contains(c))
+                .map(String::as_str)
+                .collect()
+        })
+        .collect()
+}
+
+fn parse_set(chars: &mut Peekable<Chars>, budget: &mut u32) -> Result<Vec<String>, String> {
+    let mut body = Vec::new();
+    loop {
+        match chars.next() {
+            Some(']') => break,
+            Some(c) if is_label_char(c) => body.push(c),
+            Some(c) => return Err(format!("{:?} can't be part of a name", c)),
+            None => return Err("[ is never closed".to_string()),
+        }
+    }
+
+    // size every range before building any of them
+    let mut i = 0;
+    let mut size: u32 = 0;
+    while i < body.len() {
+        if i + 2 < body.len() && body[i + 1] == '-' {
+            let (from, to) = (body[i], body[i + 2]);
+            size = size.saturating_add((to as u32).saturating_sub(from as u32) + 1);
+            i += 3;
+        } else {
+            size = size.saturating_add(1);
+            i += 1;
+        }
+    }
+    *budget = budget
+        .checked_sub(size)
+        .ok_or_else(|| format!("sets are limited to {} characters", MAX_SET_CHARS))?;
+
+    let mut set: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        // a dash at either end is just a dash
+        if i + 2 < body.len() && body[i + 1] == '-' {
+            let (from, to) = (body[i], body[i + 2]);
+            if from > to {
+                return Err(format!("range {}-{} is backwards", from, to));
+            }
+            set.extend((from..=to).filter(|c| is_label_char(*c)).map(String::from));
+            i += 3;
+        } else {
+            set.push(body[i].to_string());
+            i += 1;
+        }
+    }
+
+    let mut seen = HashSet::new();
+    set.retain(|c| seen.insert(c.clone()));
+    if set.is_empty() {
+        return Err("[] matches nothing".to_string());
+    }
+
+    Ok(set)
+}
+
+fn parse_repeat(chars: &mut Peekable<Chars>) -> Result<(usize, usize), String> {
+    let mut body = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => body.push(c),
+            None => return Err("{ is never closed".to_string()),
+        }
+    }
+
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("{{{}}} isn't a repeat count", body))
+    };
+    let (min, max) = match body.split_once(',') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => {
+            let n = parse(&body)?;
+            (n, n)
+        }
+    };
+
+    if min > max {
+        return Err(format!("{{{}}} is backwards", body));
+    }
+    if max > MAX_REPEAT {
+        return Err(format!("repeats are limited to {}", MAX_REPEAT));
+    }
+
+    Ok((min, max))
+}
+
+// lowercase ASCII, digits, hyphens and underscores, plus any non-ASCII
+// character that isn't uppercase or whitespace
+fn is_label_char(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+    } else {
+        !c.is_uppercase() && !c.is_whitespace()
+    }
+}
@@ -0,0 +1,93 @@
+//! portfolio.rs
+//!
+//! Names an address holds, from the indexer's registrar transfers: a name
+//! belongs to whoever its latest transfer went to. Names past their grace
+//! period are left out since anyone can register them again. Wrapped names
+//! are held by the NameWrapper at the registrar, so they don't show up here.
+use crate::errors::CocoError;
+use crate::services::indexer::indexed_block;
+use crate::services::labels::{display_name, lookup_labels};
+use crate::types::api::NetworkState;
+use alloy::primitives::{Address, B256};
+use sqlx::PgPool;
+
+#[derive(Debug)]
+pub struct PortfolioEntry {
+    pub name: String,
+    pub label_hash: B256,
+    pub expires: i64,
+}
+
+#[derive(Debug)]
+pub struct PortfolioPage {
+    pub indexed_block: Option<u64>,
+    pub rows: Vec<PortfolioEntry>,
+}
+
+/// Names `address` holds as of `now` (unix seconds), soonest expiry first.
+pub async fn address_portfolio(
+    db: &PgPool,
+    network: &NetworkState,
+    address: Address,
+    now: i64,
+) -> Result<PortfolioPage, CocoError> {
+    let grace_period_secs = network.constants().grace_period_secs as i64;
+
+    let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as(
+        r#"
+        WITH latest AS (
+            SELECT DISTINCT ON (label_hash) label_hash, to_address
+            FROM name_transfers
+            WHERE network = $1 AND label_hash IN (
+                SELECT label_hash FROM name_transfers WHERE network = $1 AND to_address = $2
+            )
+            ORDER BY label_hash, block_number DESC, log_index DESC
+        ), held AS (
+            SELECT l.label_hash, (
+                -- the latest registration or renewal sets the expiry
+                SELECT extract(epoch FROM expires_at)::bigint FROM (
+                    SELECT expires_at, block_number, log_index FROM name_registrations r
+                    WHERE r.network = $1 AND r.label_hash = l.label_hash
+                    UNION ALL
+                    SELECT expires_at, block_number, log_index FROM name_renewals r
+                    WHERE r.network = $1 AND r.label_hash = l.label_hash
+                ) e
+                ORDER BY block_number DESC, log_index DESC
+                LIMIT 1
+            ) AS expires
+            FROM latest l
+            WHERE l.to_address = $2
+        )
+        SELECT label_hash, expires
+        FROM held
+        WHERE expires + $3 > $4
+        ORDER BY expires, label_hash
+        "#,
+    )
+    .bind(&network.name)
+    .bind(address.as_slice())
+    .bind(grace_period_secs)
+    .bind(now)
+    .fetch_all(db)
+    .await?;
+
+    let label_hashes: Vec<B256> = rows.iter().map(|(h, _)| B256::from_slice(h)).collect();
+    let labels = lookup_labels(db, &label_hashes).await?;
+
+    Ok(PortfolioPage {
+        indexed_block: indexed_block(db, &network.name).await?,
+        rows: label_hashes
+            .into_iter()
+            .zip(rows)
+            .map(|(label_hash, (_, expires))| PortfolioEntry {
+                name: display_name(
+                    labels.get(&label_hash).map(String::as_str),
+                    label_hash,
+                    &network.tld,
+                ),
+                label_hash,
+                expires,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,48 @@
+//! premium.rs
+//!
+//! Off-chain model of the ENS `ExponentialPremiumPriceOracle` curve. A name
+//! that leaves its grace period starts at `START_PREMIUM_USD` and the premium
+//! halves every day until it hits zero after `PREMIUM_DECAY_DAYS`.
+const SECS_PER_DAY: f64 = 86_400.0;
+
+pub const START_PREMIUM_USD: f64 = 100_000_000.0;
+pub const PREMIUM_DECAY_DAYS: f64 = 21.0;
+
+// the oracle subtracts the value the curve would have at the end of the
+// auction so the premium lands exactly on zero
+fn end_value_usd() -> f64 {
+    START_PREMIUM_USD * 0.5f64.powf(PREMIUM_DECAY_DAYS)
+}
+
+/// Premium in USD `elapsed_secs` after the name was released.
+pub fn premium_usd_at(elapsed_secs: i64) -> f64 {
+    if elapsed_secs < 0 {
+        return START_PREMIUM_USD - end_value_usd();
+    }
+
+    let days = elapsed_secs as f64 / SECS_PER_DAY;
+    if days >= PREMIUM_DECAY_DAYS {
+        return 0.0;
+    }
+
+    (START_PREMIUM_USD * 0.5f64.powf(days) - end_value_usd()).max(0.0)
+}
+
+/// Seconds after release until `base_usd + premium` drops to `threshold_usd` or below.
+/// `None` when the threshold is below the base price, since the total never goes lower.
+pub fn secs_until_price_below(threshold_usd: f64, base_usd: f64) -> Option<i64> {
+    let premium_budget = threshold_usd - base_usd;
+    if premium_budget < 0.0 {
+        return None;
+    }
+
+    // solve START * 0.5^d - END <= budget for d
+    let target = (premium_budget + end_value_usd()) / START_PREMIUM_USD;
+    if target >= 1.0 {
+        return Some(0);
+    }
+
+    let days = (-target.log2()).min(PREMIUM_DECAY_DAYS);
+
+    Some((days * SECS_PER_DAY).ceil() as i64)
+}
@@ -0,0 +1,246 @@
+//! proof.rs
+//!
+//! Checks base registrar reads with eth_getProof instead of taking the RPC's
+//! eth_call results on trust. The block header is checked against a trusted
+//! hash, the registrar account against the header's state root, and the
+//! `expiries` and `_tokenOwner` slots against the account's storage root.
+use crate::errors::CocoError;
+use crate::services::ens::BaseRegistrarImplementation::BaseRegistrarImplementationInstance;
+use crate::services::ens::{BaseRegistrarImplementation, check_name_limit, normalise_names};
+use crate::services::multicall::{Multicall, chunked};
+use crate::types::alloy_providers::AppProvider;
+use crate::types::api::{NetworkState, VerifiedBlock, VerifiedName};
+use crate::types::ens::RegistrarStorageLayout;
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, U256, keccak256};
+use alloy::providers::{MulticallError, Network, Provider};
+use alloy::rpc::types::EIP1186AccountProofResponse;
+use alloy::trie::{Nibbles, TrieAccount, proof::verify_proof};
+
+/// Registrar storage for one token, proven against a state root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProvenSlots {
+    pub expires: U256,
+    // left in place after expiry, only ownerOf hides it
+    pub owner: Address,
+}
+
+/// Proves `nameExpires` and `ownerOf` for each name at `block_hash`, falling
+/// back to the network's configured trusted block.
+pub async fn verify_names(
+    network: &NetworkState,
+    names: &[String],
+    block_hash: Option<B256>,
+) -> Result<VerifiedBlock, CocoError> {
+    check_name_limit(network, names)?;
+
+    let layout = network.registrar_layout.ok_or_else(|| {
+        CocoError::ProofUnavailable(format!(
+            "No registrar storage layout configured for {}",
+            network.name
+        ))
+    })?;
+    let trusted_hash = block_hash.or(network.trusted_block_hash).ok_or_else(|| {
+        CocoError::ProofUnavailable(format!(
+            "No block_hash given and no trusted block configured for {}",
+            network.name
+        ))
+    })?;
+
+    let normalised_names = normalise_names(names, &network.tld);
+    let token_ids: Vec<U256> = normalised_names
+        .iter()
+        .map(|n| U256::from_be_bytes(*n.label_hash))
+        .collect();
+
+    network
+        .pool
+        .execute(|provider| {
+            let token_ids = &token_ids;
+            let normalised_names = &normalised_names;
+            async move {
+                let block = provider
+                    .get_block_by_hash(trusted_hash)
+                    .await?
+                    .ok_or(CocoError::BlockNotFound)?;
+
+                // the state root is only as good as the header it came in
+                if block.header.inner.hash_slow() != trusted_hash {
+                    return Err(CocoError::InvalidProof(
+                        "block header doesn't hash to the trusted block hash".to_string(),
+                    ));
+                }
+
+                let registrar = network.ens_contract_addresses.base_registrar;
+                let block_id = BlockId::hash(trusted_hash);
+                let keys: Vec<B256> = token_ids
+                    .iter()
+                    .flat_map(|id| {
+                        let (expires, owner) = registrar_slot_keys(layout, *id);
+                        [expires, owner]
+                    })
+                    .collect();
+
+                let proof = provider
+                    .get_proof(registrar, keys)
+                    .block_id(block_id)
+                    .await?;
+                let proven = verify_registrar_proof(
+                    block.header.state_root,
+                    registrar,
+                    layout,
+                    token_ids,
+                    &proof,
+                )?;
+
+                let base_registrar = BaseRegistrarImplementation::new(registrar, &provider);
+                let rpc = read_registrar(
+                    &provider,
+                    &base_registrar,
+                    token_ids,
+                    block_id,
+                    &network.multicall,
+                )
+                .await?;
+
+                let now = U256::from(block.header.timestamp);
+                let values = normalised_names
+                    .iter()
+                    .zip(proven)
+                    .zip(rpc)
+                    .map(|((name, slots), (rpc_expires, rpc_owner))| {
+                        // ownerOf reverts once a name expires, mirror that
+                        let owner = (slots.expires > now).then_some(slots.owner);
+
+                        VerifiedName {
+                            name: name.name.clone(),
+                            expires: slots.expires,
+                            owner,
+                            rpc_expires,
+                            rpc_owner,
+                            matches_rpc: slots.expires == rpc_expires && owner == rpc_owner,
+                        }
+                    })
+                    .collect();
+
+                Ok(VerifiedBlock {
+                    block_number: block.header.number,
+                    block_hash: trusted_hash,
+                    state_root: block.header.state_root,
+                    values,
+                })
+            }
+        })
+        .await
+}
+
+/// Storage keys of `expiries[token_id]` and `_tokenOwner[token_id]`.
+pub fn registrar_slot_keys(layout: RegistrarStorageLayout, token_id: U256) -> (B256, B256) {
+    (
+        mapping_slot(token_id, layout.expiries_slot),
+        mapping_slot(token_id, layout.token_owner_slot),
+    )
+}
+
+/// Checks the registrar account against `state_root` and every requested
+/// slot against the account's storage root, then returns the proven values
+/// in `token_ids` order.
+pub fn verify_registrar_proof(
+    state_root: B256,
+    registrar: Address,
+    layout: RegistrarStorageLayout,
+    token_ids: &[U256],
+    proof: &EIP1186AccountProofResponse,
+) -> Result<Vec<ProvenSlots>, CocoError> {
+    if proof.address != registrar {
+        return Err(CocoError::InvalidProof(format!(
+            "proof is for {} instead of the registrar",
+            proof.address
+        )));
+    }
+
+    let account = TrieAccount::new(
+        proof.nonce,
+        proof.balance,
+        proof.storage_hash,
+        proof.code_hash,
+    );
+    verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(registrar)),
+        Some(alloy::rlp::encode(account)),
+        &proof.account_proof,
+    )
+    .map_err(|e| CocoError::InvalidProof(format!("account proof: {}", e)))?;
+
+    token_ids
+        .iter()
+        .map(|id| {
+            let (expires_key, owner_key) = registrar_slot_keys(layout, *id);
+            let expires = proven_slot(proof, expires_key)?;
+            let owner = proven_slot(proof, owner_key)?;
+
+            Ok(ProvenSlots {
+                expires,
+                owner: Address::from_word(owner.into()),
+            })
+        })
+        .collect()
+}
+
+// solidity keeps mapping values at keccak256(key . slot)
+fn mapping_slot(key: U256, slot: u64) -> B256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    preimage[32..].copy_from_slice(&U256::from(slot).to_be_bytes::<32>());
+    keccak256(preimage)
+}
+
+fn proven_slot(proof: &EIP1186AccountProofResponse, key: B256) -> Result<U256, CocoError> {
+    let storage = proof
+        .storage_proof
+        .iter()
+        .find(|s| s.key.as_b256() == key)
+        .ok_or_else(|| CocoError::InvalidProof(format!("no storage proof for slot {}", key)))?;
+
+    // zero slots are absent from the trie, so they're proven by exclusion
+    let expected = (!storage.value.is_zero()).then(|| alloy::rlp::encode(storage.value));
+    verify_proof(
+        proof.storage_hash,
+        Nibbles::unpack(keccak256(key)),
+        expected,
+        &storage.proof,
+    )
+    .map_err(|e| CocoError::InvalidProof(format!("storage proof for slot {}: {}", key, e)))?;
+
+    Ok(storage.value)
+}
+
+async fn read_registrar<P, N>(
+    provider: &AppProvider,
+    base_registrar: &BaseRegistrarImplementationInstance<P, N>,
+    token_ids: &[U256],
+    block: BlockId,
+    multicall: &Multicall,
+) -> Result<Vec<(U256, Option<Address>)>, MulticallError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    chunked(token_ids, multicall, |chunk| async move {
+        let mut expires = provider.multicall().dynamic().block(block);
+        let mut owners = provider.multicall().dynamic().block(block);
+        for id in chunk {
+            expires = expires.add_dynamic(base_registrar.nameExpires(*id));
+            owners = owners.add_dynamic(base_registrar.ownerOf(*id));
+        }
+
+        // ownerOf reverts for expired names, which is an answer rather than an error
+        let (expires, owners) = tokio::try_join!(expires.aggregate(), owners.try_aggregate(false))?;
+        Ok(expires
+            .into_iter()
+            .zip(owners.into_iter().map(Result::ok))
+            .collect())
+    })
+    .await
+}
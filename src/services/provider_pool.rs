@@ -0,0 +1,374 @@
+//! provider_pool.rs
+//!
+//! A set of RPC endpoints for one network. Requests go to a latency-weighted
+//! endpoint and fail over to the others on transport errors. Endpoints that
+//! keep failing are taken out of rotation by a circuit breaker until a health
+//! check or a trial request succeeds again.
+use crate::config::RpcPoolConfig;
+use crate::errors::CocoError;
+use crate::services::multicall::QueueClock;
+use crate::types::alloy_providers::AppProvider;
+use crate::types::api::AppState;
+use alloy::providers::{Provider, ProviderBuilder};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// weight of the newest sample in the latency moving average
+const LATENCY_SMOOTHING: f64 = 0.2;
+// latency assumed for endpoints we haven't measured yet
+const UNMEASURED_LATENCY_MS: f64 = 250.0;
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+pub struct ProviderPool {
+    network: String,
+    endpoints: Vec<Endpoint>,
+    config: RpcPoolConfig,
+}
+
+struct Endpoint {
+    label: String,
+    provider: AppProvider,
+    stats: Mutex<EndpointStats>,
+}
+
+#[derive(Default)]
+struct EndpointStats {
+    latency_ms: Option<f64>,
+    requests: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointDiagnostics {
+    pub endpoint: String,
+    pub circuit: CircuitState,
+    pub latency_ms: Option<f64>,
+    pub requests: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl ProviderPool {
+    /// Builds a provider for every url. Urls that fail to connect are skipped.
+    pub async fn connect(
+        network: &str,
+        urls: &[String],
+        config: RpcPoolConfig,
+    ) -> Result<Self, CocoError> {
+        let mut endpoints = Vec::with_capacity(urls.len());
+        let mut last_error = None;
+
+        for (idx, url) in urls.iter().enumerate() {
+            match ProviderBuilder::new().connect(url).await {
+                Ok(provider) => endpoints.push(Endpoint {
+                    label: format!("#{} {}", idx, redact_url(url)),
+                    provider,
+                    stats: Mutex::new(EndpointStats::default()),
+                }),
+                Err(e) => {
+                    tracing::warn!("Skipping {} endpoint {}: {}", network, redact_url(url), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if endpoints.is_empty() {
+            return Err(match last_error {
+                Some(e) => CocoError::Rpc(e),
+                None => CocoError::NoHealthyProvider(network.to_string()),
+            });
+        }
+
+        Ok(Self {
+            network: network.to_string(),
+            endpoints,
+            config,
+        })
+    }
+
+    /// Runs `op` against the preferred endpoint, failing over to the next one
+    /// whenever it returns a transport error, timeout, rate limit or is
+    /// missing historical state. Time spent waiting for multicall permits
+    /// doesn't count towards the timeout. Once every
+    /// endpoint has failed the whole pass is retried after a jittered backoff,
+    /// up to `max_retries` times. Other errors are returned as is.
+    pub async fn execute<T, F, Fut>(&self, op: F) -> Result<T, CocoError>
+    where
+        F: Fn(AppProvider) -> Fut,
+        Fut: Future<Output = Result<T, CocoError>>,
+    {
+        let timeout = Duration::from_secs(self.config.request_timeout_secs);
+        let mut last_error = None;
+
+        for attempt in 0..=self.config.max_retries {
+            let order = self.selection_order();
+            // every circuit is open, waiting won't close them in time
+            if order.is_empty() {
+                break;
+            }
+
+            if attempt > 0 {
+                tokio::time::sleep(self.backoff(attempt)).await;
+            }
+
+            for idx in order {
+                let endpoint = &self.endpoints[idx];
+                let started = Instant::now();
+                let queue = Arc::new(QueueClock::default());
+
+                let result = timed_attempt(timeout, &queue, op(endpoint.provider.clone())).await;
+                let elapsed = started.elapsed().saturating_sub(queue.waited());
+
+                match result {
+                    Err(e) if e.is_transport_error() => {
+                        tracing::warn!(
+                            "{} endpoint {} failed, failing over: {}",
+                            self.network,
+                            endpoint.label,
+                            e
+                        );
+                        endpoint.record_failure(&e, &self.config);
+                        last_error = Some(e);
+                    }
+                    // healthy, just pruned, another endpoint may be an archive node
+                    Err(CocoError::ArchiveRequired) => {
+                        endpoint.record_success(elapsed);
+                        last_error = Some(CocoError::ArchiveRequired);
+                    }
+                    // reverts and bad input still mean the endpoint answered
+                    result => {
+                        endpoint.record_success(elapsed);
+                        return result;
+                    }
+                }
+            }
+
+            // pruned state doesn't come back with a retry
+            if matches!(last_error, Some(CocoError::ArchiveRequired)) {
+                break;
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| CocoError::NoHealthyProvider(self.network.clone())))
+    }
+
+    // "full jitter": uniform between zero and the exponential cap, so
+    // clients that failed together don't retry together
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap_ms = self
+            .config
+            .retry_base_delay_ms
+            .saturating_mul(1 << (attempt - 1).min(10))
+            .min(MAX_BACKOFF_MS);
+
+        Duration::from_millis((rand::random::<f64>() * cap_ms as f64) as u64)
+    }
+
+    /// `quorum_size` distinct endpoints with closed circuits, in selection order.
+    pub fn quorum_providers(&self) -> Result<Vec<(String, AppProvider)>, CocoError> {
+        let required = self.config.quorum_size.max(1);
+        let now = Instant::now();
+
+        let providers: Vec<(String, AppProvider)> = self
+            .selection_order()
+            .into_iter()
+            .map(|idx| &self.endpoints[idx])
+            // half open endpoints are on probation, don't let them vote
+            .filter(|endpoint| {
+                let stats = endpoint.stats.lock().unwrap_or_else(|e| e.into_inner());
+                stats.circuit(now) == CircuitState::Closed
+            })
+            .take(required)
+            .map(|endpoint| (endpoint.label.clone(), endpoint.provider.clone()))
+            .collect();
+
+        if providers.len() < required {
+            return Err(CocoError::QuorumUnavailable {
+                required,
+                available: providers.len(),
+            });
+        }
+
+        Ok(providers)
+    }
+
+    /// Pings every endpoint, including open circuits so they can recover.
+    pub async fn check_health(&self) {
+        let timeout = Duration::from_secs(self.config.health_check_timeout_secs);
+
+        for endpoint in &self.endpoints {
+            let started = Instant::now();
+            let result = tokio::time::timeout(timeout, endpoint.provider.get_block_number()).await;
+
+            match result {
+                Ok(Ok(_)) => endpoint.record_success(started.elapsed()),
+                Ok(Err(e)) => endpoint.record_failure(&CocoError::Rpc(e), &self.config),
+                Err(_) => endpoint.record_failure(&CocoError::RpcTimeout, &self.config),
+            }
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<EndpointDiagnostics> {
+        let now = Instant::now();
+
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats.lock().unwrap_or_else(|e| e.into_inner());
+
+                EndpointDiagnostics {
+                    endpoint: endpoint.label.clone(),
+                    circuit: stats.circuit(now),
+                    latency_ms: stats.latency_ms,
+                    requests: stats.requests,
+                    failures: stats.failures,
+                    consecutive_failures: stats.consecutive_failures,
+                    last_error: stats.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+
+    // First pick is weighted by inverse latency so traffic favours fast
+    // endpoints without starving the rest. Failover goes fastest first.
+    fn selection_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut candidates: Vec<(usize, f64)> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, endpoint)| {
+                let stats = endpoint.stats.lock().unwrap_or_else(|e| e.into_inner());
+                (stats.circuit(now) != CircuitState::Open)
+                    .then(|| (idx, stats.latency_ms.unwrap_or(UNMEASURED_LATENCY_MS)))
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let total_weight: f64 = candidates.iter().map(|(_, ms)| 1.0 / (ms + 1.0)).sum();
+        let mut pick = rand::random::<f64>() * total_weight;
+        let first = candidates
+            .iter()
+            .position(|(_, ms)| {
+                pick -= 1.0 / (ms + 1.0);
+                pick <= 0.0
+            })
+            .unwrap_or(0);
+
+        let mut order: Vec<usize> = candidates.iter().map(|(idx, _)| *idx).collect();
+        if !order.is_empty() {
+            let chosen = order.remove(first);
+            order.insert(0, chosen);
+        }
+
+        order
+    }
+}
+
+impl Endpoint {
+    fn record_success(&self, elapsed: Duration) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let sample = elapsed.as_secs_f64() * 1_000.0;
+
+        stats.requests += 1;
+        stats.consecutive_failures = 0;
+        stats.open_until = None;
+        stats.latency_ms = Some(match stats.latency_ms {
+            Some(avg) => avg + LATENCY_SMOOTHING * (sample - avg),
+            None => sample,
+        });
+    }
+
+    fn record_failure(&self, error: &CocoError, config: &RpcPoolConfig) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+
+        stats.requests += 1;
+        stats.failures += 1;
+        stats.consecutive_failures += 1;
+        stats.last_error = Some(error.to_string());
+
+        if stats.consecutive_failures >= config.failure_threshold {
+            stats.open_until =
+                Some(Instant::now() + Duration::from_secs(config.circuit_cooldown_secs));
+        }
+    }
+}
+
+impl EndpointStats {
+    // once the cooldown passes the next request is a trial (half open)
+    fn circuit(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            Some(until) if now < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+// `op` with `timeout` of endpoint time, the deadline moves back by however
+// long its chunks have queued for multicall permits
+async fn timed_attempt<T, Fut>(
+    timeout: Duration,
+    queue: &Arc<QueueClock>,
+    op: Fut,
+) -> Result<T, CocoError>
+where
+    Fut: Future<Output = Result<T, CocoError>>,
+{
+    let started = tokio::time::Instant::now();
+    let op = queue.run(op);
+    tokio::pin!(op);
+
+    loop {
+        let deadline = started + timeout + queue.waited();
+        tokio::select! {
+            result = &mut op => return result,
+            _ = tokio::time::sleep_until(deadline) => {
+                if started + timeout + queue.waited() <= tokio::time::Instant::now() {
+                    return Err(CocoError::RpcTimeout);
+                }
+            }
+        }
+    }
+}
+
+/// Periodically health checks every network's endpoints.
+pub async fn run_health_checks(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.app_config.rpc_pool.health_check_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+        for network in state.networks.values() {
+            network.pool.check_health().await;
+        }
+    }
+}
+
+// rpc urls usually carry an api key in the path, only show scheme and host
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split(['/', '?']).next().unwrap_or(rest);
+            format!("{}://{}", scheme, host)
+        }
+        None => "<invalid url>".to_string(),
+    }
+}
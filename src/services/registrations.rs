@@ -0,0 +1,272 @@
+//! registrations.rs
+//!
+//! Recent registrations from the indexer's tables, and a live stream of new
+//! ones. The indexer runs as its own process and notifies
+//! `REGISTRATIONS_CHANNEL` when it commits registrations. One listener per
+//! api process rebroadcasts those to every stream, which then reads
+//! everything after the last row it sent.
+use crate::errors::CocoError;
+use crate::services::indexer::REGISTRATIONS_CHANNEL;
+use crate::types::api::{AppState, NetworkState, RegistrationResponse};
+use alloy::primitives::{Address, B256};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+// rows read per query while a stream catches up
+const STREAM_PAGE: i64 = 100;
+// notices a slow stream can fall behind by before it re-reads anyway
+const NOTICE_CAPACITY: usize = 64;
+const LISTEN_RETRY_SECS: u64 = 5;
+
+/// Where an event sits in the chain, as `<block>-<log index>`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct EventPosition {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl EventPosition {
+    pub fn encode(&self) -> String {
+        format!("{}-{}", self.block_number, self.log_index)
+    }
+
+    pub fn decode(position: &str) -> Result<Self, CocoError> {
+        let (block_number, log_index) = position
+            .split_once('-')
+            .ok_or(CocoError::InvalidQueryInput)?;
+
+        Ok(Self {
+            block_number: block_number
+                .parse()
+                .map_err(|_| CocoError::InvalidQueryInput)?,
+            log_index: log_index
+                .parse()
+                .map_err(|_| CocoError::InvalidQueryInput)?,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct RegistrationRow {
+    label: Option<String>,
+    label_hash: Vec<u8>,
+    owner: Vec<u8>,
+    base_cost: Option<String>,
+    premium: Option<String>,
+    expires: i64,
+    registered_at: Option<i64>,
+    block_number: i64,
+    tx_hash: Vec<u8>,
+    log_index: i64,
+}
+
+const REGISTRATION_COLUMNS: &str = r#"
+    label, label_hash, owner, base_cost::text AS base_cost, premium::text AS premium,
+    extract(epoch FROM expires_at)::bigint AS expires,
+    extract(epoch FROM registered_at)::bigint AS registered_at,
+    block_number, tx_hash, log_index
+"#;
+
+/// Newest registrations first, starting below `before` when given, and the
+/// cursor for the next page if there is one.
+pub async fn recent_registrations(
+    db: &PgPool,
+    network: &NetworkState,
+    before: Option<EventPosition>,
+    limit: Option<u32>,
+) -> Result<(Vec<RegistrationResponse>, Option<EventPosition>), CocoError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let rows: Vec<RegistrationRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT {REGISTRATION_COLUMNS}
+        FROM name_registrations
+        WHERE network = $1
+            AND ($2::bigint IS NULL OR (block_number, log_index) < ($2, $3))
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT $4
+        "#
+    ))
+    .bind(&network.name)
+    .bind(before.map(|p| p.block_number as i64))
+    .bind(before.map(|p| p.log_index as i64))
+    // one extra row says whether another page follows
+    .bind(i64::from(limit) + 1)
+    .fetch_all(db)
+    .await?;
+
+    let more = rows.len() > limit as usize;
+    let values: Vec<RegistrationResponse> = rows
+        .into_iter()
+        .take(limit as usize)
+        .map(|r| registration_response(r, network))
+        .collect();
+    let next = values.last().filter(|_| more).map(|r| EventPosition {
+        block_number: r.block_number,
+        log_index: r.log_index,
+    });
+
+    Ok((values, next))
+}
+
+/// Position of the newest indexed registration, if there is one.
+pub async fn latest_registration(
+    db: &PgPool,
+    network: &NetworkState,
+) -> Result<Option<EventPosition>, CocoError> {
+    let row: Option<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT block_number, log_index
+        FROM name_registrations
+        WHERE network = $1
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(&network.name)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|(block_number, log_index)| EventPosition {
+        block_number: block_number as u64,
+        log_index: log_index as u64,
+    }))
+}
+
+/// Sender for `AppState::registrations`, fed by `run_registration_listener`.
+pub fn registration_notices() -> broadcast::Sender<String> {
+    broadcast::channel(NOTICE_CAPACITY).0
+}
+
+/// Listens on `REGISTRATIONS_CHANNEL` with a single connection and passes
+/// each notified network name on to the streams.
+pub async fn run_registration_listener(state: Arc<AppState>) {
+    loop {
+        if let Err(e) = relay_notifications(&state).await {
+            tracing::error!("Registration listener failed: {:?}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(LISTEN_RETRY_SECS)).await;
+    }
+}
+
+async fn relay_notifications(state: &AppState) -> Result<(), CocoError> {
+    let mut listener = PgListener::connect_with(&state.connection).await?;
+    listener.listen(REGISTRATIONS_CHANNEL).await?;
+
+    loop {
+        // no subscribers is fine, so send errors are ignored
+        match listener.try_recv().await? {
+            Some(notification) => {
+                let _ = state.registrations.send(notification.payload().to_string());
+            }
+            // reconnected and may have missed some, so every stream re-reads
+            None => {
+                for network in state.networks.keys() {
+                    let _ = state.registrations.send(network.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Sends every registration after `position` down `tx` as it's indexed,
+/// until the receiver goes away.
+pub async fn stream_registrations(
+    state: Arc<AppState>,
+    network: String,
+    position: Option<EventPosition>,
+    tx: mpsc::Sender<RegistrationResponse>,
+) {
+    if let Err(e) = forward_registrations(&state, &network, position, &tx).await {
+        tracing::error!("Registration stream for {} failed: {:?}", network, e);
+    }
+}
+
+async fn forward_registrations(
+    state: &AppState,
+    network_name: &str,
+    mut position: Option<EventPosition>,
+    tx: &mpsc::Sender<RegistrationResponse>,
+) -> Result<(), CocoError> {
+    let network = state.network(Some(network_name))?;
+    let mut notices = state.registrations.subscribe();
+
+    loop {
+        // anything indexed between the caller's position and subscribing goes out first
+        loop {
+            let rows: Vec<RegistrationRow> = sqlx::query_as(&format!(
+                r#"
+                SELECT {REGISTRATION_COLUMNS}
+                FROM name_registrations
+                WHERE network = $1
+                    AND ($2::bigint IS NULL OR (block_number, log_index) > ($2, $3))
+                ORDER BY block_number, log_index
+                LIMIT $4
+                "#
+            ))
+            .bind(&network.name)
+            .bind(position.map(|p| p.block_number as i64))
+            .bind(position.map(|p| p.log_index as i64))
+            .bind(STREAM_PAGE)
+            .fetch_all(&state.connection)
+            .await?;
+
+            let caught_up = (rows.len() as i64) < STREAM_PAGE;
+            for row in rows {
+                let registration = registration_response(row, network);
+                position = Some(EventPosition {
+                    block_number: registration.block_number,
+                    log_index: registration.log_index,
+                });
+                if tx.send(registration).await.is_err() {
+                    return Ok(());
+                }
+            }
+
+            if caught_up {
+                break;
+            }
+        }
+
+        // other networks share the channel
+        loop {
+            tokio::select! {
+                notice = notices.recv() => match notice {
+                    Ok(network) if network == network_name => break,
+                    Ok(_) => {}
+                    // dropped notices might have been ours
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                _ = tx.closed() => return Ok(()),
+            }
+        }
+    }
+}
+
+fn registration_response(row: RegistrationRow, network: &NetworkState) -> RegistrationResponse {
+    RegistrationResponse {
+        name: row.label.map(|label| format!("{}.{}", label, network.tld)),
+        label_hash: B256::from_slice(&row.label_hash),
+        owner: Address::from_slice(&row.owner),
+        base_cost: row.base_cost,
+        premium: row.premium,
+        expiry_date: unix_to_iso(row.expires).unwrap_or_default(),
+        registered_at: row.registered_at.and_then(unix_to_iso),
+        duration_secs: row.registered_at.map(|at| row.expires - at),
+        block_number: row.block_number as u64,
+        tx_hash: B256::from_slice(&row.tx_hash),
+        log_index: row.log_index as u64,
+    }
+}
+
+fn unix_to_iso(secs: i64) -> Option<String> {
+    Some(DateTime::<Utc>::from_timestamp(secs, 0)?.to_rfc3339())
+}
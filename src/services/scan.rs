@@ -0,0 +1,137 @@
+//! scan.rs
+//!
+//! Pattern scans over whole namespaces. A scan's pattern is parsed and
+//! counted when it's queued, so a bad or oversized pattern fails the request
+//! rather than the job. The batch worker then expands it, which always gives
+//! the same order, and checks the labels under the network's TLD.
+use crate::config::Config;
+use crate::errors::CocoError;
+use crate::services::batch::{BatchKind, BatchSource, Claim, stream_results};
+use crate::services::pattern::Pattern;
+use crate::types::api::{AppState, NameResult, ScanBody, ScanResponse, ScanStatus};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Scans for the batch worker.
+pub struct Scans;
+
+impl BatchSource for Scans {
+    const KIND: BatchKind = BatchKind::Scan;
+
+    fn chunk_size(config: &Config) -> usize {
+        config.scan.chunk_size
+    }
+
+    fn poll_interval_secs(config: &Config) -> u64 {
+        config.scan.poll_interval_secs
+    }
+
+    async fn names(state: &AppState, claim: &Claim) -> Result<Vec<String>, CocoError> {
+        let network = state.network(Some(&claim.network))?;
+        let (pattern, palindrome): (String, bool) =
+            sqlx::query_as("SELECT pattern, palindrome FROM scans WHERE id = $1")
+                .bind(claim.id)
+                .fetch_one(&state.connection)
+                .await?;
+
+        // already within the limit when it was queued, the limit may have changed since
+        let labels = Pattern::parse(&pattern)?.expand(palindrome, usize::MAX)?;
+        Ok(labels
+            .into_iter()
+            .map(|label| format!("{}.{}", label, network.tld))
+            .collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ScanRow {
+    id: Uuid,
+    network: String,
+    pattern: String,
+    palindrome: bool,
+    status: ScanStatus,
+    total: i64,
+    checked: i64,
+    available: i64,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+const SCAN_COLUMNS: &str = r#"
+    id, network, pattern, palindrome, status, total, checked, available, error,
+    created_at, completed_at
+"#;
+
+pub async fn create_scan(state: &AppState, body: &ScanBody) -> Result<ScanResponse, CocoError> {
+    let network = state.network(body.network.as_deref())?;
+    let limit = state.app_config.scan.max_labels;
+    // duplicates aren't known until expansion, the scanner corrects the total then
+    let total = Pattern::parse(&body.pattern)?.count(body.palindrome);
+    if total > limit as u64 {
+        return Err(CocoError::TooManyNames {
+            requested: usize::try_from(total).unwrap_or(usize::MAX),
+            limit,
+        });
+    }
+
+    let row: ScanRow = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO scans (id, network, pattern, palindrome, status, total)
+        VALUES ($1, $2, $3, $4, 'pending', $5)
+        RETURNING {SCAN_COLUMNS}
+        "#
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&network.name)
+    .bind(body.pattern.trim())
+    .bind(body.palindrome)
+    .bind(total as i64)
+    .fetch_one(&state.connection)
+    .await?;
+
+    Ok(scan_response(row))
+}
+
+pub async fn get_scan(db: &PgPool, id: Uuid) -> Result<ScanResponse, CocoError> {
+    let row: Option<ScanRow> =
+        sqlx::query_as(&format!("SELECT {SCAN_COLUMNS} FROM scans WHERE id = $1"))
+            .bind(id)
+            .fetch_optional(db)
+            .await?;
+
+    row.map(scan_response).ok_or(CocoError::ScanNotFound(id))
+}
+
+/// Results of a completed scan in pattern order, optionally only the
+/// available or only the taken names.
+pub async fn scan_results(
+    db: &PgPool,
+    id: Uuid,
+    available: Option<bool>,
+) -> Result<mpsc::Receiver<Result<NameResult, CocoError>>, CocoError> {
+    let scan = get_scan(db, id).await?;
+    match scan.status {
+        ScanStatus::Completed => Ok(stream_results(db.clone(), BatchKind::Scan, id, available)),
+        ScanStatus::Failed => Err(CocoError::ScanFailed(id, scan.error.unwrap_or_default())),
+        ScanStatus::Pending | ScanStatus::Running => Err(CocoError::ScanNotFinished(id)),
+    }
+}
+
+fn scan_response(row: ScanRow) -> ScanResponse {
+    ScanResponse {
+        id: row.id,
+        network: row.network,
+        pattern: row.pattern,
+        palindrome: row.palindrome,
+        status: row.status,
+        total: row.total as u64,
+        checked: row.checked as u64,
+        available: row.available as u64,
+        error: row.error,
+        created_at: row.created_at.to_rfc3339(),
+        completed_at: row.completed_at.map(|at| at.to_rfc3339()),
+    }
+}
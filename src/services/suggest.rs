@@ -0,0 +1,183 @@
+//! suggest.rs
+//!
+//! Alternatives for a taken name. Candidates come from a few fixed patterns
+//! (plurals, prefixes, suffixes, numbers, emoji) and from wordlist labels
+//! starting with the name, then go through the same availability check as
+//! /api/check. Only names that can be registered right now are returned.
+use crate::errors::CocoError;
+use crate::services::ens::{check_name_availability, normalise_and_hash_name_for};
+use crate::types::api::{AtBlock, BlockSelector, LabelSource, NetworkState, PriceResponse};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+// wordlist labels considered per request
+const WORDLIST_CANDIDATES: i64 = 50;
+// the controller won't register anything shorter
+const MIN_LABEL_CHARS: usize = 3;
+
+const PREFIXES: [&str; 8] = ["the", "my", "get", "hey", "its", "mr", "real", "0x"];
+const SUFFIXES: [&str; 10] = [
+    "dao", "labs", "hq", "app", "xyz", "club", "wallet", "nft", "dev", "vault",
+];
+const NUMERALS: [&str; 10] = ["1", "2", "3", "7", "69", "99", "100", "420", "777", "1000"];
+const EMOJI: [&str; 6] = ["🔥", "💎", "🚀", "👑", "🦊", "🌈"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Plural,
+    Prefix,
+    Suffix,
+    Numeral,
+    Emoji,
+    Wordlist,
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub name: String,
+    pub kind: SuggestionKind,
+    pub price: PriceResponse,
+}
+
+/// Available alternatives to `name`, in candidate order.
+pub async fn suggest_names(
+    db: &PgPool,
+    network: &NetworkState,
+    name: &str,
+    limit: Option<u32>,
+) -> Result<AtBlock<Suggestion>, CocoError> {
+    let label = normalise_and_hash_name_for(name, &network.tld)?.label;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+
+    let words = wordlist_matches(db, &label).await?;
+    let mut candidates = suggestion_candidates(&label, &words);
+    // stay inside the per-request name cap rather than failing with a 413
+    candidates.truncate(network.multicall.config.max_names);
+
+    let names: Vec<String> = candidates
+        .iter()
+        .map(|(label, _)| format!("{}.{}", label, network.tld))
+        .collect();
+    let checked = check_name_availability(network, &names, BlockSelector::Latest).await?;
+
+    let rows = checked
+        .rows
+        .into_iter()
+        .filter(|r| r.available)
+        .filter_map(|r| {
+            let label = r.name.strip_suffix(&format!(".{}", network.tld))?;
+            let kind = candidates
+                .iter()
+                .find(|(candidate, _)| candidate == label)
+                .map(|(_, kind)| *kind)?;
+            Some(Suggestion {
+                kind,
+                price: r.price?,
+                name: r.name,
+            })
+        })
+        .take(limit)
+        .collect();
+
+    Ok(AtBlock {
+        block: checked.block,
+        rows,
+    })
+}
+
+/// Candidate labels for `label`, without duplicates or the label itself.
+/// `words` are wordlist labels that start with it.
+pub fn suggestion_candidates(label: &str, words: &[String]) -> Vec<(String, SuggestionKind)> {
+    let mut out = Vec::new();
+
+    out.extend(plural(label).map(|p| (p, SuggestionKind::Plural)));
+    out.extend(
+        PREFIXES
+            .iter()
+            .map(|p| (format!("{}{}", p, label), SuggestionKind::Prefix)),
+    );
+    out.extend(
+        SUFFIXES
+            .iter()
+            .map(|s| (format!("{}{}", label, s), SuggestionKind::Suffix)),
+    );
+    out.extend(
+        NUMERALS
+            .iter()
+            .map(|n| (format!("{}{}", label, n), SuggestionKind::Numeral)),
+    );
+    out.extend(
+        EMOJI
+            .iter()
+            .map(|e| (format!("{}{}", label, e), SuggestionKind::Emoji)),
+    );
+    out.extend(
+        words
+            .iter()
+            .map(|w| (w.to_string(), SuggestionKind::Wordlist)),
+    );
+
+    let mut seen = HashSet::from([label.to_string()]);
+    out.retain(|(candidate, _)| {
+        candidate.chars().count() >= MIN_LABEL_CHARS && seen.insert(candidate.clone())
+    });
+
+    out
+}
+
+// plural of a singular label and singular of a plural one, plain English
+// rules so only for ASCII words
+fn plural(label: &str) -> Option<String> {
+    if !label.bytes().all(|b| b.is_ascii_lowercase()) {
+        return None;
+    }
+    let consonant_y =
+        label.ends_with('y') && !label[..label.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+
+    Some(if let Some(stem) = label.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = label.strip_suffix('s') {
+        stem.to_string()
+    } else if consonant_y {
+        format!("{}ies", &label[..label.len() - 1])
+    } else if ["x", "z", "ch", "sh"]
+        .iter()
+        .any(|end| label.ends_with(end))
+    {
+        format!("{}es", label)
+    } else {
+        format!("{}s", label)
+    })
+}
+
+// imported wordlist labels that start with `label`, shortest first
+async fn wordlist_matches(db: &PgPool, label: &str) -> Result<Vec<String>, CocoError> {
+    let pattern = format!(
+        "{}%",
+        label
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    let words = sqlx::query_scalar(
+        r#"
+        SELECT label FROM labels
+        WHERE source = $1 AND label LIKE $2 AND label <> $3
+        ORDER BY char_length(label), label
+        LIMIT $4
+        "#,
+    )
+    .bind(LabelSource::Wordlist)
+    .bind(pattern)
+    .bind(label)
+    .bind(WORDLIST_CANDIDATES)
+    .fetch_all(db)
+    .await?;
+
+    Ok(words)
+}
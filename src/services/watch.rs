@@ -0,0 +1,375 @@
+//! watch.rs
+use crate::errors::CocoError;
+use crate::services::ens::ETHRegistrarController::ETHRegistrarControllerInstance;
+use crate::services::ens::{
+    AggregatorInterface, BaseRegistrarImplementation, ETHRegistrarController, PriceOracle,
+    latest_block, normalise_and_hash_name_for,
+};
+use crate::services::premium::secs_until_price_below;
+use crate::types::alloy_providers::AppProvider;
+use crate::types::api::{
+    AppState, NetworkState, PriceCurrency, WatchBody, WatchKind, WatchResponse,
+};
+use alloy::eips::BlockId;
+use alloy::primitives::{B256, U256};
+use alloy::providers::{Network, Provider};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const ONE_YEAR_SECS: u64 = 365 * 24 * 60 * 60;
+const WEI_PER_ETH: f64 = 1e18;
+// chainlink USD feeds answer with 8 decimals
+const USD_FEED_DECIMALS: f64 = 1e8;
+// how long to wait before re-checking a watch whose trigger can't be computed
+const RECHECK_SECS: i64 = 24 * 60 * 60;
+
+struct PriceInputs {
+    // number and timestamp of the block the inputs were read at
+    block: u64,
+    now: i64,
+    available: bool,
+    expires: i64,
+    // only read for price_below watches, not every deployment has a USD oracle
+    price: Option<WatchPrice>,
+}
+
+struct WatchPrice {
+    base_wei: U256,
+    premium_wei: U256,
+    eth_usd: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct DueWatch {
+    id: Uuid,
+    name: String,
+    network: String,
+    kind: WatchKind,
+    threshold: Option<f64>,
+    currency: Option<PriceCurrency>,
+}
+
+pub async fn create_watch(state: &AppState, body: &WatchBody) -> Result<WatchResponse, CocoError> {
+    let threshold = match body.kind {
+        WatchKind::Available => None,
+        WatchKind::PriceBelow => match (body.threshold, body.currency) {
+            (Some(amount), Some(currency)) if amount > 0.0 => Some((amount, currency)),
+            _ => {
+                return Err(CocoError::InvalidWatch(
+                    "price_below watches need a positive threshold and a currency".to_string(),
+                ));
+            }
+        },
+    };
+
+    let network = state.network(body.network.as_deref())?;
+    let normalised = normalise_and_hash_name_for(&body.name, &network.tld)?;
+    let inputs =
+        fetch_price_inputs(network, &normalised.label, normalised.label_hash, body.kind).await?;
+    let grace_period_secs = network.constants().grace_period_secs as i64;
+
+    let trigger_at = match compute_trigger_at(body.kind, threshold, &inputs, grace_period_secs) {
+        Some(secs) => secs,
+        None => {
+            return Err(CocoError::InvalidWatch(
+                "Threshold is below the base price so the watch would never fire".to_string(),
+            ));
+        }
+    };
+
+    let id = Uuid::new_v4();
+    let expires_at = (inputs.expires > 0)
+        .then(|| DateTime::<Utc>::from_timestamp(inputs.expires, 0))
+        .flatten();
+    let trigger_at = DateTime::<Utc>::from_timestamp(trigger_at, 0);
+
+    sqlx::query(
+        r#"
+        INSERT INTO watch_list (
+            id, name, network, user_id, channel_id, thread_id, status,
+            created_at, updated_at, expires_at, kind, threshold, currency, trigger_at, label_hash
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, 'active', now(), now(), $7, $8, $9, $10, $11, $12)
+        "#,
+    )
+    .bind(id)
+    .bind(&normalised.name)
+    .bind(&network.name)
+    .bind(&body.user_id)
+    .bind(&body.channel_id)
+    .bind(&body.thread_id)
+    .bind(expires_at)
+    .bind(body.kind)
+    .bind(threshold.map(|(amount, _)| amount))
+    .bind(threshold.map(|(_, currency)| currency))
+    .bind(trigger_at)
+    .bind(normalised.label_hash.as_slice())
+    .execute(&state.connection)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(db) if db.is_unique_violation() => CocoError::InvalidWatch(format!(
+            "Already watching {} for this user",
+            normalised.name
+        )),
+        _ => CocoError::Database(e),
+    })?;
+
+    Ok(WatchResponse {
+        id,
+        name: normalised.name,
+        kind: body.kind,
+        trigger_at: trigger_at.map(|t| t.to_rfc3339()),
+    })
+}
+
+/// Periodically evaluates active watches whose `trigger_at` has passed.
+pub async fn run_sweeper(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.app_config.watch_sweep_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep_due_watches(&state).await {
+            tracing::error!("Watch sweep failed: {:?}", e);
+        }
+    }
+}
+
+async fn sweep_due_watches(state: &AppState) -> Result<(), CocoError> {
+    let due: Vec<DueWatch> = sqlx::query_as(
+        r#"
+        SELECT id, name, network, kind, threshold, currency
+        FROM watch_list
+        WHERE status = 'active' AND trigger_at <= now()
+        ORDER BY trigger_at
+        LIMIT 100
+        "#,
+    )
+    .fetch_all(&state.connection)
+    .await?;
+
+    for watch in due {
+        // one bad watch shouldn't hold up the rest of the batch
+        if let Err(e) = sweep_watch(state, &watch).await {
+            tracing::error!("Failed to evaluate watch {}: {:?}", watch.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn sweep_watch(state: &AppState, watch: &DueWatch) -> Result<(), CocoError> {
+    let network = state.network(Some(&watch.network))?;
+    let normalised = normalise_and_hash_name_for(&watch.name, &network.tld)?;
+    let inputs = fetch_price_inputs(
+        network,
+        &normalised.label,
+        normalised.label_hash,
+        watch.kind,
+    )
+    .await?;
+    let grace_period_secs = network.constants().grace_period_secs as i64;
+    let threshold = watch.threshold.zip(watch.currency);
+
+    if is_fulfilled(watch.kind, threshold, &inputs) {
+        tracing::info!("Watch {} for {} fulfilled", watch.id, watch.name);
+        return fulfil_watch(&state.connection, watch, inputs.block).await;
+    }
+
+    // renewals and price feed moves shift the trigger, so recompute it
+    let next_check = inputs
+        .now
+        .saturating_add_unsigned(state.app_config.watch_sweep_interval_secs);
+    let trigger_at = compute_trigger_at(watch.kind, threshold, &inputs, grace_period_secs)
+        .map(|t| t.max(next_check))
+        .unwrap_or(inputs.now.saturating_add(RECHECK_SECS));
+
+    sqlx::query(
+        "UPDATE watch_list SET trigger_at = $2, updated_at = now(), last_checked_at = now() WHERE id = $1",
+    )
+    .bind(watch.id)
+    .bind(DateTime::<Utc>::from_timestamp(trigger_at, 0))
+    .execute(&state.connection)
+    .await?;
+
+    Ok(())
+}
+
+// closes the watch and queues its notification in one go, released straight
+// away since it comes from reading the chain rather than from an event
+async fn fulfil_watch(db: &PgPool, watch: &DueWatch, block: u64) -> Result<(), CocoError> {
+    let mut tx = db.begin().await?;
+    let closed = sqlx::query(
+        "UPDATE watch_list SET status = 'fulfilled', updated_at = now(), last_checked_at = now() WHERE id = $1 AND status = 'active'",
+    )
+    .bind(watch.id)
+    .execute(&mut *tx)
+    .await?;
+    if closed.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO watch_notifications (watch_id, network, kind, block_number, released_at)
+        VALUES ($1, $2, 'fulfilled', $3, now())
+        "#,
+    )
+    .bind(watch.id)
+    .bind(&watch.network)
+    .bind(block as i64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+fn compute_trigger_at(
+    kind: WatchKind,
+    threshold: Option<(f64, PriceCurrency)>,
+    inputs: &PriceInputs,
+    grace_period_secs: i64,
+) -> Option<i64> {
+    // names that were never registered are released already
+    let released_at = if inputs.expires == 0 {
+        inputs.now
+    } else {
+        inputs.expires.saturating_add(grace_period_secs)
+    };
+
+    match (kind, threshold, &inputs.price) {
+        (WatchKind::PriceBelow, Some((amount, currency)), Some(price)) => {
+            let threshold_usd = to_usd(amount, currency, price.eth_usd);
+            let base_usd = wei_to_eth(price.base_wei) * price.eth_usd;
+
+            secs_until_price_below(threshold_usd, base_usd)
+                .map(|secs| released_at.saturating_add(secs))
+        }
+        _ => Some(released_at),
+    }
+}
+
+fn is_fulfilled(
+    kind: WatchKind,
+    threshold: Option<(f64, PriceCurrency)>,
+    inputs: &PriceInputs,
+) -> bool {
+    if !inputs.available {
+        return false;
+    }
+
+    match (kind, threshold, &inputs.price) {
+        (WatchKind::PriceBelow, Some((amount, currency)), Some(price)) => {
+            let total_eth = wei_to_eth(price.base_wei + price.premium_wei);
+            total_eth * price.eth_usd <= to_usd(amount, currency, price.eth_usd)
+        }
+        _ => true,
+    }
+}
+
+async fn fetch_price_inputs(
+    network: &NetworkState,
+    label: &str,
+    label_hash: B256,
+    kind: WatchKind,
+) -> Result<PriceInputs, CocoError> {
+    network
+        .pool
+        .execute(|provider| async move {
+            read_price_inputs(&provider, network, label, label_hash, kind).await
+        })
+        .await
+}
+
+async fn read_price_inputs(
+    provider: &AppProvider,
+    network: &NetworkState,
+    label: &str,
+    label_hash: B256,
+    kind: WatchKind,
+) -> Result<PriceInputs, CocoError> {
+    let base_registrar =
+        BaseRegistrarImplementation::new(network.ens_contract_addresses.base_registrar, provider);
+    let controller = ETHRegistrarController::new(
+        network.ens_contract_addresses.registrar_controller,
+        provider,
+    );
+
+    let block = latest_block(provider).await?;
+    let block_id = BlockId::number(block.number);
+
+    let id = U256::from_be_bytes(*label_hash);
+    let (available, expires) = provider
+        .multicall()
+        .block(block_id)
+        .add(controller.available(label.to_string()))
+        .add(base_registrar.nameExpires(id))
+        .aggregate()
+        .await?;
+
+    let price = match kind {
+        WatchKind::PriceBelow => Some(read_price(provider, &controller, label, block_id).await?),
+        WatchKind::Available => None,
+    };
+
+    Ok(PriceInputs {
+        block: block.number,
+        now: i64::try_from(block.timestamp).unwrap_or(i64::MAX),
+        available,
+        expires: expires.try_into().unwrap_or(i64::MAX),
+        price,
+    })
+}
+
+// a year's rent and the controller oracle's ETH/USD answer
+async fn read_price<P, N>(
+    provider: &AppProvider,
+    controller: &ETHRegistrarControllerInstance<P, N>,
+    label: &str,
+    block_id: BlockId,
+) -> Result<WatchPrice, CocoError>
+where
+    P: Provider<N>,
+    N: Network,
+{
+    let (price, oracle) = provider
+        .multicall()
+        .block(block_id)
+        .add(controller.rentPrice(label.to_string(), U256::from(ONE_YEAR_SECS)))
+        .add(controller.prices())
+        .aggregate()
+        .await?;
+
+    let usd_oracle = PriceOracle::new(oracle, provider)
+        .usdOracle()
+        .block(block_id)
+        .call()
+        .await?;
+    let answer = AggregatorInterface::new(usd_oracle, provider)
+        .latestAnswer()
+        .block(block_id)
+        .call()
+        .await?;
+
+    Ok(WatchPrice {
+        base_wei: price.base,
+        premium_wei: price.premium,
+        eth_usd: f64::from(answer.unsigned_abs()) / USD_FEED_DECIMALS,
+    })
+}
+
+fn to_usd(amount: f64, currency: PriceCurrency, eth_usd: f64) -> f64 {
+    match currency {
+        PriceCurrency::Usd => amount,
+        PriceCurrency::Eth => amount * eth_usd,
+    }
+}
+
+fn wei_to_eth(wei: U256) -> f64 {
+    f64::from(wei) / WEI_PER_ETH
+}
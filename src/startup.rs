@@ -1,15 +1,37 @@
-use crate::config::DBConfig;
+use crate::config::{Config, DBConfig, NetworkConfig};
+use crate::errors::CocoError;
 use crate::routes::portfolio::check_portfolio;
 use crate::routes::{
-    check::check_names, expiry::check_expiry, health_check::hello, register::register, watch::watch,
+    avatar::avatar,
+    check::check_names,
+    constants::{get_constants, refresh_constants},
+    diagnostics::rpc_diagnostics,
+    expiry::check_expiry,
+    feed::{expiring_feed, released_feed},
+    hash::hash,
+    health_check::hello,
+    history::history,
+    jobs::{job, job_download, job_status},
+    metadata::{metadata, metadata_image},
+    profile::profile,
+    register::register,
+    registrations::{registration_stream, registrations},
+    scan::{scan, scan_download, scan_status},
+    suggest::suggest,
+    verify::verify,
+    watch::watch,
 };
-use crate::types::api::AppState;
+use crate::services::cache::ResponseCache;
+use crate::services::ens::load_contract_constants;
+use crate::services::multicall::Multicall;
+use crate::services::provider_pool::ProviderPool;
+use crate::types::api::{AppState, NetworkState};
 use actix_web::dev::Server;
 use actix_web::{App, HttpServer, web};
-use alloy::providers::Provider;
 use sqlx::{PgPool, postgres::PgPoolOptions};
+use std::collections::HashMap;
 use std::net::TcpListener;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 pub fn run(listener: TcpListener, app_state: Arc<AppState>) -> Result<Server, std::io::Error> {
@@ -19,10 +41,31 @@ pub fn run(listener: TcpListener, app_state: Arc<AppState>) -> Result<Server, st
             .service(
                 web::scope("/api")
                     .route("/", web::get().to(hello))
+                    .route("/avatar/{name}", web::get().to(avatar))
                     .route("/check", web::get().to(check_names))
+                    .route("/constants", web::get().to(get_constants))
+                    .route("/constants/refresh", web::post().to(refresh_constants))
+                    .route("/diagnostics/rpc", web::get().to(rpc_diagnostics))
                     .route("/expiry", web::get().to(check_expiry))
+                    .route("/feed/expiring", web::get().to(expiring_feed))
+                    .route("/feed/released", web::get().to(released_feed))
+                    .route("/hash", web::get().to(hash))
+                    .route("/history/{name}", web::get().to(history))
+                    .route("/jobs", web::post().to(job))
+                    .route("/jobs/{id}", web::get().to(job_status))
+                    .route("/jobs/{id}/results", web::get().to(job_download))
+                    .route("/metadata/{token_id}", web::get().to(metadata))
+                    .route("/metadata/{token_id}/image", web::get().to(metadata_image))
                     .route("/portfolio", web::get().to(check_portfolio))
+                    .route("/profile/{name}", web::get().to(profile))
                     .route("/register", web::post().to(register))
+                    .route("/registrations", web::get().to(registrations))
+                    .route("/registrations/stream", web::get().to(registration_stream))
+                    .route("/scans", web::post().to(scan))
+                    .route("/scans/{id}", web::get().to(scan_status))
+                    .route("/scans/{id}/results", web::get().to(scan_download))
+                    .route("/suggest", web::get().to(suggest))
+                    .route("/verify", web::get().to(verify))
                     .route("/watch", web::post().to(watch)),
             )
             .app_data(connection.clone())
@@ -48,3 +91,69 @@ pub async fn create_pool(config: &DBConfig) -> Result<PgPool, sqlx::Error> {
     tracing::info!("Database connection pool created");
     Ok(pool)
 }
+
+/// Connects to every configured network and reads its contract constants.
+/// Networks that can't be reached are logged and left out, only a missing
+/// default network fails startup.
+#[tracing::instrument(name = "networks", skip_all)]
+pub async fn connect_networks(
+    config: &Config,
+    connection: &PgPool,
+) -> Result<HashMap<String, NetworkState>, CocoError> {
+    let cache_db = config.response_cache.postgres.then(|| connection.clone());
+    let mut networks = HashMap::with_capacity(config.networks.len());
+
+    for network in &config.networks {
+        // one unreachable network shouldn't keep the others down
+        match connect_network(config, network, cache_db.clone()).await {
+            Ok(state) => {
+                networks.insert(network.name.clone(), state);
+            }
+            Err(e) => tracing::error!("Skipping network {}: {:?}", network.name, e),
+        }
+    }
+
+    if !networks.contains_key(&config.default_network) {
+        return Err(CocoError::UnknownNetwork(config.default_network.clone()));
+    }
+
+    Ok(networks)
+}
+
+async fn connect_network(
+    config: &Config,
+    network: &NetworkConfig,
+    cache_db: Option<PgPool>,
+) -> Result<NetworkState, CocoError> {
+    let pool = ProviderPool::connect(&network.name, &network.rpc_urls, config.rpc_pool).await?;
+    let contracts = &network.contracts;
+    let contract_constants = pool
+        .execute(|provider| async move { load_contract_constants(&provider, contracts).await })
+        .await?;
+
+    tracing::info!(
+        "Connected to {}, contract constants: {:?}",
+        network.name,
+        contract_constants
+    );
+
+    Ok(NetworkState {
+        name: network.name.clone(),
+        tld: network.tld.clone(),
+        pool,
+        multicall: Multicall::new(config.multicall),
+        ens_contract_addresses: network.contracts.clone(),
+        contract_constants: RwLock::new(contract_constants),
+        registrar_layout: network.registrar_layout,
+        trusted_block_hash: network.trusted_block_hash,
+        block_time_secs: network.block_time_secs,
+        index_from_block: network.index_from_block,
+        check_cache: ResponseCache::new(
+            &network.name,
+            "check",
+            config.response_cache,
+            cache_db.clone(),
+        ),
+        expiry_cache: ResponseCache::new(&network.name, "expiry", config.response_cache, cache_db),
+    })
+}
@@ -1,16 +1,98 @@
 use crate::{
     config,
-    types::{alloy_providers::AppProvider, ens::EnsContractAddresses},
+    errors::CocoError,
+    services::{
+        cache::ResponseCache, labels::LabelQueue, multicall::Multicall, provider_pool::ProviderPool,
+    },
+    types::ens::{ContractConstants, EnsContractAddresses, RegistrarStorageLayout},
 };
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, B256, U256};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+use uuid::Uuid;
 // --------------- App State --------------
 pub struct AppState {
     pub app_config: config::Config,
-    pub provider: AppProvider,
     pub connection: PgPool,
+    pub networks: HashMap<String, NetworkState>,
+    // outbound HTTP to untrusted hosts, eg NFT metadata, see `metadata_client`
+    pub http: reqwest::Client,
+    // network names with newly indexed registrations, for the SSE streams
+    pub registrations: broadcast::Sender<String>,
+    // labels of queried names, saved by a single writer
+    pub labels: LabelQueue,
+}
+
+// one ENS deployment and the provider used to reach it
+pub struct NetworkState {
+    pub name: String,
+    pub tld: String,
+    pub pool: ProviderPool,
+    pub multicall: Multicall,
     pub ens_contract_addresses: EnsContractAddresses,
+    pub contract_constants: RwLock<ContractConstants>,
+    pub registrar_layout: Option<RegistrarStorageLayout>,
+    pub trusted_block_hash: Option<B256>,
+    pub block_time_secs: u64,
+    pub index_from_block: u64,
+    pub check_cache: ResponseCache<CheckNameResponse>,
+    pub expiry_cache: ResponseCache<CheckExpiryResponse>,
+}
+
+impl AppState {
+    /// Looks up a configured network, falling back to the default one.
+    pub fn network(&self, name: Option<&str>) -> Result<&NetworkState, CocoError> {
+        let name = name.unwrap_or(&self.app_config.default_network);
+
+        self.networks
+            .get(name)
+            .ok_or_else(|| CocoError::UnknownNetwork(name.to_string()))
+    }
+}
+
+impl NetworkState {
+    /// Snapshot of the cached contract constants.
+    pub fn constants(&self) -> ContractConstants {
+        *self
+            .contract_constants
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// ---- Block pinning -----
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BlockContext {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+}
+
+// which block a lookup reads, the latest unless the caller asks for history
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BlockSelector {
+    #[default]
+    Latest,
+    Number(u64),
+    // the last block at or before this unix timestamp
+    Time(u64),
+}
+
+// rows read from a single pinned block
+#[derive(Debug)]
+pub struct AtBlock<T> {
+    pub block: BlockContext,
+    pub rows: Vec<T>,
+}
+
+// what one provider answered during a quorum read
+#[derive(Debug, Serialize)]
+pub struct QuorumResult {
+    pub endpoint: String,
+    pub values: Vec<CheckNameResponse>,
 }
 
 // ---- Api error -----
@@ -18,19 +100,44 @@ pub struct AppState {
 pub struct ApiError {
     pub code: &'static str,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
 }
 // ----- Check -------
 #[derive(Deserialize)]
 pub struct CheckQuery {
     pub names: String,
+    pub network: Option<String>,
+    #[serde(default)]
+    pub consistency: Consistency,
+    pub at_block: Option<u64>,
+    // unix seconds, YYYY-MM-DD or RFC 3339
+    pub at_time: Option<String>,
+    // rows a chunk at a time as NDJSON, or SSE with Accept: text/event-stream
+    #[serde(default)]
+    pub stream: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Consistency {
+    #[default]
+    Single,
+    // cross-check the answer across several providers
+    Quorum,
+}
+
+#[derive(Deserialize)]
+pub struct NetworkQuery {
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PriceResponse {
     pub base: U256,
     pub premium: U256,
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CheckNameResponse {
     pub name: String,
     pub available: bool,
@@ -40,17 +147,48 @@ pub struct CheckNameResponse {
 }
 
 // -------------- Expirty ----------------
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckExpiryResponse {
     pub name: String,
     pub available: bool,
     pub expiry_date: Option<U256>, // date like
 }
 
+// -------------- Proofs ----------------
+#[derive(Deserialize)]
+pub struct VerifyQuery {
+    pub names: String,
+    pub network: Option<String>,
+    // overrides the network's configured trusted block
+    pub block_hash: Option<B256>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedBlock {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub state_root: B256,
+    pub values: Vec<VerifiedName>,
+}
+
+// values proven from storage next to what eth_call returned at the same block
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedName {
+    pub name: String,
+    pub expires: U256,
+    pub owner: Option<Address>,
+    pub rpc_expires: U256,
+    pub rpc_owner: Option<Address>,
+    pub matches_rpc: bool,
+}
+
 // ------ Portfolio -------
-#[derive(Clone, Copy, Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct AddressQuery {
     pub address: String,
+    pub network: Option<String>,
 }
 // -------------- Register -----------------
 #[derive(Deserialize)]
@@ -66,4 +204,374 @@ pub struct WatchBody {
     pub user_id: String,
     pub channel_id: String,
     pub thread_id: String,
+    pub network: Option<String>,
+    #[serde(default)]
+    pub kind: WatchKind,
+    // only used by price_below watches
+    pub threshold: Option<f64>,
+    pub currency: Option<PriceCurrency>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "watch_kind", rename_all = "snake_case")]
+pub enum WatchKind {
+    #[default]
+    Available,
+    PriceBelow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "price_currency", rename_all = "snake_case")]
+pub enum PriceCurrency {
+    Usd,
+    Eth,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: WatchKind,
+    pub trigger_at: Option<String>,
+}
+
+// -------------- Feeds -----------------
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub network: Option<String>,
+    pub window_days: Option<u32>,
+    pub min_length: Option<u32>,
+    pub max_length: Option<u32>,
+    pub char_class: Option<CharClass>,
+    // highest cost the name was ever registered for, in ETH
+    pub min_cost: Option<f64>,
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharClass {
+    Digits,
+    Letters,
+    Alphanumeric,
+    // anything outside ascii, eg emoji
+    Unicode,
+}
+
+impl CharClass {
+    /// Postgres regex a label has to match.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            CharClass::Digits => "^[0-9]+$",
+            CharClass::Letters => "^[a-z]+$",
+            CharClass::Alphanumeric => "^[a-z0-9]+$",
+            CharClass::Unicode => "[^\\x01-\\x7f]",
+        }
+    }
+}
+
+// -------------- Registrations -----------------
+#[derive(Deserialize)]
+pub struct RegistrationsQuery {
+    pub network: Option<String>,
+    // cursor from a previous page, as `<block>-<log index>`
+    pub before: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationResponse {
+    // None until the indexer has seen the controller event with the label
+    pub name: Option<String>,
+    pub label_hash: B256,
+    pub owner: Address,
+    // wei
+    pub base_cost: Option<String>,
+    pub premium: Option<String>,
+    pub expiry_date: String,
+    pub registered_at: Option<String>,
+    pub duration_secs: Option<i64>,
+    pub block_number: u64,
+    pub tx_hash: B256,
+    pub log_index: u64,
+}
+
+// -------------- Profile -----------------
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileResponse {
+    pub name: String,
+    pub node: B256,
+    pub block_number: u64,
+    // None when the name has no resolver set, everything below is then empty
+    pub resolver: Option<Address>,
+    pub address: Option<Address>,
+    pub addresses: Vec<CoinAddress>,
+    pub texts: BTreeMap<String, String>,
+    // raw bytes, and the URI form when the protocol is recognised
+    pub contenthash: Option<String>,
+    pub content_uri: Option<String>,
+    // reverse record of `address`, and whether it points back at this name
+    pub primary_name: Option<String>,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAddress {
+    pub coin: &'static str,
+    pub coin_type: u64,
+    pub address: String,
+}
+
+// -------------- Avatar -----------------
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvatarResponse {
+    pub name: String,
+    // the avatar text record as set on the resolver
+    pub record: Option<String>,
+    // http(s) or data URL, ready to display
+    pub image: Option<String>,
+    pub nft: Option<NftAvatar>,
+    // whether the name's address owns the NFT, None when it isn't an NFT
+    pub verified: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftAvatar {
+    pub chain_id: u64,
+    pub standard: NftStandard,
+    pub contract: Address,
+    // decimal
+    pub token_id: String,
+    pub token_uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NftStandard {
+    Erc721,
+    Erc1155,
+}
+
+// -------------- Token metadata -----------------
+// OpenSea style metadata for a name's ERC-721 / ERC-1155 token
+#[derive(Debug, Serialize)]
+pub struct NftMetadataResponse {
+    pub name: String,
+    pub description: String,
+    // the SVG card as a data URI
+    pub image: String,
+    pub attributes: Vec<MetadataAttribute>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetadataAttribute {
+    pub trait_type: &'static str,
+    // "date" values are unix seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_type: Option<&'static str>,
+    pub value: serde_json::Value,
+}
+
+// -------------- Hash -----------------
+#[derive(Deserialize)]
+pub struct HashQuery {
+    pub names: String,
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashResponse {
+    pub name: String,
+    // None for [labelhash] input the labels table has no label for
+    pub label: Option<String>,
+    pub label_hash: B256,
+    pub namehash: B256,
+    // registrar ERC-721 id, the label hash as a number
+    pub token_id: String,
+    pub token_id_hex: B256,
+    // NameWrapper ERC-1155 id, the namehash as a number
+    pub wrapped_token_id: String,
+    pub dns_encoded: String,
+}
+
+// -------------- Labels -----------------
+// where a label in the labels table came from
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "label_source", rename_all = "snake_case")]
+pub enum LabelSource {
+    Event,
+    Wordlist,
+    Query,
+}
+
+// -------------- History -----------------
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub network: Option<String>,
+    // cursor from a previous page, as `<block>-<log index>`
+    pub before: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryResponse {
+    // `[<label hash>].eth` when the label isn't known
+    pub name: String,
+    pub label_hash: B256,
+    pub next_cursor: Option<String>,
+    pub events: Vec<HistoryEvent>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEvent {
+    pub kind: HistoryEventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_date: Option<String>,
+    // wei, base + premium for registrations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<String>,
+    pub block_number: u64,
+    pub tx_hash: B256,
+    pub log_index: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryEventKind {
+    Registered,
+    Renewed,
+    Transferred,
+}
+
+// -------------- Suggest -----------------
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    pub name: String,
+    pub network: Option<String>,
+    pub limit: Option<u32>,
+}
+
+// -------------- Scans -----------------
+#[derive(Deserialize)]
+pub struct ScanBody {
+    pub pattern: String,
+    #[serde(default)]
+    pub palindrome: bool,
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "scan_status", rename_all = "snake_case")]
+pub enum ScanStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResponse {
+    pub id: Uuid,
+    pub network: String,
+    pub pattern: String,
+    pub palindrome: bool,
+    pub status: ScanStatus,
+    // labels the pattern expands to
+    pub total: u64,
+    pub checked: u64,
+    pub available: u64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ScanResultsQuery {
+    // only available (or only taken) names when set
+    pub available: Option<bool>,
+}
+
+// one checked name from a scan or job
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameResult {
+    pub name: String,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_date: Option<String>,
+    // wei, one year's rent when the name is available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_cost: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub premium: Option<String>,
+}
+
+// -------------- Jobs -----------------
+// JSON body for POST /jobs, CSV uploads are read into the same shape
+#[derive(Deserialize)]
+pub struct JobBody {
+    pub names: Vec<String>,
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobResponse {
+    pub id: Uuid,
+    pub network: String,
+    pub status: JobStatus,
+    pub total: u64,
+    pub checked: u64,
+    pub available: u64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct JobResultsQuery {
+    #[serde(default)]
+    pub format: ResultFormat,
+    pub available: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    #[default]
+    Json,
+    Ndjson,
+    Csv,
 }
@@ -1,10 +1,15 @@
 use alloy::primitives::{Address, address};
+use alloy::sol;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct EnsContractAddresses {
     pub ens_registry: Address,
     pub base_registrar: Address,
     pub registrar_controller: Address,
+    // not every deployment has a NameWrapper, eg Basenames
+    #[serde(default)]
+    pub name_wrapper: Option<Address>,
 }
 
 impl EnsContractAddresses {
@@ -13,6 +18,62 @@ impl EnsContractAddresses {
             ens_registry: address!("0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e"),
             base_registrar: address!("0x57f1887a8BF19b14fC0dF6Fd9B2acc9Af147eA85"),
             registrar_controller: address!("0x253553366Da8546fC250F225fe3d25d0C782303b"),
+            name_wrapper: Some(address!("0xD4416b13d2b3a9aBae7AcD5D6C2BbDBE25686401")),
         }
     }
 }
+
+// Storage slots of the base registrar's `_tokenOwner` and `expiries` mappings,
+// used to prove reads with eth_getProof. They depend on the compiled contract
+// (inheritance order, OpenZeppelin version) so they are set per network.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RegistrarStorageLayout {
+    pub token_owner_slot: u64,
+    pub expiries_slot: u64,
+}
+
+impl RegistrarStorageLayout {
+    pub fn mainnet() -> Self {
+        Self {
+            token_owner_slot: 5,
+            expiries_slot: 9,
+        }
+    }
+}
+
+// Timing constants that differ between deployments, read from the contracts.
+// Basenames has no commit/reveal step so the commitment ages are optional.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractConstants {
+    pub grace_period_secs: u64,
+    pub min_registration_duration_secs: u64,
+    pub min_commitment_age_secs: Option<u64>,
+    pub max_commitment_age_secs: Option<u64>,
+}
+
+// custom errors the controller and registrar revert with, decoded into
+// error messages
+sol! {
+    interface ControllerReverts {
+        error CommitmentTooNew(bytes32 commitment);
+        error CommitmentTooOld(bytes32 commitment);
+        error NameNotAvailable(string name);
+        error DurationTooShort(uint256 duration);
+        error ResolverRequiredWhenDataSupplied();
+        error UnexpiredCommitmentExists(bytes32 commitment);
+        error InsufficientValue();
+        error Unauthorised(bytes32 node);
+        error MaxCommitmentAgeTooLow();
+        error MaxCommitmentAgeTooHigh();
+    }
+
+    // Basenames, the ENS registrar reverts with bare requires
+    interface RegistrarReverts {
+        error Expired(uint256 tokenId);
+        error NonexistentToken(uint256 tokenId);
+        error NotAvailable(uint256 tokenId);
+        error OnlyController();
+        error RegistrarNotLive();
+    }
+}
@@ -0,0 +1,19 @@
+//! tests/admin.rs
+use actix_web::test::TestRequest;
+use coco::routes::constants::require_admin;
+
+#[test]
+fn admin_routes_need_the_configured_bearer_token() {
+    let with = |auth: &str| {
+        TestRequest::default()
+            .insert_header(("Authorization", auth))
+            .to_http_request()
+    };
+
+    assert!(require_admin(&with("Bearer s3cret"), Some("s3cret")).is_ok());
+    assert!(require_admin(&with("Bearer wrong"), Some("s3cret")).is_err());
+    assert!(require_admin(&with("s3cret"), Some("s3cret")).is_err());
+    // unset token disables the route
+    assert!(require_admin(&with("Bearer "), None).is_err());
+    assert!(require_admin(&TestRequest::default().to_http_request(), Some("s3cret")).is_err());
+}
@@ -0,0 +1,144 @@
+//! tests/avatar.rs
+use alloy::primitives::{U256, address};
+use coco::config::AvatarConfig;
+use coco::services::avatar::{
+    AvatarUri, NftRef, decode_data_uri, erc1155_uri, gateway_url, is_public_ip, metadata_client,
+    metadata_image, parse_avatar_uri,
+};
+use coco::types::api::NftStandard;
+use serde_json::json;
+
+fn config() -> AvatarConfig {
+    AvatarConfig {
+        ipfs_gateway: "https://gateway.example/".to_string(),
+        arweave_gateway: "https://arweave.net".to_string(),
+        metadata_timeout_secs: 5,
+    }
+}
+
+#[test]
+fn nft_references_are_parsed() {
+    let parsed =
+        parse_avatar_uri("eip155:1/erc721:0xb47e3cd837dDF8e4c57F05d70Ab865de6e193BBB/1234")
+            .unwrap();
+    assert_eq!(
+        parsed,
+        AvatarUri::Nft(NftRef {
+            chain_id: 1,
+            standard: NftStandard::Erc721,
+            contract: address!("b47e3cd837dDF8e4c57F05d70Ab865de6e193BBB"),
+            token_id: U256::from(1234),
+        })
+    );
+
+    let erc1155 =
+        parse_avatar_uri("EIP155:1/ERC1155:0x495f947276749ce646f68ac8c248420045cb7b5e/0x10")
+            .unwrap();
+    assert!(matches!(
+        erc1155,
+        AvatarUri::Nft(NftRef { standard: NftStandard::Erc1155, token_id, .. }) if token_id == U256::from(16)
+    ));
+
+    assert!(
+        parse_avatar_uri("eip155:1/erc20:0xb47e3cd837dDF8e4c57F05d70Ab865de6e193BBB/1").is_err()
+    );
+    assert!(parse_avatar_uri("ftp://example.com/me.png").is_err());
+    assert!(parse_avatar_uri("é").is_err());
+}
+
+#[test]
+fn uris_are_rewritten_through_the_gateways() {
+    let config = config();
+
+    assert_eq!(
+        gateway_url(
+            "ipfs://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4/1.png",
+            &config
+        )
+        .as_deref(),
+        Some("https://gateway.example/ipfs/QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4/1.png")
+    );
+    // the doubled form some collections use
+    assert_eq!(
+        gateway_url("ipfs://ipfs/bafyabc", &config).as_deref(),
+        Some("https://gateway.example/ipfs/bafyabc")
+    );
+    assert_eq!(
+        gateway_url("ar://ys32Pt8uC7TrVxHdOLByOspfPEq2LO63wREHQIM9SJQ", &config).as_deref(),
+        Some("https://arweave.net/ys32Pt8uC7TrVxHdOLByOspfPEq2LO63wREHQIM9SJQ")
+    );
+    assert_eq!(
+        gateway_url("https://example.com/me.png", &config).as_deref(),
+        Some("https://example.com/me.png")
+    );
+    assert!(gateway_url("data:image/png;base64,iVBO", &config).is_some());
+    assert_eq!(gateway_url("ftp://example.com/me.png", &config), None);
+}
+
+#[test]
+fn metadata_images_and_data_uris() {
+    assert_eq!(
+        metadata_image(&json!({"name": "punk", "image": "ipfs://Qm1"})).as_deref(),
+        Some("ipfs://Qm1")
+    );
+    assert_eq!(
+        metadata_image(&json!({"image": "", "image_url": "https://x/y.png"})).as_deref(),
+        Some("https://x/y.png")
+    );
+    assert_eq!(
+        metadata_image(&json!({"image_data": "<svg/>"})).as_deref(),
+        Some("data:image/svg+xml;base64,PHN2Zy8+")
+    );
+    assert_eq!(metadata_image(&json!({"name": "no image"})), None);
+
+    assert_eq!(
+        erc1155_uri("https://api.example/{id}.json", U256::from(255)),
+        format!("https://api.example/{}ff.json", "0".repeat(62))
+    );
+
+    // on-chain metadata is often an inline data URI
+    assert_eq!(
+        decode_data_uri("data:application/json;base64,eyJhIjoxfQ==").unwrap(),
+        br#"{"a":1}"#
+    );
+    assert_eq!(
+        decode_data_uri("data:application/json,%7B%22a%22%3A1%7D").unwrap(),
+        br#"{"a":1}"#
+    );
+    assert_eq!(decode_data_uri("https://example.com"), None);
+}
+
+#[test]
+fn private_addresses_are_not_public() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fe80::1",
+        "fd00::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public_ip(ip.parse().unwrap()), "{} is private", ip);
+    }
+
+    for ip in ["1.1.1.1", "104.18.0.1", "2606:4700::1111"] {
+        assert!(is_public_ip(ip.parse().unwrap()), "{} is public", ip);
+    }
+}
+
+#[tokio::test]
+async fn metadata_client_refuses_names_that_resolve_privately() {
+    let client = metadata_client(&config());
+    let err = client
+        .get("https://localhost:1/metadata.json")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(format!("{:?}", err).contains("no public address"));
+}
@@ -0,0 +1,62 @@
+//! tests/blocks.rs
+use alloy::primitives::B256;
+use coco::errors::CocoError;
+use coco::services::blocks::{block_at_time, block_selector, parse_time};
+use coco::types::api::{BlockContext, BlockSelector};
+
+// 12 second blocks from t=1000, with a missed slot after block 50
+fn block(number: u64) -> BlockContext {
+    let timestamp = 1_000 + number * 12 + if number > 50 { 12 } else { 0 };
+    BlockContext {
+        number,
+        hash: B256::with_last_byte(number as u8),
+        timestamp,
+    }
+}
+
+async fn search(timestamp: u64) -> Result<BlockContext, CocoError> {
+    block_at_time(
+        block(100),
+        block(0),
+        timestamp,
+        |n| async move { Ok(block(n)) },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn finds_last_block_at_or_before_time() {
+    assert_eq!(search(block(37).timestamp).await.unwrap().number, 37);
+    assert_eq!(search(block(37).timestamp + 11).await.unwrap().number, 37);
+    // inside the missed slot
+    assert_eq!(search(block(50).timestamp + 20).await.unwrap().number, 50);
+    assert_eq!(search(block(0).timestamp).await.unwrap().number, 0);
+    assert_eq!(
+        search(block(100).timestamp + 500).await.unwrap().number,
+        100
+    );
+}
+
+#[tokio::test]
+async fn rejects_times_before_genesis() {
+    assert!(matches!(search(999).await, Err(CocoError::InvalidBlock(_))));
+}
+
+#[test]
+fn parses_dates_times_and_unix_seconds() {
+    assert_eq!(parse_time("2024-01-01").unwrap(), 1_704_067_200);
+    assert_eq!(parse_time("2024-01-01T12:00:00Z").unwrap(), 1_704_110_400);
+    assert_eq!(parse_time("1704067200").unwrap(), 1_704_067_200);
+    assert!(parse_time("yesterday").is_err());
+    assert!(parse_time("2999-01-01").is_err());
+}
+
+#[test]
+fn at_block_and_at_time_are_exclusive() {
+    assert_eq!(block_selector(None, None).unwrap(), BlockSelector::Latest);
+    assert_eq!(
+        block_selector(Some(19_000_000), None).unwrap(),
+        BlockSelector::Number(19_000_000)
+    );
+    assert!(block_selector(Some(19_000_000), Some("2024-01-01")).is_err());
+}
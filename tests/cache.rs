@@ -0,0 +1,131 @@
+//! tests/cache.rs
+use alloy::primitives::{B256, U256};
+use coco::config::ResponseCacheConfig;
+use coco::errors::CocoError;
+use coco::services::cache::ResponseCache;
+use coco::services::ens::{NormalisedNameData, normalise_and_hash_name};
+use coco::types::api::{BlockContext, CheckExpiryResponse};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn cache(ttl_secs: u64) -> ResponseCache<CheckExpiryResponse> {
+    ResponseCache::new(
+        "test",
+        "expiry",
+        ResponseCacheConfig {
+            ttl_secs,
+            postgres: false,
+        },
+        None,
+    )
+}
+
+fn block(number: u64) -> BlockContext {
+    BlockContext {
+        number,
+        hash: B256::with_last_byte(number as u8),
+        timestamp: 1_700_000_000 + number * 12,
+    }
+}
+
+fn names(labels: &[&str]) -> Vec<NormalisedNameData> {
+    labels
+        .iter()
+        .map(|l| normalise_and_hash_name(l).expect("valid name"))
+        .collect()
+}
+
+// counts fetched names and answers with the block number as the expiry
+async fn fetch(
+    calls: &AtomicUsize,
+    at: BlockContext,
+    batch: Vec<NormalisedNameData>,
+) -> Result<Vec<CheckExpiryResponse>, CocoError> {
+    calls.fetch_add(batch.len(), Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    Ok(batch
+        .into_iter()
+        .map(|n| CheckExpiryResponse {
+            name: n.name,
+            available: false,
+            expiry_date: Some(U256::from(at.number)),
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn hits_until_a_new_block_arrives() {
+    let cache = cache(60);
+    let calls = AtomicUsize::new(0);
+    let batch = names(&["alice", "bob"]);
+
+    for _ in 0..3 {
+        let rows = cache
+            .get_or_fetch(block(1), &batch, |misses| fetch(&calls, block(1), misses))
+            .await
+            .unwrap();
+        assert_eq!(rows[1].name, "bob.eth");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let rows = cache
+        .get_or_fetch(block(2), &batch, |misses| fetch(&calls, block(2), misses))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].expiry_date, Some(U256::from(2)));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn concurrent_lookups_are_coalesced() {
+    let cache = cache(60);
+    let calls = AtomicUsize::new(0);
+    let first = names(&["alice", "bob"]);
+    let second = names(&["bob", "carol"]);
+
+    let (a, b) = tokio::join!(
+        cache.get_or_fetch(block(1), &first, |misses| fetch(&calls, block(1), misses)),
+        cache.get_or_fetch(block(1), &second, |misses| fetch(&calls, block(1), misses)),
+    );
+
+    assert_eq!(a.unwrap().len(), 2);
+    assert_eq!(b.unwrap()[0].name, "bob.eth");
+    // bob was only fetched once
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn entries_expire_after_the_ttl() {
+    let cache = cache(0);
+    let calls = AtomicUsize::new(0);
+    let batch = names(&["alice"]);
+
+    for _ in 0..2 {
+        cache
+            .get_or_fetch(block(1), &batch, |misses| fetch(&calls, block(1), misses))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn failed_lookups_are_not_cached() {
+    let cache = cache(60);
+    let calls = AtomicUsize::new(0);
+    let batch = names(&["alice"]);
+
+    let failed = cache
+        .get_or_fetch(block(1), &batch, |_| async { Err(CocoError::RpcTimeout) })
+        .await;
+    assert!(matches!(failed, Err(CocoError::RpcTimeout)));
+
+    let rows = cache
+        .get_or_fetch(block(1), &batch, |misses| fetch(&calls, block(1), misses))
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
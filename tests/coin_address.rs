@@ -0,0 +1,56 @@
+//! tests/coin_address.rs
+use alloy::hex;
+use coco::services::coin_address::{ETH_COIN_TYPE, evm_coin_type, format_address};
+
+fn format(coin_type: u64, bytes: &str) -> Option<String> {
+    format_address(coin_type, &hex::decode(bytes).unwrap())
+}
+
+#[test]
+fn bitcoin_scripts_format_as_addresses() {
+    // p2pkh, p2sh, segwit v0 and taproot (bech32m)
+    assert_eq!(
+        format(0, "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").as_deref(),
+        Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa")
+    );
+    assert_eq!(
+        format(0, "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87").as_deref(),
+        Some("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy")
+    );
+    assert_eq!(
+        format(0, "0014751e76e8199196d454941c45d1b3a323f1433bd6").as_deref(),
+        Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+    );
+    assert_eq!(
+        format(
+            0,
+            "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        )
+        .as_deref(),
+        Some("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0")
+    );
+
+    // dogecoin has no segwit
+    assert_eq!(
+        format(3, "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+        None
+    );
+    assert_eq!(format(0, "deadbeef"), None);
+}
+
+#[test]
+fn evm_and_solana_addresses() {
+    let address = "d8da6bf26964af9d7eed9e03e53415d37aa96045";
+    let expected = Some("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+    assert_eq!(format(ETH_COIN_TYPE, address).as_deref(), expected);
+    assert_eq!(format(evm_coin_type(8453), address).as_deref(), expected);
+    assert_eq!(format(evm_coin_type(10), "d8da6b"), None);
+
+    assert_eq!(
+        format(501, &"00".repeat(32)).as_deref(),
+        Some("11111111111111111111111111111111")
+    );
+    // unknown coin types are left out rather than shown as hex
+    assert_eq!(format(9999, address), None);
+}
@@ -0,0 +1,84 @@
+//! tests/contenthash.rs
+use alloy::hex;
+use coco::errors::CocoError;
+use coco::services::contenthash::{decode, encode};
+
+// decodes to `uri` and encodes back to the same bytes
+fn assert_round_trip(bytes: &str, uri: &str) {
+    let bytes = hex::decode(bytes).unwrap();
+    assert_eq!(decode(&bytes).unwrap(), uri);
+    assert_eq!(encode(uri).unwrap(), bytes);
+}
+
+fn error_message(result: Result<impl std::fmt::Debug, CocoError>) -> String {
+    match result {
+        Err(CocoError::InvalidContenthash(msg)) => msg,
+        other => panic!("expected an invalid contenthash, got {:?}", other),
+    }
+}
+
+#[test]
+fn known_contenthashes_round_trip() {
+    // dag-pb sha2-256 shows as a CIDv0, other codecs as base32 CIDv1
+    assert_round_trip(
+        "e3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f",
+        "ipfs://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4",
+    );
+    assert_round_trip(
+        "e301015512204f682b71153ffa91e608445d7ea1257e2076d0d95eab6336cd1aa94b49680f11",
+        "ipfs://bafkreicpnavxcfj77ki6mccelv7kcjl6eb3nbwk6vnrtnti2vffus2apce",
+    );
+    assert_round_trip(
+        "e50101720024080112204f682b71153ffa91e608445d7ea1257e2076d0d95eab6336cd1aa94b49680f11",
+        "ipns://k51qzi5uqu5di5u65qxit0ytqdhfzjltsek9sjrywj4hnqyqzew9qtdgv8p635",
+    );
+    assert_round_trip(
+        "e40101fa011b20d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162",
+        "bzz://d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162",
+    );
+    assert_round_trip(
+        "90b2ca05cacdf63edf2e0bb4eb5711dd38b0723aca5f3c4ab62ceeb7c1110740833d4894",
+        "ar://ys32Pt8uC7TrVxHdOLByOspfPEq2LO63wREHQIM9SJQ",
+    );
+    assert_round_trip(
+        "bc037a716b746c776934666563766f367269",
+        "onion://zqktlwi4fecvo6ri",
+    );
+}
+
+#[test]
+fn other_cid_spellings_encode_to_the_same_bytes() {
+    let expected = encode("ipfs://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4").unwrap();
+    let cid = hex::encode(&expected[2..]);
+
+    assert_eq!(encode(&format!("ipfs://f{}", cid)).unwrap(), expected);
+    assert_eq!(
+        encode("IPFS://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4/").unwrap(),
+        expected
+    );
+
+    // v3 onion addresses written with the plain onion scheme
+    let onion3 = "a".repeat(56);
+    assert_eq!(
+        encode(&format!("onion://{}.onion", onion3)).unwrap(),
+        encode(&format!("onion3://{}", onion3)).unwrap()
+    );
+}
+
+#[test]
+fn malformed_input_is_described() {
+    assert!(error_message(encode("QmRAQB6Y")).contains("not a URI"));
+    assert!(error_message(encode("http://example.com")).contains("unsupported scheme http"));
+    assert!(error_message(encode("ipfs://Qm0000")).contains("base58 decoding failed"));
+    assert!(error_message(encode("bzz://abcd")).contains("64 hex characters"));
+    assert!(error_message(encode("ar://c2hvcnQ")).contains("should be 32 bytes, found 5"));
+
+    assert!(error_message(decode(&[])).contains("empty"));
+    assert!(error_message(decode(&[0xe3])).contains("truncated"));
+    assert!(error_message(decode(&[0x99, 0x01])).contains("unsupported protocol codec 0x99"));
+    // the multihash promises 32 bytes but only carries 2
+    let short = hex::decode("e3010170122029f2").unwrap();
+    assert!(error_message(decode(&short)).contains("32 digest bytes, found 2"));
+    let arweave = hex::decode("90b2ca0568656c6c6f").unwrap();
+    assert!(error_message(decode(&arweave)).contains("should be 32 bytes, found 5"));
+}
@@ -0,0 +1,74 @@
+//! tests/errors.rs
+use alloy::primitives::Bytes;
+use alloy::providers::MulticallError;
+use alloy::sol_types::SolError;
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
+use coco::errors::CocoError;
+use coco::types::ens::ControllerReverts::NameNotAvailable;
+
+fn error_resp(json: &str) -> TransportError {
+    RpcError::ErrorResp(serde_json::from_str(json).expect("valid error payload"))
+}
+
+#[test]
+fn rate_limits_are_their_own_variant() {
+    let http = CocoError::from(TransportErrorKind::http_error(429, "slow down".to_string()));
+    let infura = CocoError::from(error_resp(
+        r#"{"code":-32005,"message":"project ID request rate exceeded"}"#,
+    ));
+
+    assert!(matches!(http, CocoError::RateLimited));
+    assert!(matches!(infura, CocoError::RateLimited));
+    assert!(http.is_transport_error());
+    assert_eq!(http.to_api_error().code, "rpc_rate_limited");
+}
+
+#[test]
+fn controller_reverts_are_decoded() {
+    let data = NameNotAvailable {
+        name: "alice".to_string(),
+    }
+    .abi_encode();
+    let err = CocoError::from(MulticallError::CallFailed(Bytes::from(data)));
+
+    match &err {
+        CocoError::Reverted(reason) => assert!(reason.contains("NameNotAvailable: alice")),
+        other => panic!("expected a revert, got {:?}", other),
+    }
+    // the node answered, so there is nothing to fail over for
+    assert!(!err.is_transport_error());
+}
+
+#[test]
+fn reverts_in_error_responses_are_decoded() {
+    // Error(string) "not owner"
+    let err = CocoError::from(error_resp(
+        r#"{"code":3,"message":"execution reverted: not owner","data":"0x08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000096e6f74206f776e65720000000000000000000000000000000000000000000000"}"#,
+    ));
+
+    match err {
+        CocoError::Reverted(reason) => assert!(reason.contains("not owner")),
+        other => panic!("expected a revert, got {:?}", other),
+    }
+}
+
+#[test]
+fn other_errors_keep_their_codes() {
+    let transport = CocoError::from(TransportErrorKind::custom_str("connection reset"));
+    assert!(matches!(transport, CocoError::Rpc(_)));
+    assert!(transport.is_transport_error());
+
+    let decode = CocoError::from(MulticallError::NoReturnData);
+    assert_eq!(decode.to_api_error().code, "ens_error");
+}
+
+#[test]
+fn pruned_state_asks_for_an_archive_node() {
+    let geth = CocoError::from(error_resp(
+        r#"{"code":-32000,"message":"missing trie node 1a2b3c (path ) state 0x1a2b3c is not available"}"#,
+    ));
+
+    assert!(matches!(geth, CocoError::ArchiveRequired));
+    assert_eq!(geth.status_code().as_u16(), 501);
+    assert_eq!(geth.to_api_error().code, "archive_node_required");
+}
@@ -0,0 +1,90 @@
+//! tests/expiry.rs
+use alloy::primitives::U256;
+use coco::routes::check::u256_to_days_left;
+use coco::routes::expiry::expiry_response;
+use coco::types::api::CheckExpiryResponse;
+
+const DAY: i64 = 86_400;
+const GRACE_PERIOD_SECS: i64 = 90 * DAY;
+// a fixed block timestamp so nothing depends on the clock
+const BLOCK_TIMESTAMP: i64 = 1_735_689_600;
+
+fn registered(expiry: i64) -> CheckExpiryResponse {
+    CheckExpiryResponse {
+        name: "alice.eth".to_string(),
+        available: false,
+        expiry_date: Some(U256::from(expiry)),
+    }
+}
+
+#[test]
+fn days_left_is_relative_to_block_timestamp() {
+    let expires = U256::from(BLOCK_TIMESTAMP + 10 * DAY + 5);
+
+    assert_eq!(u256_to_days_left(expires, BLOCK_TIMESTAMP as u64), Some(10));
+    assert_eq!(
+        u256_to_days_left(U256::from(BLOCK_TIMESTAMP - 1), BLOCK_TIMESTAMP as u64),
+        Some(0)
+    );
+}
+
+#[test]
+fn name_in_grace_period() {
+    let response = expiry_response(
+        registered(BLOCK_TIMESTAMP - DAY),
+        BLOCK_TIMESTAMP,
+        GRACE_PERIOD_SECS,
+    );
+
+    assert!(response.is_expired);
+    assert!(response.is_in_grace_period);
+    assert!(!response.is_in_premium_period);
+    assert_eq!(response.days_until_expiry, Some(0));
+}
+
+#[test]
+fn name_in_premium_period() {
+    let expiry = BLOCK_TIMESTAMP - GRACE_PERIOD_SECS - DAY;
+    let response = expiry_response(registered(expiry), BLOCK_TIMESTAMP, GRACE_PERIOD_SECS);
+
+    assert!(response.is_expired);
+    assert!(!response.is_in_grace_period);
+    assert!(response.is_in_premium_period);
+}
+
+#[test]
+fn available_name_in_premium_auction() {
+    // the controller reports names in the premium auction as available
+    let expiry = BLOCK_TIMESTAMP - GRACE_PERIOD_SECS - 3 * DAY;
+    let response = expiry_response(
+        CheckExpiryResponse {
+            available: true,
+            ..registered(expiry)
+        },
+        BLOCK_TIMESTAMP,
+        GRACE_PERIOD_SECS,
+    );
+
+    assert!(response.is_expired);
+    assert!(!response.is_in_grace_period);
+    assert!(response.is_in_premium_period);
+    assert!(response.premium_period_end.is_some());
+    assert_eq!(response.days_until_expiry, Some(0));
+}
+
+#[test]
+fn never_registered_name_has_no_expiry() {
+    let response = expiry_response(
+        CheckExpiryResponse {
+            name: "fresh.eth".to_string(),
+            available: true,
+            expiry_date: Some(U256::ZERO),
+        },
+        BLOCK_TIMESTAMP,
+        GRACE_PERIOD_SECS,
+    );
+
+    assert!(!response.is_expired);
+    assert!(!response.is_in_premium_period);
+    assert_eq!(response.expiry_date, None);
+}
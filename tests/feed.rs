@@ -0,0 +1,27 @@
+//! tests/feed.rs
+use alloy::primitives::keccak256;
+use coco::errors::CocoError;
+use coco::services::feed::FeedCursor;
+
+#[test]
+fn cursor_round_trips() {
+    let cursor = FeedCursor {
+        expires: 1_767_225_600,
+        label_hash: keccak256("alice"),
+    };
+
+    assert_eq!(FeedCursor::decode(&cursor.encode()).unwrap(), cursor);
+}
+
+#[test]
+fn rejects_malformed_cursors() {
+    for cursor in ["", "1767225600", "soon-0x00", "1767225600-0x1234"] {
+        assert!(
+            matches!(
+                FeedCursor::decode(cursor),
+                Err(CocoError::InvalidQueryInput)
+            ),
+            "{cursor} should be rejected"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! tests/hash.rs
+use alloy::primitives::{U256, b256};
+use alloy_ens::namehash;
+use coco::services::hash::{dns_encode, encoded_label_hash, hash_name};
+
+#[test]
+fn hashes_for_a_name() {
+    let hashed = hash_name("Vitalik", "eth").unwrap();
+
+    assert_eq!(hashed.name, "vitalik.eth");
+    assert_eq!(hashed.label.as_deref(), Some("vitalik"));
+    assert_eq!(
+        hashed.label_hash,
+        b256!("af2caa1c2ca1d027f1ac823b529d0a67cd144264b2789fa2ea4d63a67c7103cc")
+    );
+    assert_eq!(
+        hashed.namehash,
+        b256!("ee6c4522aab0003e8d14cd40a6af439055fd2577951148c14b6cea9a53475835")
+    );
+    assert_eq!(hashed.token_id_hex, hashed.label_hash);
+    assert_eq!(
+        hashed.token_id,
+        U256::from_be_bytes(*hashed.label_hash).to_string()
+    );
+    assert_eq!(
+        hashed.wrapped_token_id,
+        U256::from_be_bytes(*hashed.namehash).to_string()
+    );
+    assert_eq!(hashed.dns_encoded, "0x07766974616c696b0365746800");
+
+    // subdomain tlds hash under their own node
+    let base = hash_name("alice", "base.eth").unwrap();
+    assert_eq!(base.namehash, namehash("alice.base.eth"));
+}
+
+#[test]
+fn bracketed_label_hashes_hash_the_same() {
+    let encoded = hash_name(
+        "[af2caa1c2ca1d027f1ac823b529d0a67cd144264b2789fa2ea4d63a67c7103cc].eth",
+        "eth",
+    )
+    .unwrap();
+    let plain = hash_name("vitalik.eth", "eth").unwrap();
+
+    assert_eq!(encoded.label, None);
+    assert_eq!(encoded.label_hash, plain.label_hash);
+    assert_eq!(encoded.namehash, plain.namehash);
+    assert_eq!(encoded.token_id, plain.token_id);
+
+    assert_eq!(encoded_label_hash("[abc]"), None);
+    assert_eq!(encoded_label_hash("vitalik"), None);
+    assert!(hash_name("a.b.eth", "eth").is_err());
+}
+
+#[test]
+fn oversized_labels_are_dns_encoded_as_their_hash() {
+    let label = "a".repeat(300);
+    let encoded = dns_encode(&format!("{}.eth", label));
+
+    assert_eq!(encoded[0], 66);
+    assert_eq!(encoded[1], b'[');
+    assert_eq!(encoded.len(), 1 + 66 + 4 + 1);
+}
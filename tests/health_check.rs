@@ -1,10 +1,14 @@
 //! tests/health_check.rs
-use alloy::providers::{Provider, ProviderBuilder};
+use coco::services::avatar::metadata_client;
+use coco::services::labels::LabelQueue;
+use coco::services::registrations::registration_notices;
+use coco::startup::{connect_networks, create_pool, run};
 use coco::types::api::AppState;
 use serde::Serialize;
 use serde_json::json;
-use sqlx::{Connection, PgConnection};
+use sqlx::PgPool;
 use std::net::TcpListener;
+use std::sync::Arc;
 
 #[tokio::test]
 pub async fn home_works() {
@@ -13,7 +17,7 @@ pub async fn home_works() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(format!("{}/api/check/ens.eth", address))
+        .get(format!("{}/api/check?names=ens.eth", address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -39,7 +43,7 @@ async fn test_register_endpoint_returns_200() {
     }];
 
     let response = client
-        .post(&format!("{}/api/register", address))
+        .post(format!("{}/api/register", address))
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
@@ -72,33 +76,40 @@ async fn test_register_endpoint_returns_400() {
             .await
             .expect("Failed to execute request");
 
-        assert_eq!(400, response.status().as_u16(),)
+        assert_eq!(400, response.status().as_u16(), "{}", msg)
     }
 }
 
 #[tokio::test]
 async fn test_watch() {
-    let (mut connection, address) = spawn_app().await;
+    let (connection, address) = spawn_app().await;
 
     let client = reqwest::Client::new();
 
     let response = client
-        .post(&format!("{}/api/watch", address))
-        .header("Content-Type", "application/json")
-        .query("ens")
+        .post(format!("{}/api/watch", address))
+        .json(&json!({
+            "name": "ens.eth",
+            "user_id": "health-check",
+            "channel_id": "channel",
+            "thread_id": "thread",
+        }))
         .send()
         .await
         .expect("Failed to execute request");
 
     assert_eq!(200, response.status().as_u16());
 
-    let save = sqlx::query!("SELECT name, status::text FROM watch_list",)
-        .fetch_one(&mut connection)
-        .await
-        .expect("Failed to fetch saved name in watch list");
+    let (name, status): (String, String) =
+        sqlx::query_as("SELECT name, status::text FROM watch_list WHERE user_id = 'health-check'")
+            .fetch_one(&connection)
+            .await
+            .expect("Failed to fetch saved name in watch list");
+    assert_eq!(name, "ens.eth");
+    assert_eq!(status, "active");
 }
 
-pub async fn spawn_app() -> (PgConnection, String) {
+pub async fn spawn_app() -> (PgPool, String) {
     unsafe {
         std::env::set_var("ETH_RPC", "http://127.0.0.1:8545");
         std::env::set_var("BASE_RPC", "http://127.0.0.1:8545");
@@ -108,28 +119,30 @@ pub async fn spawn_app() -> (PgConnection, String) {
 
     let config = coco::config::Config::load_env().expect("Missing required env vars for Config");
 
-    let connection_string = config.database.connection_string();
-    let connection = PgConnection::connect(&connection_string)
+    let connection = create_pool(&config.database)
         .await
         .expect("Failed to connect to Postgres");
-
-    let provider = ProviderBuilder::new()
-        .connect(&config.eth_rpc)
+    let networks = connect_networks(&config, &connection)
         .await
-        .expect("Failed to connect provider");
+        .expect("Failed to connect to the default network");
 
-    let app_state = AppState {
+    let http = metadata_client(&config.avatar);
+    let app_state = Arc::new(AppState {
         app_config: config,
-        provider,
-    };
+        connection: connection.clone(),
+        networks,
+        http,
+        registrations: registration_notices(),
+        labels: LabelQueue::new().0,
+    });
 
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
 
     let port = listener.local_addr().unwrap().port();
 
-    let server = coco::startup::run(listener, app_state).expect("Failed to bind server");
+    let server = run(listener, app_state).expect("Failed to bind server");
 
-    let _ = tokio::spawn(server);
+    tokio::spawn(server);
 
     let address = format!("http://127.0.0.1:{}", port);
     (connection, address)
@@ -0,0 +1,198 @@
+//! tests/indexer.rs
+use alloy::primitives::{Address, B256, Bytes, U256, address, keccak256};
+use alloy::rpc::types::Log;
+use alloy::sol_types::SolEvent;
+use coco::services::ens::{BaseRegistrarImplementation, ETHRegistrarController, NameWrapper};
+use coco::services::indexer::{
+    IndexedLog, NameEvent, WrapperEvent, collect_block_hashes, decode_log,
+};
+use coco::types::ens::EnsContractAddresses;
+
+const OWNER: Address = address!("0x1111111111111111111111111111111111111111");
+
+fn rpc_log(address: Address, event: &impl SolEvent) -> Log {
+    Log {
+        inner: alloy::primitives::Log {
+            address,
+            data: event.encode_log_data(),
+        },
+        block_number: Some(20_000_000),
+        block_hash: Some(B256::repeat_byte(0xbb)),
+        block_timestamp: Some(1_720_000_000),
+        transaction_hash: Some(B256::repeat_byte(0xab)),
+        log_index: Some(7),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decodes_registrar_registration() {
+    let contracts = EnsContractAddresses::mainnet();
+    let label_hash = keccak256("alice");
+    let event = BaseRegistrarImplementation::NameRegistered {
+        id: U256::from_be_bytes(*label_hash),
+        owner: OWNER,
+        expires: U256::from(1_900_000_000u64),
+    };
+
+    let decoded = decode_log(&contracts, &rpc_log(contracts.base_registrar, &event));
+
+    assert_eq!(
+        decoded,
+        Some(IndexedLog {
+            block_number: 20_000_000,
+            block_hash: B256::repeat_byte(0xbb),
+            block_timestamp: Some(1_720_000_000),
+            tx_hash: B256::repeat_byte(0xab),
+            log_index: 7,
+            event: NameEvent::Registered {
+                label_hash,
+                owner: OWNER,
+                expires: 1_900_000_000,
+            },
+        })
+    );
+}
+
+#[test]
+fn decodes_controller_registration_with_the_registrant() {
+    let contracts = EnsContractAddresses::mainnet();
+    let event = ETHRegistrarController::NameRegistered {
+        name: "alice".to_string(),
+        label: keccak256("alice"),
+        owner: OWNER,
+        baseCost: U256::from(3_000_000_000_000_000u64),
+        premium: U256::ZERO,
+        expires: U256::from(1_900_000_000u64),
+    };
+
+    let decoded = decode_log(&contracts, &rpc_log(contracts.registrar_controller, &event))
+        .expect("controller registration should decode");
+
+    // the registrar's NameRegistered names the NameWrapper for wrapped names
+    assert_eq!(
+        decoded.event,
+        NameEvent::ControllerRegistered {
+            label: "alice".to_string(),
+            label_hash: keccak256("alice"),
+            owner: OWNER,
+            base_cost: U256::from(3_000_000_000_000_000u64),
+            premium: U256::ZERO,
+        }
+    );
+}
+
+#[test]
+fn decodes_controller_renewal_with_label_and_cost() {
+    let contracts = EnsContractAddresses::mainnet();
+    let event = ETHRegistrarController::NameRenewed {
+        name: "alice".to_string(),
+        label: keccak256("alice"),
+        cost: U256::from(3_000_000_000_000_000u64),
+        expires: U256::from(1_900_000_000u64),
+    };
+
+    let decoded = decode_log(&contracts, &rpc_log(contracts.registrar_controller, &event))
+        .expect("controller renewal should decode");
+
+    assert_eq!(
+        decoded.event,
+        NameEvent::ControllerRenewed {
+            label: "alice".to_string(),
+            label_hash: keccak256("alice"),
+            cost: U256::from(3_000_000_000_000_000u64),
+        }
+    );
+}
+
+#[test]
+fn decodes_wrapped_name_from_dns_encoding() {
+    let contracts = EnsContractAddresses::mainnet();
+    let node = B256::repeat_byte(0x42);
+    let event = NameWrapper::NameWrapped {
+        node,
+        name: Bytes::from_static(b"\x05alice\x03eth\x00"),
+        owner: OWNER,
+        fuses: 196_608,
+        expiry: 1_907_776_000,
+    };
+
+    let decoded = decode_log(
+        &contracts,
+        &rpc_log(contracts.name_wrapper.unwrap(), &event),
+    )
+    .expect("wrapper event should decode");
+
+    assert_eq!(
+        decoded.event,
+        NameEvent::Wrapper(WrapperEvent {
+            node,
+            kind: "wrapped",
+            name: Some("alice.eth".to_string()),
+            owner: Some(OWNER),
+            fuses: Some(196_608),
+            expires: Some(1_907_776_000),
+        })
+    );
+}
+
+#[test]
+fn ignores_logs_from_other_contracts() {
+    let contracts = EnsContractAddresses::mainnet();
+    let event = BaseRegistrarImplementation::NameRenewed {
+        id: U256::from(1),
+        expires: U256::from(1),
+    };
+
+    assert_eq!(decode_log(&contracts, &rpc_log(OWNER, &event)), None);
+}
+
+fn transfer_at(block_number: u64, block_hash: B256) -> IndexedLog {
+    IndexedLog {
+        block_number,
+        block_hash,
+        block_timestamp: None,
+        tx_hash: B256::ZERO,
+        log_index: 0,
+        event: NameEvent::Transfer {
+            label_hash: keccak256("alice"),
+            from: OWNER,
+            to: Address::ZERO,
+        },
+    }
+}
+
+#[test]
+fn collects_one_hash_per_block() {
+    let events = [
+        transfer_at(10, B256::repeat_byte(1)),
+        transfer_at(10, B256::repeat_byte(1)),
+        transfer_at(12, B256::repeat_byte(2)),
+    ];
+
+    let blocks =
+        collect_block_hashes(&events, (15, B256::repeat_byte(3))).expect("hashes are consistent");
+
+    assert_eq!(
+        blocks.into_iter().collect::<Vec<_>>(),
+        vec![
+            (10, B256::repeat_byte(1)),
+            (12, B256::repeat_byte(2)),
+            (15, B256::repeat_byte(3)),
+        ]
+    );
+}
+
+#[test]
+fn rejects_logs_from_two_forks() {
+    let events = [
+        transfer_at(10, B256::repeat_byte(1)),
+        transfer_at(15, B256::repeat_byte(2)),
+    ];
+
+    // the range's last header came from a different fork than its logs
+    assert_eq!(
+        collect_block_hashes(&events, (15, B256::repeat_byte(3))),
+        None
+    );
+}
@@ -0,0 +1,202 @@
+//! tests/jobs.rs
+use alloy::primitives::{Address, U256};
+use coco::errors::CocoError;
+use coco::services::batch::check_chunks;
+use coco::services::jobs::{CSV_HEADER, csv_names, csv_row, job_names, ndjson_row};
+use coco::types::api::{CheckNameResponse, NameResult};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn results() -> Vec<NameResult> {
+    vec![
+        NameResult {
+            name: "free.eth".to_string(),
+            available: true,
+            owner: None,
+            expiry_date: None,
+            base_cost: Some("3125000000000000".to_string()),
+            premium: Some("0".to_string()),
+        },
+        NameResult {
+            name: "taken.eth".to_string(),
+            available: false,
+            owner: Some(Address::repeat_byte(0x11)),
+            expiry_date: Some("2030-01-01T00:00:00+00:00".to_string()),
+            base_cost: None,
+            premium: None,
+        },
+    ]
+}
+
+#[test]
+fn csv_names_reads_the_first_column() {
+    let upload = b"name,note\nalice.eth,x\n\n  bob \n\"carol,eth\"\n";
+    assert_eq!(
+        csv_names(upload).unwrap(),
+        vec![
+            "alice.eth".to_string(),
+            "bob".to_string(),
+            "carol,eth".to_string()
+        ]
+    );
+
+    // no header, just names
+    assert_eq!(
+        csv_names(b"alice\nbob").unwrap(),
+        vec!["alice".to_string(), "bob".to_string()]
+    );
+}
+
+#[test]
+fn results_serialise_as_csv_and_ndjson() {
+    let csv: String = CSV_HEADER.to_string() + &results().iter().map(csv_row).collect::<String>();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "name,available,owner,expiry_date,base_cost,premium"
+    );
+    assert_eq!(lines[1], "free.eth,true,,,3125000000000000,0");
+    assert!(lines[2].starts_with("taken.eth,false,0x1111"));
+    assert_eq!(lines.len(), 3);
+
+    let ndjson: String = results().iter().map(ndjson_row).collect();
+    let rows: Vec<serde_json::Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["name"], "free.eth");
+    assert_eq!(rows[1]["expiryDate"], "2030-01-01T00:00:00+00:00");
+}
+
+#[test]
+fn job_names_are_normalised_and_deduplicated() {
+    let upload = ["Alice", "alice.eth", " ", "bob"].map(String::from);
+    assert_eq!(
+        job_names(&upload, "eth", 10).unwrap(),
+        vec!["alice.eth".to_string(), "bob.eth".to_string()]
+    );
+
+    assert!(matches!(
+        job_names(&upload, "eth", 3),
+        Err(CocoError::TooManyNames {
+            requested: 4,
+            limit: 3
+        })
+    ));
+    assert!(matches!(
+        job_names(&["a.b.eth".to_string()], "eth", 10),
+        Err(CocoError::InvalidName(_))
+    ));
+    assert!(matches!(
+        job_names(&["".to_string()], "eth", 10),
+        Err(CocoError::InvalidUpload(_))
+    ));
+}
+
+fn checked(names: &[String]) -> Vec<CheckNameResponse> {
+    names
+        .iter()
+        .map(|name| CheckNameResponse {
+            name: name.clone(),
+            available: true,
+            price: None,
+            owner: None,
+            expires: Some(U256::ZERO),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn worker_resumes_in_chunks_and_retries_an_unreachable_node() {
+    let names: Vec<String> = (0..7).map(|i| format!("n{}.eth", i)).collect();
+    let calls = Arc::new(Mutex::new(0));
+    let saved = Arc::new(Mutex::new(Vec::new()));
+
+    let result = check_chunks(
+        &names,
+        2,
+        2,
+        Duration::ZERO,
+        |chunk| {
+            let calls = calls.clone();
+            async move {
+                *calls.lock().unwrap() += 1;
+                // the first try at each chunk times out
+                if *calls.lock().unwrap() % 2 == 1 {
+                    return Err(CocoError::RpcTimeout);
+                }
+                Ok(checked(&chunk))
+            }
+        },
+        |start, chunk, rows| {
+            let saved = saved.clone();
+            async move {
+                assert_eq!(rows.len(), chunk.len());
+                saved.lock().unwrap().push((start, chunk));
+                Ok(())
+            }
+        },
+    )
+    .await;
+
+    assert!(result.is_ok());
+    let saved = saved.lock().unwrap();
+    // already checked names are skipped, the last chunk is short
+    assert_eq!(
+        saved.iter().map(|(start, _)| *start).collect::<Vec<_>>(),
+        vec![2, 4, 6]
+    );
+    assert_eq!(saved[2].1, vec!["n6.eth".to_string()]);
+    assert_eq!(*calls.lock().unwrap(), 6);
+}
+
+#[tokio::test]
+async fn worker_stops_at_the_first_lasting_error() {
+    let names: Vec<String> = (0..4).map(|i| format!("n{}.eth", i)).collect();
+    let saved = Arc::new(Mutex::new(Vec::new()));
+
+    let result = check_chunks(
+        &names,
+        0,
+        2,
+        Duration::ZERO,
+        |chunk| async move {
+            if chunk[0] == "n2.eth" {
+                return Err(CocoError::InvalidQueryInput);
+            }
+            Ok(checked(&chunk))
+        },
+        |start, _, _| {
+            let saved = saved.clone();
+            async move {
+                saved.lock().unwrap().push(start);
+                Ok(())
+            }
+        },
+    )
+    .await;
+
+    assert!(matches!(result, Err(CocoError::InvalidQueryInput)));
+    assert_eq!(*saved.lock().unwrap(), vec![0]);
+
+    // an unreachable node is given up on after three tries
+    let tries = Arc::new(Mutex::new(0));
+    let result = check_chunks(
+        &names,
+        0,
+        2,
+        Duration::ZERO,
+        |_| {
+            let tries = tries.clone();
+            async move {
+                *tries.lock().unwrap() += 1;
+                Err::<Vec<CheckNameResponse>, _>(CocoError::RpcTimeout)
+            }
+        },
+        |_, _, _| async { Ok(()) },
+    )
+    .await;
+    assert!(matches!(result, Err(CocoError::RpcTimeout)));
+    assert_eq!(*tries.lock().unwrap(), 3);
+}
@@ -0,0 +1,86 @@
+//! tests/labels.rs
+use alloy::primitives::{Address, B256, U256, keccak256};
+use coco::services::indexer::{IndexedLog, NameEvent, WrapperEvent, event_labels};
+use coco::services::labels::{LabelQueue, display_name, remember_names, wordlist_labels};
+
+fn event(event: NameEvent) -> IndexedLog {
+    IndexedLog {
+        block_number: 1,
+        block_hash: B256::ZERO,
+        block_timestamp: None,
+        tx_hash: B256::ZERO,
+        log_index: 0,
+        event,
+    }
+}
+
+#[test]
+fn shows_unknown_labels_as_their_hash() {
+    let label_hash = keccak256("alice");
+
+    assert_eq!(display_name(Some("alice"), label_hash, "eth"), "alice.eth");
+    assert_eq!(
+        display_name(None, label_hash, "base.eth"),
+        "[9c0257114eb9399a2985f8e75dad7600c5d89fe3824ffa99ec1c3eb8bf3b0501].base.eth"
+    );
+}
+
+#[test]
+fn reads_labels_from_a_wordlist() {
+    let text = "# common words\nAlice\n\n  bob  \nsub.name\n🦊\n";
+
+    assert_eq!(wordlist_labels(text), vec!["alice", "bob", "🦊"]);
+}
+
+#[test]
+fn collects_labels_from_controller_and_wrapper_events() {
+    let events = [
+        event(NameEvent::ControllerRegistered {
+            label: "alice".to_string(),
+            label_hash: keccak256("alice"),
+            owner: Address::ZERO,
+            base_cost: U256::ZERO,
+            premium: U256::ZERO,
+        }),
+        event(NameEvent::Wrapper(WrapperEvent {
+            node: B256::ZERO,
+            kind: "wrapped",
+            name: Some("pay.bob.eth".to_string()),
+            owner: None,
+            fuses: None,
+            expires: None,
+        })),
+        event(NameEvent::Renewed {
+            label_hash: keccak256("carol"),
+            expires: 0,
+        }),
+    ];
+
+    assert_eq!(event_labels(&events), vec!["alice", "bob", "eth", "pay"]);
+}
+
+#[test]
+fn queried_names_are_queued_once_and_dropped_when_full() {
+    let (queue, mut rx) = LabelQueue::new();
+    let names = ["Alice", "alice.eth", "bob", "a.b.eth", " "].map(String::from);
+
+    remember_names(&queue, "eth", &names);
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        vec!["alice".to_string(), "bob".to_string()]
+    );
+
+    // nothing valid, nothing queued
+    remember_names(&queue, "eth", &["a.b.eth".to_string()]);
+    assert!(rx.try_recv().is_err());
+
+    // a full queue drops new batches instead of waiting
+    let mut queued = 0;
+    for _ in 0..1000 {
+        remember_names(&queue, "eth", &names);
+    }
+    while rx.try_recv().is_ok() {
+        queued += 1;
+    }
+    assert!(queued < 1000);
+}
@@ -0,0 +1,25 @@
+//! tests/metadata.rs
+use coco::services::metadata::{character_set, render_card};
+
+#[test]
+fn character_sets() {
+    assert_eq!(character_set("123"), "digit");
+    assert_eq!(character_set("abc"), "letter");
+    assert_eq!(character_set("abc123"), "alphanumeric");
+    assert_eq!(character_set("a-b"), "mixed");
+    assert_eq!(character_set("🔥"), "unicode");
+}
+
+#[test]
+fn cards_escape_and_shorten_names() {
+    let card = render_card("<a&b>.eth", false);
+    assert!(card.starts_with("<svg"));
+    assert!(card.contains("&lt;a&amp;b&gt;.eth"));
+    assert!(!card.contains("Expired"));
+
+    let long = format!("{}.eth", "a".repeat(60));
+    let card = render_card(&long, true);
+    assert!(card.contains(&format!("{}…", "a".repeat(39))));
+    assert!(!card.contains(&long));
+    assert!(card.contains("Expired"));
+}
@@ -0,0 +1,101 @@
+//! tests/multicall.rs
+use coco::config::MulticallConfig;
+use coco::services::multicall::{Multicall, chunked, in_background};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+fn config(chunk_size: usize, concurrency: usize) -> Multicall {
+    Multicall::new(MulticallConfig {
+        chunk_size,
+        concurrency,
+        background_concurrency: 1,
+        max_names: 1_000,
+    })
+}
+
+#[tokio::test]
+async fn chunks_are_merged_in_input_order() {
+    let items: Vec<u64> = (0..25).collect();
+
+    // earlier chunks finish last, order must still be preserved
+    let out = chunked(&items, &config(4, 8), |chunk| async move {
+        let delay = 30 - chunk[0];
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        Ok(chunk.iter().map(|n| n * 2).collect())
+    })
+    .await
+    .expect("chunked call failed");
+
+    assert_eq!(out, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn empty_input_makes_no_calls() {
+    let items: Vec<u64> = Vec::new();
+
+    let out: Vec<u64> = chunked(&items, &config(4, 2), |_| async {
+        panic!("no chunks expected")
+    })
+    .await
+    .expect("chunked call failed");
+
+    assert!(out.is_empty());
+}
+
+#[tokio::test]
+async fn concurrency_is_shared_across_batches() {
+    let multicall = config(1, 2);
+    let items: Vec<u64> = (0..6).collect();
+    let in_flight = AtomicUsize::new(0);
+    let peak = AtomicUsize::new(0);
+
+    let batch = || {
+        chunked(&items, &multicall, |chunk| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(chunk.to_vec())
+            }
+        })
+    };
+
+    // two lookups at once still only get two chunks in flight between them
+    let (a, b) = tokio::join!(batch(), batch());
+    assert_eq!(a.unwrap(), items);
+    assert_eq!(b.unwrap(), items);
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn background_batches_have_their_own_permits() {
+    let multicall = config(1, 1);
+    let items: Vec<u64> = (0..4).collect();
+    let started = Instant::now();
+
+    let slow = |chunk: &[u64]| {
+        let chunk = chunk.to_vec();
+        async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(chunk)
+        }
+    };
+
+    // a background batch holding its permit doesn't hold up an interactive one
+    let (background, interactive) =
+        tokio::join!(in_background(chunked(&items, &multicall, slow)), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let out = chunked(&items[..1], &multicall, |chunk| async move {
+                Ok(chunk.to_vec())
+            })
+            .await;
+            (out, started.elapsed())
+        });
+
+    assert_eq!(background.unwrap(), items);
+    assert_eq!(interactive.0.unwrap(), vec![0]);
+    assert!(interactive.1 < Duration::from_millis(150));
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
@@ -0,0 +1,25 @@
+//! tests/normalise.rs
+use coco::services::ens::{normalise_and_hash_name, normalise_and_hash_name_for};
+
+#[test]
+fn bare_labels_get_the_network_tld() {
+    let eth = normalise_and_hash_name(" Alice ").expect("valid name");
+    let base = normalise_and_hash_name_for("alice", "base.eth").expect("valid name");
+
+    assert_eq!(eth.name, "alice.eth");
+    assert_eq!(base.name, "alice.base.eth");
+    // the registrar token is keyed by label so both share a labelhash
+    assert_eq!(eth.label_hash, base.label_hash);
+    assert_ne!(eth.name_hash, base.name_hash);
+
+    // stored names keep their tld, only the network's own is stripped
+    let stored = normalise_and_hash_name_for("alice.base.eth", "base.eth").expect("valid name");
+    assert_eq!(stored.label_hash, base.label_hash);
+}
+
+#[test]
+fn subnames_and_empty_labels_are_rejected() {
+    assert!(normalise_and_hash_name("pay.alice.eth").is_err());
+    assert!(normalise_and_hash_name(".eth").is_err());
+    assert!(normalise_and_hash_name_for("pay.alice", "base.eth").is_err());
+}
@@ -0,0 +1,93 @@
+//! tests/pattern.rs
+use coco::errors::CocoError;
+use coco::services::pattern::{EMOJI_SET, Pattern};
+
+fn expand(pattern: &str, palindrome: bool) -> Vec<String> {
+    Pattern::parse(pattern)
+        .unwrap()
+        .expand(palindrome, 1_000_000)
+        .unwrap()
+}
+
+#[test]
+fn expands_the_usual_clubs() {
+    let digits = expand("DDD", false);
+    assert_eq!(digits.len(), 1_000);
+    assert_eq!(digits.first().map(String::as_str), Some("000"));
+    assert_eq!(digits.last().map(String::as_str), Some("999"));
+
+    assert_eq!(expand("D{4}", false).len(), 10_000);
+    assert_eq!(expand("LLL", false).len(), 26 * 26 * 26);
+    assert_eq!(expand("E", false).len(), EMOJI_SET.len());
+}
+
+#[test]
+fn expands_sets_literals_and_repeat_ranges() {
+    assert_eq!(
+        expand("0x[a-c]", false),
+        vec!["0xa".to_string(), "0xb".to_string(), "0xc".to_string()]
+    );
+    // shortest first, a dash at the end of a set is literal
+    assert_eq!(
+        expand("[7-]{1,2}", false),
+        vec!["7", "-", "77", "7-", "-7", "--"]
+    );
+}
+
+#[test]
+fn mirrors_palindromes() {
+    let palindromes = expand("D{5}", true);
+    assert_eq!(palindromes.len(), 1_000);
+    assert!(palindromes.contains(&"12321".to_string()));
+    assert!(
+        palindromes
+            .iter()
+            .all(|p| p.chars().rev().collect::<String>() == *p)
+    );
+
+    // positions only keep what their mirror allows
+    let mirrored = expand("[ab]L[bc]", true);
+    assert_eq!(mirrored.len(), 26);
+    assert!(
+        mirrored
+            .iter()
+            .all(|p| p.starts_with('b') && p.ends_with('b'))
+    );
+}
+
+#[test]
+fn rejects_bad_or_oversized_patterns() {
+    for pattern in [
+        "", "DX", "[a-", "D{3", "{2}", "D{3,1}", "a.b", "[z-a]", "D{99}",
+    ] {
+        assert!(
+            matches!(Pattern::parse(pattern), Err(CocoError::InvalidPattern(_))),
+            "{pattern} should be rejected"
+        );
+    }
+
+    let too_many = Pattern::parse("LLLL").unwrap().expand(false, 100_000);
+    assert!(matches!(
+        too_many,
+        Err(CocoError::TooManyNames {
+            requested: 456_976,
+            limit: 100_000
+        })
+    ));
+}
+
+#[test]
+fn wide_sets_are_sized_before_they_are_built() {
+    // every code point past ¡ would be over a million strings
+    assert!(matches!(
+        Pattern::parse("[¡-\u{10FFFF}]"),
+        Err(CocoError::InvalidPattern(msg)) if msg.contains("sets are limited")
+    ));
+    // the budget covers the whole pattern, not each set
+    assert!(Pattern::parse("[一-龥][一-龥][一-龥][一-龥]").is_err());
+
+    let cjk = Pattern::parse("[一-龥]").unwrap();
+    assert_eq!(cjk.count(false), 20_902);
+    assert_eq!(cjk.count(true), 20_902);
+    assert_eq!(Pattern::parse("LLLL").unwrap().count(false), 456_976);
+}
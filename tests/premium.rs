@@ -0,0 +1,40 @@
+//! tests/premium.rs
+use coco::services::premium::{
+    PREMIUM_DECAY_DAYS, START_PREMIUM_USD, premium_usd_at, secs_until_price_below,
+};
+
+const DAY: i64 = 86_400;
+
+#[test]
+fn premium_halves_every_day_and_ends_at_zero() {
+    let start = premium_usd_at(0);
+    let one_day = premium_usd_at(DAY);
+
+    assert!(start > START_PREMIUM_USD * 0.99);
+    assert!((one_day - START_PREMIUM_USD / 2.0).abs() < 100.0);
+    assert_eq!(premium_usd_at(PREMIUM_DECAY_DAYS as i64 * DAY), 0.0);
+}
+
+#[test]
+fn trigger_lands_where_the_curve_crosses_the_threshold() {
+    let base = 5.0;
+    let threshold = 1_000.0;
+
+    let secs = secs_until_price_below(threshold, base).expect("threshold above base");
+
+    assert!(base + premium_usd_at(secs) <= threshold);
+    assert!(base + premium_usd_at(secs - 60) > threshold);
+}
+
+#[test]
+fn threshold_below_base_never_triggers() {
+    assert_eq!(secs_until_price_below(4.0, 5.0), None);
+}
+
+#[test]
+fn threshold_above_start_premium_triggers_on_release() {
+    assert_eq!(
+        secs_until_price_below(START_PREMIUM_USD * 2.0, 5.0),
+        Some(0)
+    );
+}
@@ -0,0 +1,84 @@
+//! tests/proof.rs
+//!
+//! The fixture is an eth_getProof response for the mainnet registrar layout,
+//! built from a local state trie holding alice.eth (registered) and a few
+//! dozen other names. bob.eth was never registered so its slots are proven
+//! by exclusion.
+use alloy::primitives::{Address, B256, U256, address, keccak256};
+use alloy::rpc::types::EIP1186AccountProofResponse;
+use coco::errors::CocoError;
+use coco::services::proof::{ProvenSlots, verify_registrar_proof};
+use coco::types::ens::RegistrarStorageLayout;
+use serde::Deserialize;
+
+const REGISTRAR: Address = address!("0x57f1887a8BF19b14fC0dF6Fd9B2acc9Af147eA85");
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fixture {
+    state_root: B256,
+    proof: EIP1186AccountProofResponse,
+}
+
+fn fixture() -> Fixture {
+    serde_json::from_str(include_str!("fixtures/registrar_proof.json"))
+        .expect("Failed to parse proof fixture")
+}
+
+fn token_id(label: &str) -> U256 {
+    U256::from_be_bytes(*keccak256(label))
+}
+
+fn verify(fixture: &Fixture, labels: &[&str]) -> Result<Vec<ProvenSlots>, CocoError> {
+    let ids: Vec<U256> = labels.iter().map(|l| token_id(l)).collect();
+    verify_registrar_proof(
+        fixture.state_root,
+        REGISTRAR,
+        RegistrarStorageLayout::mainnet(),
+        &ids,
+        &fixture.proof,
+    )
+}
+
+#[test]
+fn proves_registered_and_unregistered_names() {
+    let proven = verify(&fixture(), &["alice", "bob"]).expect("proof should verify");
+
+    assert_eq!(proven[0].expires, U256::from(1_900_000_000u64));
+    assert_eq!(
+        proven[0].owner,
+        address!("0x000000000000000000000000000000000000a11c")
+    );
+    assert_eq!(proven[1].expires, U256::ZERO);
+    assert_eq!(proven[1].owner, Address::ZERO);
+}
+
+#[test]
+fn rejects_tampered_storage_values() {
+    let mut fixture = fixture();
+    fixture.proof.storage_proof[0].value = U256::from(2_000_000_000u64);
+
+    assert!(matches!(
+        verify(&fixture, &["alice"]),
+        Err(CocoError::InvalidProof(_))
+    ));
+}
+
+#[test]
+fn rejects_proofs_against_another_state_root() {
+    let mut fixture = fixture();
+    fixture.state_root = keccak256("some other block");
+
+    assert!(matches!(
+        verify(&fixture, &["alice"]),
+        Err(CocoError::InvalidProof(_))
+    ));
+}
+
+#[test]
+fn rejects_names_missing_from_the_proof() {
+    assert!(matches!(
+        verify(&fixture(), &["carol"]),
+        Err(CocoError::InvalidProof(_))
+    ));
+}
@@ -0,0 +1,176 @@
+//! tests/provider_pool.rs
+use alloy::providers::Provider;
+use coco::config::{MulticallConfig, RpcPoolConfig};
+use coco::errors::CocoError;
+use coco::services::multicall::{Multicall, chunked};
+use coco::services::provider_pool::{CircuitState, ProviderPool};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// nothing listens here, so every request is a transport error
+const DEAD_RPC: &str = "http://127.0.0.1:1/v2/secret-key";
+
+fn config() -> RpcPoolConfig {
+    RpcPoolConfig {
+        failure_threshold: 2,
+        circuit_cooldown_secs: 60,
+        health_check_interval_secs: 15,
+        health_check_timeout_secs: 1,
+        quorum_size: 2,
+        request_timeout_secs: 5,
+        max_retries: 0,
+        retry_base_delay_ms: 0,
+    }
+}
+
+async fn block_number(pool: &ProviderPool) -> Result<u64, CocoError> {
+    pool.execute(|provider| async move { Ok(provider.get_block_number().await?) })
+        .await
+}
+
+#[tokio::test]
+async fn failing_endpoints_trip_the_circuit_breaker() {
+    let urls = vec![DEAD_RPC.to_string(), DEAD_RPC.to_string()];
+    let pool = ProviderPool::connect("test", &urls, config())
+        .await
+        .expect("Failed to build pool");
+
+    // first request fails over across both endpoints, second trips both breakers
+    assert!(block_number(&pool).await.unwrap_err().is_transport_error());
+    assert!(block_number(&pool).await.is_err());
+
+    let diagnostics = pool.diagnostics();
+    assert!(diagnostics.iter().all(|d| d.circuit == CircuitState::Open));
+    assert!(diagnostics.iter().all(|d| !d.endpoint.contains("secret")));
+
+    assert!(matches!(
+        block_number(&pool).await,
+        Err(CocoError::NoHealthyProvider(_))
+    ));
+}
+
+#[tokio::test]
+async fn non_transport_errors_are_not_retried() {
+    let urls = vec![DEAD_RPC.to_string(), DEAD_RPC.to_string()];
+    let pool = ProviderPool::connect("test", &urls, config())
+        .await
+        .expect("Failed to build pool");
+
+    let result: Result<(), CocoError> = pool
+        .execute(|_| async { Err(CocoError::InvalidQueryInput) })
+        .await;
+
+    assert!(matches!(result, Err(CocoError::InvalidQueryInput)));
+    let requests: u64 = pool.diagnostics().iter().map(|d| d.requests).sum();
+    assert_eq!(requests, 1);
+}
+
+#[tokio::test]
+async fn quorum_needs_enough_closed_circuits() {
+    let urls = vec![DEAD_RPC.to_string(), DEAD_RPC.to_string()];
+    let pool = ProviderPool::connect("test", &urls, config())
+        .await
+        .expect("Failed to build pool");
+
+    assert_eq!(
+        pool.quorum_providers().expect("both circuits closed").len(),
+        2
+    );
+
+    // trip both breakers
+    let _ = block_number(&pool).await;
+    let _ = block_number(&pool).await;
+
+    assert!(matches!(
+        pool.quorum_providers(),
+        Err(CocoError::QuorumUnavailable {
+            required: 2,
+            available: 0
+        })
+    ));
+}
+
+#[tokio::test]
+async fn transient_errors_are_retried_with_backoff() {
+    let urls = vec![DEAD_RPC.to_string()];
+    let pool = ProviderPool::connect(
+        "test",
+        &urls,
+        RpcPoolConfig {
+            failure_threshold: 10,
+            max_retries: 2,
+            retry_base_delay_ms: 1,
+            ..config()
+        },
+    )
+    .await
+    .expect("Failed to build pool");
+    let calls = AtomicUsize::new(0);
+
+    let result: Result<(), CocoError> = pool
+        .execute(|_| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(CocoError::RateLimited)
+        })
+        .await;
+
+    assert!(matches!(result, Err(CocoError::RateLimited)));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn waiting_for_permits_is_not_an_endpoint_timeout() {
+    let urls = vec![DEAD_RPC.to_string()];
+    let pool = ProviderPool::connect(
+        "test",
+        &urls,
+        RpcPoolConfig {
+            request_timeout_secs: 1,
+            ..config()
+        },
+    )
+    .await
+    .expect("Failed to build pool");
+    let multicall = Multicall::new(MulticallConfig {
+        chunk_size: 1,
+        concurrency: 1,
+        background_concurrency: 1,
+        max_names: 10,
+    });
+    let items = [1u64];
+
+    // another lookup holds the only permit for longer than the timeout
+    let busy = chunked(&items, &multicall, |chunk| async move {
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+        Ok(chunk.to_vec())
+    });
+    let queued = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        pool.execute(|_| async {
+            Ok(chunked(
+                &items,
+                &multicall,
+                |chunk| async move { Ok(chunk.to_vec()) },
+            )
+            .await?)
+        })
+        .await
+    };
+    let (_, result) = tokio::join!(busy, queued);
+
+    assert_eq!(result.unwrap(), vec![1]);
+    let diagnostics = pool.diagnostics();
+    assert_eq!(diagnostics[0].failures, 0);
+    assert_eq!(diagnostics[0].circuit, CircuitState::Closed);
+    assert!(diagnostics[0].latency_ms.unwrap() < 500.0);
+
+    // a slow endpoint still times out
+    let result: Result<(), CocoError> = pool
+        .execute(|_| async {
+            tokio::time::sleep(Duration::from_millis(1_200)).await;
+            Ok(())
+        })
+        .await;
+    assert!(matches!(result, Err(CocoError::RpcTimeout)));
+    assert_eq!(pool.diagnostics()[0].failures, 1);
+}
@@ -0,0 +1,15 @@
+//! tests/registrations.rs
+use coco::services::registrations::EventPosition;
+
+#[test]
+fn event_position_round_trips_and_orders() {
+    let earlier = EventPosition {
+        block_number: 21_000_000,
+        log_index: 312,
+    };
+    let later = EventPosition::decode("21000001-4").unwrap();
+
+    assert_eq!(EventPosition::decode(&earlier.encode()).unwrap(), earlier);
+    assert!(later > earlier);
+    assert!(EventPosition::decode("21000001").is_err());
+}
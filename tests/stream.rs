@@ -0,0 +1,143 @@
+//! tests/stream.rs
+use actix_web::body::to_bytes;
+use actix_web::test::TestRequest;
+use alloy::primitives::B256;
+use coco::errors::CocoError;
+use coco::routes::stream::{StreamFormat, stream_event, stream_rows};
+use coco::types::api::{AtBlock, BlockContext, BlockSelector};
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn accept_header_picks_the_format() {
+    let sse = TestRequest::default()
+        .insert_header(("Accept", "text/event-stream"))
+        .to_http_request();
+    assert_eq!(StreamFormat::from_request(&sse), StreamFormat::Sse);
+
+    let plain = TestRequest::default().to_http_request();
+    assert_eq!(StreamFormat::from_request(&plain), StreamFormat::Ndjson);
+}
+
+#[test]
+fn events_are_framed_per_format() {
+    let row = json!({ "name": "alice.eth", "isAvailable": false });
+
+    assert_eq!(
+        String::from_utf8(stream_event(StreamFormat::Ndjson, "result", &row)).unwrap(),
+        "{\"isAvailable\":false,\"name\":\"alice.eth\"}\n"
+    );
+    assert_eq!(
+        String::from_utf8(stream_event(StreamFormat::Sse, "result", &row)).unwrap(),
+        "event: result\ndata: {\"isAvailable\":false,\"name\":\"alice.eth\"}\n\n"
+    );
+}
+
+fn names(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("n{}.eth", i)).collect()
+}
+
+// every chunk answers from block 100 unless it was pinned elsewhere
+fn fake_block(at: BlockSelector) -> BlockContext {
+    let number = match at {
+        BlockSelector::Number(number) => number,
+        _ => 100,
+    };
+    BlockContext {
+        number,
+        hash: B256::ZERO,
+        timestamp: number * 12,
+    }
+}
+
+async fn ndjson_lines(
+    names: Vec<String>,
+    fail_on: Option<&'static str>,
+) -> (Vec<Value>, Vec<BlockSelector>) {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let calls = seen.clone();
+    let req = TestRequest::default().to_http_request();
+    let resp = stream_rows(
+        &req,
+        names,
+        2,
+        3,
+        BlockSelector::Latest,
+        move |names, at| {
+            calls.borrow_mut().push(at);
+            async move {
+                if fail_on.is_some_and(|name| names.iter().any(|n| n == name)) {
+                    return Err(CocoError::RpcTimeout);
+                }
+                Ok(AtBlock {
+                    block: fake_block(at),
+                    rows: names,
+                })
+            }
+        },
+    );
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let lines = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let seen = seen.borrow().clone();
+    (lines, seen)
+}
+
+#[actix_web::test]
+async fn later_chunks_are_pinned_to_the_first_block() {
+    let (lines, seen) = ndjson_lines(names(7), None).await;
+
+    assert_eq!(seen[0], BlockSelector::Latest);
+    assert_eq!(seen.len(), 4);
+    assert!(seen[1..].iter().all(|at| *at == BlockSelector::Number(100)));
+
+    let mut rows: Vec<&str> = lines[..7].iter().map(|v| v.as_str().unwrap()).collect();
+    rows.sort();
+    assert_eq!(
+        rows,
+        names(7).iter().map(String::as_str).collect::<Vec<_>>()
+    );
+}
+
+#[actix_web::test]
+async fn the_summary_comes_last() {
+    let (lines, _) = ndjson_lines(names(5), None).await;
+
+    assert_eq!(lines.len(), 6);
+    let summary = &lines[5]["summary"];
+    assert_eq!(summary["blockNumber"], 100);
+    assert_eq!(summary["blockTimestamp"], 1200);
+    assert_eq!(summary["requested"], 5);
+    assert_eq!(summary["returned"], 5);
+    assert!(summary.get("error").is_none());
+
+    let (lines, seen) = ndjson_lines(Vec::new(), None).await;
+    assert!(seen.is_empty());
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["summary"]["requested"], 0);
+}
+
+#[actix_web::test]
+async fn a_failed_chunk_ends_the_stream_with_its_error() {
+    let (lines, _) = ndjson_lines(names(6), Some("n2.eth")).await;
+
+    let summary = &lines.last().unwrap()["summary"];
+    assert_eq!(summary["requested"], 6);
+    assert_eq!(summary["returned"], lines.len() - 1);
+    assert_eq!(summary["blockNumber"], 100);
+    assert!(summary["error"].is_object());
+    assert!(lines[..lines.len() - 1].iter().all(|v| v.is_string()));
+    assert!(!lines.iter().any(|v| v == "n2.eth"));
+
+    // the first chunk failing leaves nothing to pin to
+    let (lines, seen) = ndjson_lines(names(6), Some("n0.eth")).await;
+    assert_eq!(seen.len(), 1);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["summary"]["returned"], 0);
+    assert!(lines[0]["summary"]["blockNumber"].is_null());
+}
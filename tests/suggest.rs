@@ -0,0 +1,60 @@
+//! tests/suggest.rs
+use coco::services::suggest::{SuggestionKind, suggestion_candidates};
+
+fn labels_of(kind: SuggestionKind, candidates: &[(String, SuggestionKind)]) -> Vec<&str> {
+    candidates
+        .iter()
+        .filter(|(_, k)| *k == kind)
+        .map(|(label, _)| label.as_str())
+        .collect()
+}
+
+#[test]
+fn builds_candidates_from_every_pattern() {
+    let words = vec!["alicent".to_string(), "alices".to_string()];
+    let candidates = suggestion_candidates("alice", &words);
+
+    assert_eq!(
+        labels_of(SuggestionKind::Plural, &candidates),
+        vec!["alices"]
+    );
+    assert!(labels_of(SuggestionKind::Prefix, &candidates).contains(&"thealice"));
+    assert!(labels_of(SuggestionKind::Suffix, &candidates).contains(&"alicedao"));
+    assert!(labels_of(SuggestionKind::Numeral, &candidates).contains(&"alice420"));
+    assert!(labels_of(SuggestionKind::Emoji, &candidates).contains(&"alice🔥"));
+    // "alices" was already suggested as the plural
+    assert_eq!(
+        labels_of(SuggestionKind::Wordlist, &candidates),
+        vec!["alicent"]
+    );
+}
+
+#[test]
+fn pluralises_and_singularises() {
+    let plural = |label: &str| {
+        labels_of(SuggestionKind::Plural, &suggestion_candidates(label, &[]))
+            .first()
+            .map(|p| p.to_string())
+    };
+
+    assert_eq!(plural("party").as_deref(), Some("parties"));
+    assert_eq!(plural("key").as_deref(), Some("keys"));
+    assert_eq!(plural("box").as_deref(), Some("boxes"));
+    assert_eq!(plural("cats").as_deref(), Some("cat"));
+    assert_eq!(plural("stories").as_deref(), Some("story"));
+    assert_eq!(plural("🦊🦊🦊"), None);
+}
+
+#[test]
+fn skips_the_name_itself_and_short_labels() {
+    let words = vec!["ab".to_string(), "abc".to_string()];
+    let candidates = suggestion_candidates("ab", &words);
+
+    // "abs" is long enough, "ab" is the name and too short anyway
+    assert_eq!(labels_of(SuggestionKind::Plural, &candidates), vec!["abs"]);
+    assert_eq!(
+        labels_of(SuggestionKind::Wordlist, &candidates),
+        vec!["abc"]
+    );
+    assert!(candidates.iter().all(|(label, _)| label != "ab"));
+}